@@ -55,10 +55,10 @@ fn resolve_git_sha() -> String {
 }
 
 fn resolve_git_tag() -> String {
-    if let Ok(value) = env::var("PTYCTL_GIT_TAG") {
-        if !value.trim().is_empty() {
-            return value.trim().to_string();
-        }
+    if let Ok(value) = env::var("PTYCTL_GIT_TAG")
+        && !value.trim().is_empty()
+    {
+        return value.trim().to_string();
     }
 
     if !Path::new(".git").exists() {
@@ -69,12 +69,11 @@ fn resolve_git_tag() -> String {
         return tag;
     }
 
-    if let Some(tags) = git_output(&["tag", "--sort=-creatordate"]) {
-        if let Some(first) = tags.lines().next() {
-            if !first.trim().is_empty() {
-                return first.trim().to_string();
-            }
-        }
+    if let Some(tags) = git_output(&["tag", "--sort=-creatordate"])
+        && let Some(first) = tags.lines().next()
+        && !first.trim().is_empty()
+    {
+        return first.trim().to_string();
     }
 
     String::new()
@@ -108,19 +107,17 @@ fn normalize_git_sha(value: String) -> String {
 }
 
 fn resolve_build_time() -> String {
-    if let Ok(value) = env::var("PTYCTL_BUILD_TIME") {
-        if !value.trim().is_empty() {
-            return value;
-        }
-    }
-    if let Ok(value) = env::var("SOURCE_DATE_EPOCH") {
-        if let Ok(epoch) = value.trim().parse::<i64>() {
-            if let Ok(dt) = time::OffsetDateTime::from_unix_timestamp(epoch) {
-                if let Ok(text) = dt.format(&time::format_description::well_known::Rfc3339) {
-                    return text;
-                }
-            }
-        }
+    if let Ok(value) = env::var("PTYCTL_BUILD_TIME")
+        && !value.trim().is_empty()
+    {
+        return value;
+    }
+    if let Ok(value) = env::var("SOURCE_DATE_EPOCH")
+        && let Ok(epoch) = value.trim().parse::<i64>()
+        && let Ok(dt) = time::OffsetDateTime::from_unix_timestamp(epoch)
+        && let Ok(text) = dt.format(&time::format_description::well_known::Rfc3339)
+    {
+        return text;
     }
 
     time::OffsetDateTime::now_utc()
@@ -0,0 +1,244 @@
+use crate::error::{ApiError, ErrorCode, PtyResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+use tokio::time::{Duration, timeout};
+use uuid::Uuid;
+
+/// A command parked until an operator approves or rejects it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingApproval {
+    pub approval_id: String,
+    pub session_id: String,
+    pub host: String,
+    pub port: u16,
+    pub profile: Option<String>,
+    pub task_id: Option<String>,
+    pub action: String,
+    pub command: String,
+    pub rule: String,
+    pub pattern: String,
+    pub requested_at_ms: u64,
+    pub expires_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalsAction {
+    List,
+    Approve,
+    Reject,
+}
+
+/// Parameters of the `ptyctl_approvals` control-socket method.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalsRequest {
+    pub action: ApprovalsAction,
+    pub approval_id: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalsResponse {
+    pub action: ApprovalsAction,
+    pub pending: Option<Vec<PendingApproval>>,
+    pub decided: Option<PendingApproval>,
+}
+
+#[derive(Debug)]
+enum Decision {
+    Approved,
+    Rejected(Option<String>),
+}
+
+struct Waiter {
+    request: PendingApproval,
+    decide: oneshot::Sender<Decision>,
+}
+
+/// Pending approvals shared by every transport; the requesting call blocks until a
+/// decision arrives through the control socket or the timeout elapses.
+pub struct ApprovalQueue {
+    timeout_ms: AtomicU64,
+    pending: Mutex<HashMap<String, Waiter>>,
+}
+
+impl ApprovalQueue {
+    pub fn new(timeout_ms: u64) -> Self {
+        Self {
+            timeout_ms: AtomicU64::new(timeout_ms),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Applies to approvals requested from now on.
+    pub fn set_timeout_ms(&self, timeout_ms: u64) {
+        self.timeout_ms.store(timeout_ms, Ordering::Relaxed);
+    }
+
+    /// Parks `request` and waits for an operator decision.
+    pub async fn wait_for_decision(&self, mut request: PendingApproval) -> PtyResult<()> {
+        let approval_id = Uuid::new_v4().simple().to_string()[..8].to_string();
+        let timeout_ms = self.timeout_ms.load(Ordering::Relaxed);
+        let now = now_ms();
+        request.approval_id = approval_id.clone();
+        request.requested_at_ms = now;
+        request.expires_at_ms = now + timeout_ms;
+        let (decide, decision) = oneshot::channel();
+        tracing::info!(
+            approval_id = %approval_id,
+            session_id = %request.session_id,
+            rule = %request.rule,
+            "Command awaiting operator approval"
+        );
+        self.lock()
+            .insert(approval_id.clone(), Waiter { request, decide });
+        // Drop the entry even if the waiting call is cancelled.
+        let _cleanup = Cleanup {
+            queue: self,
+            approval_id: &approval_id,
+        };
+
+        let result = timeout(Duration::from_millis(timeout_ms), decision).await;
+        let denied = |message: String| ApiError::new(ErrorCode::PolicyDenied, message);
+        match result {
+            Ok(Ok(Decision::Approved)) => Ok(()),
+            Ok(Ok(Decision::Rejected(reason))) => {
+                let error = denied(format!(
+                    "Approval {approval_id} was rejected by an operator"
+                ));
+                Err(match reason {
+                    Some(reason) => error.with_details(reason),
+                    None => error,
+                }
+                .into())
+            }
+            Ok(Err(_)) => Err(denied(format!("Approval {approval_id} was abandoned")).into()),
+            Err(_) => Err(denied(format!(
+                "Approval {approval_id} timed out after {timeout_ms} ms"
+            ))
+            .into()),
+        }
+    }
+
+    pub fn list(&self) -> Vec<PendingApproval> {
+        let mut pending: Vec<_> = self
+            .lock()
+            .values()
+            .map(|waiter| waiter.request.clone())
+            .collect();
+        pending.sort_by_key(|request| request.requested_at_ms);
+        pending
+    }
+
+    pub fn decide(
+        &self,
+        approval_id: &str,
+        approve: bool,
+        reason: Option<String>,
+    ) -> PtyResult<PendingApproval> {
+        let waiter = self.lock().remove(approval_id).ok_or_else(|| {
+            ApiError::new(
+                ErrorCode::NotFound,
+                format!("Approval not found: {approval_id}"),
+            )
+        })?;
+        let decision = if approve {
+            Decision::Approved
+        } else {
+            Decision::Rejected(reason)
+        };
+        let _ = waiter.decide.send(decision);
+        Ok(waiter.request)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Waiter>> {
+        self.pending.lock().expect("approval queue poisoned")
+    }
+}
+
+struct Cleanup<'a> {
+    queue: &'a ApprovalQueue,
+    approval_id: &'a str,
+}
+
+impl Drop for Cleanup<'_> {
+    fn drop(&mut self) {
+        self.queue.lock().remove(self.approval_id);
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn request(command: &str) -> PendingApproval {
+        PendingApproval {
+            approval_id: String::new(),
+            session_id: "s1".to_string(),
+            host: "core-sw1".to_string(),
+            port: 22,
+            profile: None,
+            task_id: Some("agent".to_string()),
+            action: "exec".to_string(),
+            command: command.to_string(),
+            rule: "changes".to_string(),
+            pattern: "^reload".to_string(),
+            requested_at_ms: 0,
+            expires_at_ms: 0,
+        }
+    }
+
+    async fn next_pending(queue: &ApprovalQueue) -> PendingApproval {
+        loop {
+            if let Some(pending) = queue.list().into_iter().next() {
+                return pending;
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn approvals_unblock_reject_and_time_out() {
+        let queue = Arc::new(ApprovalQueue::new(5_000));
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.wait_for_decision(request("reload")).await }
+        });
+        let pending = next_pending(&queue).await;
+        assert_eq!(pending.command, "reload");
+        queue.decide(&pending.approval_id, true, None).unwrap();
+        assert!(waiting.await.unwrap().is_ok());
+        assert!(queue.decide(&pending.approval_id, true, None).is_err());
+
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.wait_for_decision(request("commit")).await }
+        });
+        let pending = next_pending(&queue).await;
+        queue
+            .decide(
+                &pending.approval_id,
+                false,
+                Some("change freeze".to_string()),
+            )
+            .unwrap();
+        let err = waiting.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("rejected"));
+
+        let quick = ApprovalQueue::new(20);
+        assert!(quick.wait_for_decision(request("reload")).await.is_err());
+        assert!(quick.list().is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::error::{ApiError, ErrorCode, PtyError, PtyResult};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditTransport {
+    Stdio,
+    Http,
+    Control,
+}
+
+/// Who is calling: the transport plus an identity (MCP `clientInfo`, control peer uid/pid).
+#[derive(Debug, Clone)]
+pub struct AuditClient {
+    pub transport: AuditTransport,
+    identity: Arc<OnceLock<String>>,
+    token: Option<String>,
+}
+
+impl AuditClient {
+    pub fn new(transport: AuditTransport, identity: Option<String>) -> Self {
+        let cell = OnceLock::new();
+        if let Some(identity) = identity {
+            let _ = cell.set(identity);
+        }
+        Self {
+            transport,
+            identity: Arc::new(cell),
+            token: None,
+        }
+    }
+
+    /// The same client acting with the named HTTP token.
+    pub fn with_token(&self, token: &str) -> Self {
+        Self {
+            token: Some(token.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// Records the identity once it becomes known (e.g. on MCP initialize).
+    pub fn identify(&self, identity: String) {
+        let _ = self.identity.set(identity);
+    }
+
+    pub fn identity(&self) -> Option<String> {
+        self.identity.get().cloned()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct AuditEvent {
+    pub event: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Set for writes the caller flagged `sensitive`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sensitive: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    ts_ms: u64,
+    transport: AuditTransport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<&'a str>,
+    #[serde(flatten)]
+    event: &'a AuditEvent,
+    outcome: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_code: Option<ErrorCode>,
+    duration_ms: u64,
+}
+
+/// Append-only JSON-lines audit log; a no-op when no path is configured.
+#[derive(Default)]
+pub struct AuditLog {
+    file: Option<Mutex<File>>,
+}
+
+impl AuditLog {
+    pub fn open(path: &str) -> PtyResult<Self> {
+        if path.is_empty() {
+            return Ok(Self::default());
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o600)
+            .open(path)
+            .map_err(|err| {
+                ApiError::new(ErrorCode::IoError, "Failed to open audit log")
+                    .with_details(err.to_string())
+            })?;
+        Ok(Self {
+            file: Some(Mutex::new(file)),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some()
+    }
+
+    pub fn record<T>(
+        &self,
+        client: &AuditClient,
+        event: &AuditEvent,
+        elapsed: Duration,
+        result: &PtyResult<T>,
+    ) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let error_code = match result {
+            Ok(_) => None,
+            Err(PtyError::Api(api)) => Some(api.error_code.clone()),
+            Err(_) => Some(ErrorCode::IoError),
+        };
+        let record = AuditRecord {
+            ts_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or_default(),
+            transport: client.transport,
+            client: client.identity(),
+            token: client.token.as_deref(),
+            event,
+            outcome: if error_code.is_none() { "ok" } else { "error" },
+            error_code,
+            duration_ms: elapsed.as_millis() as u64,
+        };
+        let Ok(mut line) = serde_json::to_vec(&record) else {
+            return;
+        };
+        line.push(b'\n');
+        let mut file = file.lock().expect("audit log poisoned");
+        if let Err(err) = file.write_all(&line) {
+            tracing::warn!(%err, "Failed to write audit event");
+        }
+    }
+
+    /// Forces recorded events to disk.
+    pub fn flush(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let file = file.lock().expect("audit log poisoned");
+        if let Err(err) = file.sync_data() {
+            tracing::warn!(%err, "Failed to flush audit log");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_one_json_line_per_event() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let log = AuditLog::open(path.to_str().unwrap()).unwrap();
+        let client = AuditClient::new(AuditTransport::Control, Some("uid=0".to_string()));
+        let event = AuditEvent {
+            event: "exec",
+            session_id: Some("s1".to_string()),
+            command: Some("show version".to_string()),
+            exit_code: Some(0),
+            ..AuditEvent::default()
+        };
+        log.record(&client, &event, Duration::from_millis(12), &Ok(()));
+        let failed: PtyResult<()> = Err(ApiError::new(ErrorCode::PolicyDenied, "no").into());
+        log.record(&client, &event, Duration::ZERO, &failed);
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["transport"], "control");
+        assert_eq!(lines[0]["client"], "uid=0");
+        assert_eq!(lines[0]["command"], "show version");
+        assert_eq!(lines[0]["duration_ms"], 12);
+        assert_eq!(lines[1]["outcome"], "error");
+        assert_eq!(lines[1]["error_code"], "POLICY_DENIED");
+        assert!(lines[1].get("host").is_none());
+    }
+}
@@ -0,0 +1,622 @@
+use crate::terminal;
+use crate::{ControlEndpoint, control_request, default_human_task_id, exit_on_control_error};
+use ptyctl::config::{
+    Config, ControlApproveArgs, ControlCloseArgs, ControlExecArgs, ControlLockArgs, ControlMode,
+    ControlOpenArgs, ControlReadArgs, ControlReloadArgs, ControlSendArgs, ControlUnlockArgs,
+    DoctorArgs, SecretsAction, SecretsArgs, ServeArgs, TokenAction, TokenArgs,
+};
+use ptyctl::doctor::{CheckStatus, DoctorCheck, run_checks};
+use ptyctl::error::{ApiError, ErrorCode, PtyError, PtyResult};
+use ptyctl::secrets::{load_secrets_file, save_secrets_file, secret_name};
+use ptyctl::tokens::{generate_token, hash_token};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Read, Write};
+use std::os::fd::AsRawFd;
+
+const EXIT_FAILURE: i32 = 1;
+const EXIT_NOT_FOUND: i32 = 2;
+const EXIT_NO_MATCH: i32 = 3;
+const EXIT_TIMEOUT: i32 = 124;
+
+pub async fn run_open(args: ControlOpenArgs) -> PtyResult<()> {
+    let endpoint = ControlEndpoint::new(args.control_socket.clone(), args.control_token.clone());
+    let mut params = json!({
+        "action": "open",
+        "profile": args.profile,
+        "protocol": args.protocol,
+        "host": args.host,
+        "port": args.port,
+        "username": args.username,
+        "session_type": args.session_type,
+        "device_id": args.device_id,
+        "acquire_lock": args.lock.then_some(true),
+        "task_id": args.task_id,
+    });
+    if let Some(auth) = open_auth(&args)? {
+        params["auth"] = auth;
+    }
+    if args.no_pty {
+        params["pty"] = json!({ "enabled": false });
+    }
+    let response = call(&endpoint, "ptyctl_session", params, args.json).await;
+    if args.json {
+        return print_json(&response);
+    }
+    if let Some(warning) = response.get("security_warning").and_then(|v| v.as_str()) {
+        eprintln!("Warning: {warning}");
+    }
+    if let Some(session_id) = response.get("session_id").and_then(|v| v.as_str()) {
+        println!("{session_id}");
+    }
+    Ok(())
+}
+
+pub async fn run_exec(args: ControlExecArgs) -> PtyResult<()> {
+    let endpoint = ControlEndpoint::new(args.control_socket, args.control_token);
+    let params = json!({
+        "session_id": args.session_id,
+        "cmd": command_line(&args.cmd),
+        "timeout_ms": args.timeout_ms,
+        "until_idle_ms": args.until_idle_ms,
+        "rc_mode": args.no_rc.then(|| json!({ "enabled": false })),
+        "task_id": args.task_id,
+    });
+    let response = call(&endpoint, "ptyctl_session_exec", params, args.json).await;
+    if args.json {
+        print_json(&response)?;
+    } else {
+        let stdout = response.get("stdout").and_then(|v| v.as_str());
+        let stderr = response.get("stderr").and_then(|v| v.as_str());
+        write_stream(&mut std::io::stdout().lock(), stdout.unwrap_or_default())?;
+        write_stream(&mut std::io::stderr().lock(), stderr.unwrap_or_default())?;
+    }
+    std::process::exit(exec_exit_code(&response));
+}
+
+pub async fn run_send(args: ControlSendArgs) -> PtyResult<()> {
+    let endpoint = ControlEndpoint::new(args.control_socket, args.control_token);
+    let mut responses = Vec::new();
+    if args.data.is_some() || args.key.is_some() || !args.enter {
+        let params = json!({
+            "action": "write",
+            "session_id": args.session_id.as_str(),
+            "data": args.data,
+            "key": args.key,
+            "sensitive": args.sensitive.then_some(true),
+            "task_id": args.task_id.as_deref(),
+        });
+        responses.push(call(&endpoint, "ptyctl_session_io", params, args.json).await);
+    }
+    if args.enter {
+        let params = json!({
+            "action": "write",
+            "session_id": args.session_id.as_str(),
+            "key": "enter",
+            "task_id": args.task_id.as_deref(),
+        });
+        responses.push(call(&endpoint, "ptyctl_session_io", params, args.json).await);
+    }
+    if args.json {
+        let bytes_written: u64 = responses
+            .iter()
+            .filter_map(|response| response.get("bytes_written").and_then(|v| v.as_u64()))
+            .sum();
+        return print_json(&json!({ "action": "write", "bytes_written": bytes_written }));
+    }
+    Ok(())
+}
+
+pub async fn run_read(args: ControlReadArgs) -> PtyResult<()> {
+    let endpoint = ControlEndpoint::new(args.control_socket, args.control_token);
+    let params = json!({
+        "action": "read",
+        "session_id": args.session_id,
+        "mode": if args.tail { "tail" } else { "cursor" },
+        "cursor": args.cursor,
+        "until_regex": args.until.as_deref(),
+        "timeout_ms": args.timeout_ms,
+        "until_idle_ms": args.until_idle_ms,
+        "max_bytes": args.max_bytes,
+        "encoding": "utf-8",
+    });
+    let response = call(&endpoint, "ptyctl_session_io", params, args.json).await;
+    if args.json {
+        print_json(&response)?;
+    } else if let Some(chunk) = response.get("chunk").and_then(|v| v.as_str()) {
+        write_stream(&mut std::io::stdout().lock(), chunk)?;
+    }
+    let matched = response.get("matched").and_then(|v| v.as_bool()) == Some(true);
+    if args.until.is_some() && !matched {
+        if !args.json {
+            eprintln!("Pattern not matched before the read returned.");
+        }
+        std::process::exit(EXIT_NO_MATCH);
+    }
+    Ok(())
+}
+
+pub async fn run_close(args: ControlCloseArgs) -> PtyResult<()> {
+    let endpoint = ControlEndpoint::new(args.control_socket, args.control_token);
+    let params = json!({
+        "action": "close",
+        "session_id": args.session_id,
+        "force": args.force.then_some(true),
+    });
+    let response = call(&endpoint, "ptyctl_session", params, args.json).await;
+    finish_session_action(&response, args.json)
+}
+
+pub async fn run_lock(args: ControlLockArgs) -> PtyResult<()> {
+    let endpoint = ControlEndpoint::new(args.control_socket, args.control_token);
+    let task_id = args.task_id.unwrap_or_else(default_human_task_id);
+    let params = json!({
+        "action": "lock",
+        "session_id": args.session_id,
+        "task_id": task_id,
+        "lock_ttl_ms": args.lock_ttl_ms,
+        "force": args.force.then_some(true),
+    });
+    let response = call(&endpoint, "ptyctl_session", params, args.json).await;
+    finish_session_action(&response, args.json)
+}
+
+pub async fn run_unlock(args: ControlUnlockArgs) -> PtyResult<()> {
+    let endpoint = ControlEndpoint::new(args.control_socket, args.control_token);
+    let task_id = args.task_id.unwrap_or_else(default_human_task_id);
+    let params = json!({
+        "action": "unlock",
+        "session_id": args.session_id,
+        "task_id": task_id,
+    });
+    let response = call(&endpoint, "ptyctl_session", params, args.json).await;
+    finish_session_action(&response, args.json)
+}
+
+pub async fn run_approve(args: ControlApproveArgs) -> PtyResult<()> {
+    let endpoint = ControlEndpoint::new(args.control_socket, args.control_token);
+    let params = match &args.approval_id {
+        None => json!({ "action": "list" }),
+        Some(approval_id) => json!({
+            "action": if args.reject { "reject" } else { "approve" },
+            "approval_id": approval_id,
+            "reason": args.reason,
+        }),
+    };
+    let response = call(&endpoint, "ptyctl_approvals", params, args.json).await;
+    if args.json {
+        return print_json(&response);
+    }
+    if let Some(decided) = response.get("decided") {
+        let verb = if args.reject { "Rejected" } else { "Approved" };
+        eprintln!(
+            "{verb} {}: {}",
+            decided["approval_id"].as_str().unwrap_or_default(),
+            decided["command"].as_str().unwrap_or_default()
+        );
+        return Ok(());
+    }
+    let pending = response["pending"].as_array().cloned().unwrap_or_default();
+    if pending.is_empty() {
+        eprintln!("No pending approvals.");
+        return Ok(());
+    }
+    println!(
+        "{:<8}  {:<12}  {:<21}  {:<12}  {:<14}  COMMAND",
+        "ID", "SESSION", "HOST", "TASK", "RULE"
+    );
+    for item in pending {
+        let text = |key: &str| item[key].as_str().unwrap_or("-").to_string();
+        let host = format!("{}:{}", text("host"), item["port"]);
+        let command = text("command").replace(['\r', '\n'], " ");
+        println!(
+            "{:<8}  {:<12}  {:<21}  {:<12}  {:<14}  {command}",
+            text("approval_id"),
+            text("session_id"),
+            host,
+            text("task_id"),
+            text("rule"),
+        );
+    }
+    Ok(())
+}
+
+pub async fn run_reload(args: ControlReloadArgs) -> PtyResult<()> {
+    let endpoint = ControlEndpoint::new(args.control_socket, args.control_token);
+    let response = call(&endpoint, "reload", Value::Null, args.json).await;
+    if args.json {
+        return print_json(&response);
+    }
+    let paths = |key: &str| {
+        response[key]
+            .as_array()
+            .map(|paths| {
+                paths
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default()
+    };
+    let applied = paths("applied");
+    let restart_required = paths("restart_required");
+    if applied.is_empty() && restart_required.is_empty() {
+        eprintln!("Config reloaded; nothing changed.");
+    }
+    if !applied.is_empty() {
+        eprintln!("Applied: {applied}");
+    }
+    if !restart_required.is_empty() {
+        eprintln!("Needs a restart: {restart_required}");
+    }
+    Ok(())
+}
+
+pub async fn run_doctor(args: DoctorArgs) -> PtyResult<()> {
+    let source = match &args.config {
+        Some(path) => path.display().to_string(),
+        None if std::path::Path::new("ptyctl.toml").exists() => "ptyctl.toml".to_string(),
+        None => "built-in defaults".to_string(),
+    };
+    let mut checks = Vec::new();
+    match Config::load(&ServeArgs {
+        config: args.config.clone(),
+        ..ServeArgs::default()
+    }) {
+        Ok(config) => {
+            checks.push(DoctorCheck::ok("config", source));
+            checks.extend(run_checks(&config));
+            let token = args.control_token.clone().or_else(|| {
+                let token = &config.server.control.control_auth_token;
+                (!token.is_empty()).then(|| token.clone())
+            });
+            let socket = args
+                .control_socket
+                .clone()
+                .unwrap_or(config.server.control.control_socket_path);
+            checks.push(check_running_server(ControlEndpoint::new(Some(socket), token)).await);
+        }
+        Err(err) => checks.push(DoctorCheck::fail(
+            "config",
+            format!("{source}: {err}"),
+            "Fix the config file (see docs/usage.md) and run `ptyctl doctor` again",
+        )),
+    }
+
+    let failed = checks.iter().any(|check| check.status == CheckStatus::Fail);
+    if args.json {
+        print_json(&serde_json::to_value(&checks)?)?;
+    } else {
+        for check in &checks {
+            let status = match check.status {
+                CheckStatus::Ok => "ok",
+                CheckStatus::Warn => "WARN",
+                CheckStatus::Fail => "FAIL",
+            };
+            println!("{status:<5} {:<16} {}", check.name, check.detail);
+            if let Some(fix) = &check.fix {
+                println!("{:<22} fix: {fix}", "");
+            }
+        }
+    }
+    if failed {
+        std::process::exit(EXIT_FAILURE);
+    }
+    Ok(())
+}
+
+async fn check_running_server(endpoint: ControlEndpoint) -> DoctorCheck {
+    match control_request(&endpoint, "server_info", json!({})).await {
+        Ok(info) => DoctorCheck::ok(
+            "server",
+            format!(
+                "ptyctl {} running (pid {}, up {}s, {} session(s))",
+                info["version"].as_str().unwrap_or_default(),
+                info["pid"],
+                info["uptime_ms"].as_u64().unwrap_or_default() / 1000,
+                info["sessions"],
+            ),
+        ),
+        Err(PtyError::Api(api)) if api.error_code == ErrorCode::AuthFailed => DoctorCheck::warn(
+            "server",
+            "control socket rejected the request",
+            "Pass --control-token or set PTYCTL_CONTROL_AUTH_TOKEN",
+        ),
+        Err(_) => DoctorCheck::ok("server", format!("not running on {}", endpoint.socket_path)),
+    }
+}
+
+pub fn run_secrets(args: SecretsArgs) -> PtyResult<()> {
+    let config = Config::load(&ServeArgs {
+        config: args.config.clone(),
+        ..ServeArgs::default()
+    })?;
+    let path = args
+        .file
+        .clone()
+        .or_else(|| {
+            (!config.secrets.file_path.is_empty()).then(|| config.secrets.file_path.clone().into())
+        })
+        .ok_or_else(|| {
+            ApiError::new(
+                ErrorCode::InvalidArgument,
+                "No secrets file: pass --file or set secrets.file_path",
+            )
+        })?;
+    let key = match std::env::var(&config.secrets.file_key_env) {
+        Ok(key) => key,
+        Err(_) => read_hidden(&format!(
+            "Secrets file key ({} is not set): ",
+            config.secrets.file_key_env
+        ))?,
+    };
+    let mut secrets = if path.exists() {
+        load_secrets_file(&path, &key)?
+    } else {
+        BTreeMap::new()
+    };
+    match args.action {
+        SecretsAction::List => {
+            for name in secrets.keys() {
+                println!("{name}");
+            }
+        }
+        SecretsAction::Set { name, from_file } => {
+            let name = secret_name(&name)?.to_string();
+            let value = match from_file {
+                Some(path) => {
+                    let value = std::fs::read_to_string(&path).map_err(|err| {
+                        ApiError::new(ErrorCode::InvalidArgument, "Failed to read --from-file")
+                            .with_details(err.to_string())
+                    })?;
+                    strip_final_newline(&value).to_string()
+                }
+                None if terminal::is_terminal(std::io::stdin().as_raw_fd()) => {
+                    read_hidden(&format!("Value for secret://{name}: "))?
+                }
+                None => {
+                    let mut value = String::new();
+                    std::io::stdin().read_to_string(&mut value)?;
+                    strip_final_newline(&value).to_string()
+                }
+            };
+            if value.is_empty() {
+                return Err(ApiError::new(ErrorCode::InvalidArgument, "Empty secret value").into());
+            }
+            secrets.insert(name, value);
+            save_secrets_file(&path, &key, &secrets)?;
+        }
+        SecretsAction::Rm { name } => {
+            let name = secret_name(&name)?;
+            if secrets.remove(name).is_none() {
+                return Err(ApiError::new(
+                    ErrorCode::NotFound,
+                    format!("Secret not found: secret://{name}"),
+                )
+                .into());
+            }
+            save_secrets_file(&path, &key, &secrets)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run_token(args: TokenArgs) -> PtyResult<()> {
+    match args.action {
+        TokenAction::New { name, mode } => {
+            let token = generate_token()?;
+            let mode = match mode {
+                ControlMode::Readwrite => "readwrite",
+                _ => "readonly",
+            };
+            println!("{token}");
+            eprintln!();
+            eprintln!("# Give the token above to the client; add this to the server config:");
+            eprintln!("[[server.http.tokens]]");
+            eprintln!("name = {}", json!(name));
+            eprintln!("token_sha256 = \"{}\"", hash_token(&token));
+            eprintln!("mode = \"{mode}\"");
+        }
+        TokenAction::Hash => {
+            let token = read_hidden("Token: ")?;
+            if token.is_empty() {
+                return Err(ApiError::new(ErrorCode::InvalidArgument, "Empty token").into());
+            }
+            println!("{}", hash_token(&token));
+        }
+    }
+    Ok(())
+}
+
+/// Drops the one line ending a file or pipe usually ends with, keeping the
+/// rest of a multi-line value intact.
+fn strip_final_newline(value: &str) -> &str {
+    let value = value.strip_suffix('\n').unwrap_or(value);
+    value.strip_suffix('\r').unwrap_or(value)
+}
+
+/// Reads one line from stdin, with echo disabled when stdin is a terminal.
+fn read_hidden(prompt: &str) -> PtyResult<String> {
+    let stdin = std::io::stdin();
+    let fd = stdin.as_raw_fd();
+    let echo_off = if terminal::is_terminal(fd) {
+        eprint!("{prompt}");
+        std::io::stderr().flush()?;
+        Some(terminal::EchoOff::enable(fd)?)
+    } else {
+        None
+    };
+    let mut line = String::new();
+    stdin.lock().read_line(&mut line)?;
+    if echo_off.is_some() {
+        eprintln!();
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn open_auth(args: &ControlOpenArgs) -> PtyResult<Option<Value>> {
+    let password = args
+        .password_env
+        .as_deref()
+        .map(read_env_secret)
+        .transpose()?;
+    let passphrase = args
+        .passphrase_env
+        .as_deref()
+        .map(read_env_secret)
+        .transpose()?;
+    let private_key_pem = match &args.key_file {
+        Some(path) => Some(std::fs::read_to_string(path).map_err(|err| {
+            ApiError::new(ErrorCode::InvalidArgument, "Failed to read --key-file")
+                .with_details(err.to_string())
+        })?),
+        None => None,
+    };
+    if password.is_none() && private_key_pem.is_none() && passphrase.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(json!({
+        "password": password,
+        "private_key_pem": private_key_pem,
+        "passphrase": passphrase,
+    })))
+}
+
+fn read_env_secret(name: &str) -> PtyResult<String> {
+    std::env::var(name).map_err(|_| {
+        ApiError::new(
+            ErrorCode::InvalidArgument,
+            format!("Environment variable {name} is not set"),
+        )
+        .into()
+    })
+}
+
+fn finish_session_action(response: &Value, json: bool) -> PtyResult<()> {
+    if json {
+        return print_json(response);
+    }
+    if let Some(message) = response.get("message").and_then(|v| v.as_str()) {
+        eprintln!("{message}");
+    }
+    Ok(())
+}
+
+/// The remote command line for `exec`: a single argument is sent as typed,
+/// several are joined with POSIX shell quoting so each stays one word.
+fn command_line(args: &[String]) -> String {
+    if let [command] = args {
+        return command.clone();
+    }
+    let quote = |arg: &String| {
+        let plain = !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%^".contains(c));
+        if plain {
+            arg.clone()
+        } else {
+            format!("'{}'", arg.replace('\'', r"'\''"))
+        }
+    };
+    args.iter().map(quote).collect::<Vec<_>>().join(" ")
+}
+
+fn exec_exit_code(response: &Value) -> i32 {
+    if let Some(code) = response.get("exit_code").and_then(|v| v.as_i64()) {
+        // A process exit status holds 8 bits; keep a failure a failure when
+        // the remote code is a non-zero multiple of 256.
+        if code != 0 && code & 0xff == 0 {
+            return 1;
+        }
+        return (code & 0xff) as i32;
+    }
+    if response.get("timed_out").and_then(|v| v.as_bool()) == Some(true) {
+        return EXIT_TIMEOUT;
+    }
+    0
+}
+
+async fn call(endpoint: &ControlEndpoint, method: &str, params: Value, json: bool) -> Value {
+    match control_request(endpoint, method, params).await {
+        Ok(response) => response,
+        Err(err) => fail(endpoint, err, json),
+    }
+}
+
+fn fail(endpoint: &ControlEndpoint, err: PtyError, json: bool) -> ! {
+    let code = match &err {
+        PtyError::Api(api) if api.error_code == ErrorCode::NotFound => EXIT_NOT_FOUND,
+        PtyError::Api(api) if api.error_code == ErrorCode::ExecTimeout => EXIT_TIMEOUT,
+        _ => EXIT_FAILURE,
+    };
+    if json {
+        let api = match err {
+            PtyError::Api(api) => api,
+            other => ApiError::new(ErrorCode::IoError, other.to_string()),
+        };
+        let _ = print_json(&json!({ "error": api }));
+        std::process::exit(code);
+    }
+    let err = exit_on_control_error(endpoint, err);
+    eprintln!("Error: {err}");
+    std::process::exit(code);
+}
+
+fn print_json(value: &Value) -> PtyResult<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn write_stream(out: &mut impl Write, text: &str) -> PtyResult<()> {
+    out.write_all(text.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_final_newline_keeps_inner_lines() {
+        assert_eq!(strip_final_newline("a\nb\n\n"), "a\nb\n");
+        assert_eq!(strip_final_newline("key\r\n"), "key");
+        assert_eq!(strip_final_newline("plain"), "plain");
+    }
+
+    #[test]
+    fn command_line_keeps_argument_boundaries() {
+        let args = |words: &[&str]| words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        assert_eq!(command_line(&args(&["show", "version"])), "show version");
+        assert_eq!(
+            command_line(&args(&["echo", "a  b", "it's", ""])),
+            r"echo 'a  b' 'it'\''s' ''"
+        );
+        assert_eq!(
+            command_line(&args(&["show run | include vlan"])),
+            "show run | include vlan"
+        );
+    }
+
+    #[test]
+    fn exec_exit_code_follows_remote_status() {
+        assert_eq!(
+            exec_exit_code(&json!({ "exit_code": 3, "timed_out": false })),
+            3
+        );
+        assert_eq!(exec_exit_code(&json!({ "exit_code": 256 })), 1);
+        assert_eq!(exec_exit_code(&json!({ "exit_code": 257 })), 1);
+        assert_eq!(exec_exit_code(&json!({ "exit_code": -1 })), 255);
+        assert_eq!(exec_exit_code(&json!({ "exit_code": 0 })), 0);
+        assert_eq!(
+            exec_exit_code(&json!({ "exit_code": null, "timed_out": true })),
+            124
+        );
+        assert_eq!(
+            exec_exit_code(&json!({ "exit_code": null, "timed_out": false })),
+            0
+        );
+    }
+}
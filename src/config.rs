@@ -1,6 +1,11 @@
 use crate::error::{ApiError, ErrorCode, PtyResult};
+use crate::session::{
+    ExpectConfig, JumpHost, Protocol, PtyOptions, ReconnectPolicy, SessionType, SshOptions,
+    Timeouts,
+};
 use clap::{Parser, ValueEnum};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -55,6 +60,9 @@ pub struct Config {
     pub ssh: SshConfig,
     pub telnet: TelnetConfig,
     pub logging: LoggingConfig,
+    pub secrets: SecretsConfig,
+    pub policy: PolicyConfig,
+    pub hosts: Vec<HostProfile>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,6 +71,8 @@ pub struct ServerConfig {
     pub transport: Transport,
     pub http: HttpConfig,
     pub control: ControlConfig,
+    pub metrics_listen: String,
+    pub shutdown: ShutdownConfig,
 }
 
 impl Default for ServerConfig {
@@ -71,6 +81,27 @@ impl Default for ServerConfig {
             transport: Transport::Stdio,
             http: HttpConfig::default(),
             control: ControlConfig::default(),
+            metrics_listen: String::new(),
+            shutdown: ShutdownConfig::default(),
+        }
+    }
+}
+
+/// What happens to open sessions on SIGTERM/SIGINT.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShutdownConfig {
+    /// Lines written to every session before closing it, e.g. `["exit"]`.
+    pub logout_commands: Vec<String>,
+    /// How long sessions get to exit on their own before they are closed.
+    pub grace_period_ms: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            logout_commands: Vec::new(),
+            grace_period_ms: 5_000,
         }
     }
 }
@@ -80,6 +111,12 @@ impl Default for ServerConfig {
 pub struct HttpConfig {
     pub listen: String,
     pub auth_token: String,
+    pub tokens: Vec<HttpToken>,
+    /// PEM certificate chain; with `tls_key`, the HTTP transport serves HTTPS.
+    pub tls_cert: String,
+    pub tls_key: String,
+    /// PEM CA bundle; when set, clients must present a certificate it signed.
+    pub client_ca: String,
 }
 
 impl Default for HttpConfig {
@@ -87,16 +124,37 @@ impl Default for HttpConfig {
         Self {
             listen: "127.0.0.1:8765".to_string(),
             auth_token: String::new(),
+            tokens: Vec::new(),
+            tls_cert: String::new(),
+            tls_key: String::new(),
+            client_ca: String::new(),
         }
     }
 }
 
+/// A named bearer token for the HTTP transport, stored as its SHA-256 hex digest.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HttpToken {
+    pub name: String,
+    pub token_sha256: String,
+    pub mode: ControlMode,
+    /// Allowed tools, as `tool` or `tool:action`; empty allows every tool.
+    pub tools: Vec<String>,
+    /// Host globs this token may open and use sessions on; empty allows any host.
+    pub hosts: Vec<String>,
+    /// Sessions this token may have open at once; 0 means no per-token limit.
+    pub max_sessions: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ControlConfig {
     pub control_socket_path: String,
     pub control_mode: ControlMode,
     pub control_auth_token: String,
+    pub control_socket_mode: u32,
+    pub control_allowed_uids: Vec<u32>,
 }
 
 impl Default for ControlConfig {
@@ -105,15 +163,17 @@ impl Default for ControlConfig {
             control_socket_path: default_control_socket_path(),
             control_mode: ControlMode::Readonly,
             control_auth_token: String::new(),
+            control_socket_mode: 0o600,
+            control_allowed_uids: Vec::new(),
         }
     }
 }
 
 fn default_control_socket_path() -> String {
-    if let Ok(dir) = env::var("XDG_RUNTIME_DIR") {
-        if is_dir(&dir) {
-            return format!("{}/ptyctl.sock", dir);
-        }
+    if let Ok(dir) = env::var("XDG_RUNTIME_DIR")
+        && is_dir(&dir)
+    {
+        return format!("{}/ptyctl.sock", dir);
     }
 
     let uid = unsafe { libc::geteuid() };
@@ -141,6 +201,34 @@ pub struct SessionConfig {
     pub record_tx_events: bool,
     pub default_exec_timeout_ms: u64,
     pub default_read_timeout_ms: u64,
+    pub redact_builtin_patterns: bool,
+    pub redact_patterns: Vec<String>,
+    pub redact_replacement: String,
+    /// MCP clients only see sessions they opened or that were shared with them.
+    pub isolate_clients: bool,
+    pub limits: SessionLimits,
+}
+
+/// Caps on concurrently open sessions on top of `max_sessions`; 0 means no limit.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SessionLimits {
+    pub per_host: usize,
+    pub per_device: usize,
+    /// Per MCP client (see session ownership); control socket opens are not counted.
+    pub per_client: usize,
+    pub per_protocol: HashMap<Protocol, usize>,
+    /// Overrides `per_host` for hosts matching a glob, e.g. routers with few vty lines.
+    pub hosts: Vec<HostLimit>,
+    /// How long `open` waits in line for a free slot before failing; 0 fails at once.
+    pub queue_timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HostLimit {
+    pub pattern: String,
+    pub max_sessions: usize,
 }
 
 impl Default for SessionConfig {
@@ -153,6 +241,11 @@ impl Default for SessionConfig {
             record_tx_events: false,
             default_exec_timeout_ms: 60_000,
             default_read_timeout_ms: 2_000,
+            redact_builtin_patterns: true,
+            redact_patterns: Vec::new(),
+            redact_replacement: "[REDACTED]".to_string(),
+            isolate_clients: true,
+            limits: SessionLimits::default(),
         }
     }
 }
@@ -165,6 +258,8 @@ pub struct SshConfig {
     pub config_path: String,
     pub host_key_policy: HostKeyPolicy,
     pub known_hosts_path: String,
+    pub server_alive_interval_ms: u64,
+    pub server_alive_count_max: u32,
 }
 
 impl Default for SshConfig {
@@ -175,6 +270,8 @@ impl Default for SshConfig {
             config_path: String::new(),
             host_key_policy: HostKeyPolicy::Strict,
             known_hosts_path: String::new(),
+            server_alive_interval_ms: 30_000,
+            server_alive_count_max: 3,
         }
     }
 }
@@ -184,6 +281,8 @@ impl Default for SshConfig {
 pub struct TelnetConfig {
     pub telnet_path: String,
     pub line_ending: TelnetLineEnding,
+    pub keepalive_interval_ms: u64,
+    pub keepalive_count_max: u32,
 }
 
 impl Default for TelnetConfig {
@@ -191,15 +290,122 @@ impl Default for TelnetConfig {
         Self {
             telnet_path: "telnet".to_string(),
             line_ending: TelnetLineEnding::Cr,
+            keepalive_interval_ms: 30_000,
+            keepalive_count_max: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HostProfile {
+    pub name: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub protocol: Option<Protocol>,
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub auth: Option<ProfileAuth>,
+    pub pty: Option<PtyOptions>,
+    pub timeouts: Option<Timeouts>,
+    pub ssh_options: Option<SshOptions>,
+    pub jump_hosts: Option<Vec<JumpHost>>,
+    pub reconnect: Option<ReconnectPolicy>,
+    pub expect: Option<ExpectConfig>,
+    pub session_type: Option<SessionType>,
+    pub device_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ProfileAuth {
+    pub method: Option<String>,
+    pub password: Option<String>,
+    pub password_env: Option<String>,
+    pub private_key_path: Option<String>,
+    pub passphrase: Option<String>,
+    pub passphrase_env: Option<String>,
+    pub password_ref: Option<String>,
+    pub passphrase_ref: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretProviderKind {
+    Env,
+    File,
+    Command,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecretsConfig {
+    pub providers: Vec<SecretProviderKind>,
+    pub env_prefix: String,
+    pub file_path: String,
+    pub file_key_env: String,
+    pub command: Vec<String>,
+    pub command_timeout_ms: u64,
+    /// Host globs each named secret may be sent to; secrets not listed here
+    /// are usable with any host.
+    pub allowed_hosts: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            providers: vec![
+                SecretProviderKind::Env,
+                SecretProviderKind::File,
+                SecretProviderKind::Command,
+            ],
+            env_prefix: "PTYCTL_SECRET_".to_string(),
+            file_path: String::new(),
+            file_key_env: "PTYCTL_SECRETS_KEY".to_string(),
+            command: Vec::new(),
+            command_timeout_ms: 5_000,
+            allowed_hosts: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PolicyConfig {
+    pub dry_run: bool,
+    pub approval_timeout_ms: u64,
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            approval_timeout_ms: 120_000,
+            rules: Vec::new(),
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct PolicyRule {
+    pub name: String,
+    pub hosts: Vec<String>,
+    pub profiles: Vec<String>,
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    pub require_confirmation: Vec<String>,
+    pub require_approval: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct LoggingConfig {
     pub level: String,
     pub format: String,
+    pub audit_path: String,
 }
 
 impl Default for LoggingConfig {
@@ -207,6 +413,7 @@ impl Default for LoggingConfig {
         Self {
             level: "info".to_string(),
             format: "text".to_string(),
+            audit_path: String::new(),
         }
     }
 }
@@ -222,12 +429,26 @@ pub struct Cli {
 pub enum Command {
     Serve(ServeArgs),
     Mcp(ServeArgs),
+    Daemon(ServeArgs),
     Sessions(ControlClientArgs),
     Tail(ControlTailArgs),
     Attach(ControlAttachArgs),
+    Open(ControlOpenArgs),
+    Exec(ControlExecArgs),
+    Send(ControlSendArgs),
+    Read(ControlReadArgs),
+    Close(ControlCloseArgs),
+    Lock(ControlLockArgs),
+    Unlock(ControlUnlockArgs),
+    Top(ControlTopArgs),
+    Approve(ControlApproveArgs),
+    Reload(ControlReloadArgs),
+    Secrets(SecretsArgs),
+    Token(TokenArgs),
+    Doctor(DoctorArgs),
 }
 
-#[derive(Debug, Parser, Clone)]
+#[derive(Debug, Parser, Clone, Default)]
 pub struct ServeArgs {
     #[arg(long)]
     pub config: Option<PathBuf>,
@@ -238,17 +459,31 @@ pub struct ServeArgs {
     #[arg(long)]
     pub auth_token: Option<String>,
     #[arg(long)]
+    pub tls_cert: Option<String>,
+    #[arg(long)]
+    pub tls_key: Option<String>,
+    #[arg(long)]
+    pub client_ca: Option<String>,
+    #[arg(long)]
+    pub metrics_listen: Option<String>,
+    #[arg(long)]
     pub control_socket: Option<String>,
     #[arg(long)]
     pub control_mode: Option<ControlMode>,
     #[arg(long)]
+    pub control_auth_token: Option<String>,
+    #[arg(long)]
     pub log_level: Option<String>,
+    #[arg(long)]
+    pub audit_log: Option<String>,
 }
 
 #[derive(Debug, Parser, Clone)]
 pub struct ControlClientArgs {
     #[arg(long)]
     pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
 }
 
 #[derive(Debug, Parser, Clone)]
@@ -262,6 +497,8 @@ pub struct ControlTailArgs {
     pub encoding: Option<String>,
     #[arg(long)]
     pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
 }
 
 #[derive(Debug, Parser, Clone)]
@@ -270,25 +507,406 @@ pub struct ControlAttachArgs {
     pub session_id: Option<String>,
     #[arg(long, default_value_t = 65536)]
     pub max_bytes: usize,
+    #[arg(long, short = 'i')]
+    pub interactive: bool,
+    #[arg(long, requires = "interactive")]
+    pub force: bool,
+    #[arg(long)]
+    pub task_id: Option<String>,
+    #[arg(long, default_value = "ctrl-]")]
+    pub detach_key: String,
+    #[arg(long, default_value_t = 30_000)]
+    pub lock_ttl_ms: u64,
+    #[arg(long)]
+    pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ControlOpenArgs {
+    #[arg(value_name = "HOST")]
+    pub host: Option<String>,
+    #[arg(long)]
+    pub profile: Option<String>,
+    #[arg(long)]
+    pub protocol: Option<String>,
+    #[arg(long, short = 'p')]
+    pub port: Option<u16>,
+    #[arg(long, short = 'u')]
+    pub username: Option<String>,
+    #[arg(long, value_name = "VAR")]
+    pub password_env: Option<String>,
+    #[arg(long, value_name = "PATH")]
+    pub key_file: Option<PathBuf>,
+    #[arg(long, value_name = "VAR")]
+    pub passphrase_env: Option<String>,
+    #[arg(long)]
+    pub no_pty: bool,
+    #[arg(long)]
+    pub session_type: Option<String>,
+    #[arg(long)]
+    pub device_id: Option<String>,
+    #[arg(long)]
+    pub lock: bool,
+    #[arg(long)]
+    pub task_id: Option<String>,
+    #[arg(long)]
+    pub json: bool,
+    #[arg(long)]
+    pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ControlExecArgs {
+    pub session_id: String,
+    #[arg(last = true, required = true, value_name = "CMD")]
+    pub cmd: Vec<String>,
+    #[arg(long)]
+    pub timeout_ms: Option<u64>,
+    #[arg(long)]
+    pub until_idle_ms: Option<u64>,
+    #[arg(long)]
+    pub no_rc: bool,
+    #[arg(long)]
+    pub task_id: Option<String>,
+    #[arg(long)]
+    pub json: bool,
+    #[arg(long)]
+    pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ControlSendArgs {
+    pub session_id: String,
+    #[arg(value_name = "DATA")]
+    pub data: Option<String>,
+    #[arg(long, conflicts_with = "data")]
+    pub key: Option<String>,
+    #[arg(long, conflicts_with = "key")]
+    pub enter: bool,
+    #[arg(long)]
+    pub sensitive: bool,
+    #[arg(long)]
+    pub task_id: Option<String>,
+    #[arg(long)]
+    pub json: bool,
+    #[arg(long)]
+    pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ControlReadArgs {
+    pub session_id: String,
+    #[arg(long)]
+    pub cursor: Option<String>,
+    #[arg(long, value_name = "REGEX")]
+    pub until: Option<String>,
+    #[arg(long)]
+    pub timeout_ms: Option<u64>,
+    #[arg(long)]
+    pub until_idle_ms: Option<u64>,
+    #[arg(long)]
+    pub max_bytes: Option<usize>,
+    #[arg(long, conflicts_with_all = ["cursor", "until"])]
+    pub tail: bool,
+    #[arg(long)]
+    pub json: bool,
     #[arg(long)]
     pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ControlCloseArgs {
+    pub session_id: String,
+    #[arg(long)]
+    pub force: bool,
+    #[arg(long)]
+    pub json: bool,
+    #[arg(long)]
+    pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ControlLockArgs {
+    pub session_id: String,
+    #[arg(long)]
+    pub task_id: Option<String>,
+    #[arg(long, default_value_t = 30_000)]
+    pub lock_ttl_ms: u64,
+    #[arg(long)]
+    pub force: bool,
+    #[arg(long)]
+    pub json: bool,
+    #[arg(long)]
+    pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ControlUnlockArgs {
+    pub session_id: String,
+    #[arg(long)]
+    pub task_id: Option<String>,
+    #[arg(long)]
+    pub json: bool,
+    #[arg(long)]
+    pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ControlTopArgs {
+    #[arg(long, default_value_t = 1_000)]
+    pub interval_ms: u64,
+    #[arg(long)]
+    pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ControlReloadArgs {
+    #[arg(long)]
+    pub json: bool,
+    #[arg(long)]
+    pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ControlApproveArgs {
+    /// Approval to decide; lists pending approvals when omitted.
+    pub approval_id: Option<String>,
+    #[arg(long)]
+    pub reject: bool,
+    #[arg(long, requires = "reject")]
+    pub reason: Option<String>,
+    #[arg(long)]
+    pub json: bool,
+    #[arg(long)]
+    pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct SecretsArgs {
+    #[command(subcommand)]
+    pub action: SecretsAction,
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        help = "Encrypted secrets file (defaults to secrets.file_path)"
+    )]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct DoctorArgs {
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    #[arg(long)]
+    pub json: bool,
+    #[arg(long)]
+    pub control_socket: Option<String>,
+    #[arg(long)]
+    pub control_token: Option<String>,
+}
+
+#[derive(Debug, clap::Subcommand, Clone)]
+pub enum SecretsAction {
+    /// Store a secret. The value is typed without echo on a terminal, or read
+    /// from piped stdin or --from-file up to EOF (multi-line values are kept).
+    Set {
+        name: String,
+        #[arg(long, value_name = "PATH", help = "Read the value from this file")]
+        from_file: Option<PathBuf>,
+    },
+    /// List stored secret names.
+    List,
+    /// Remove a stored secret.
+    Rm { name: String },
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct TokenArgs {
+    #[command(subcommand)]
+    pub action: TokenAction,
+}
+
+#[derive(Debug, clap::Subcommand, Clone)]
+pub enum TokenAction {
+    /// Generate a token and print the `[[server.http.tokens]]` entry for it.
+    New {
+        name: String,
+        #[arg(long, value_enum, default_value = "readonly")]
+        mode: ControlMode,
+    },
+    /// Print the token_sha256 of a token read from stdin.
+    Hash,
+}
+
+/// Non-secret view of the effective configuration, reported by `server_info`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigSummary {
+    pub transport: Transport,
+    pub http_listen: Option<String>,
+    pub http_auth: bool,
+    pub http_tokens: Vec<String>,
+    pub http_tls: bool,
+    pub http_client_certs: bool,
+    pub control_mode: ControlMode,
+    pub control_socket: String,
+    pub metrics_listen: Option<String>,
+    pub max_sessions: usize,
+    pub host_profiles: usize,
+    pub policy_rules: usize,
+    pub policy_dry_run: bool,
+    pub audit_log: Option<String>,
+    pub secret_providers: Vec<SecretProviderKind>,
 }
 
 impl Config {
+    pub fn summary(&self) -> ConfigSummary {
+        let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_string());
+        ConfigSummary {
+            transport: self.server.transport.clone(),
+            http_listen: match self.server.transport {
+                Transport::Stdio => None,
+                Transport::Http | Transport::Both => Some(self.server.http.listen.clone()),
+            },
+            http_auth: !self.server.http.auth_token.is_empty()
+                || !self.server.http.tokens.is_empty(),
+            http_tokens: self
+                .server
+                .http
+                .tokens
+                .iter()
+                .map(|token| token.name.clone())
+                .collect(),
+            http_tls: !self.server.http.tls_cert.is_empty(),
+            http_client_certs: !self.server.http.client_ca.is_empty(),
+            control_mode: self.server.control.control_mode.clone(),
+            control_socket: self.server.control.control_socket_path.clone(),
+            metrics_listen: non_empty(&self.server.metrics_listen),
+            max_sessions: self.session.max_sessions,
+            host_profiles: self.hosts.len(),
+            policy_rules: self.policy.rules.len(),
+            policy_dry_run: self.policy.dry_run,
+            audit_log: non_empty(&self.logging.audit_path),
+            secret_providers: self.secrets.providers.clone(),
+        }
+    }
+
     pub fn load(args: &ServeArgs) -> PtyResult<Self> {
-        let mut config = if let Some(path) = &args.config {
-            Self::from_file(path)?
-        } else if Path::new("ptyctl.toml").exists() {
-            Self::from_file(Path::new("ptyctl.toml"))?
-        } else {
-            Self::default()
+        let mut config = match Self::path(args) {
+            Some(path) => Self::from_file(&path)?,
+            None => Self::default(),
         };
 
         config.apply_env();
         config.apply_cli(args);
+        config.validate()?;
         Ok(config)
     }
 
+    /// The file `load` reads: `--config`, else `ptyctl.toml` when it exists.
+    pub fn path(args: &ServeArgs) -> Option<PathBuf> {
+        match &args.config {
+            Some(path) => Some(path.clone()),
+            None => Some(PathBuf::from("ptyctl.toml")).filter(|path| path.exists()),
+        }
+    }
+
+    fn validate(&self) -> PtyResult<()> {
+        for pattern in &self.session.redact_patterns {
+            regex::bytes::Regex::new(pattern).map_err(|err| {
+                ApiError::new(
+                    ErrorCode::InvalidArgument,
+                    format!("Invalid redaction pattern: {pattern}"),
+                )
+                .with_details(err.to_string())
+            })?;
+        }
+        let http = &self.server.http;
+        if http.tls_cert.is_empty() != http.tls_key.is_empty() {
+            return Err(ApiError::new(
+                ErrorCode::InvalidArgument,
+                "server.http.tls_cert and tls_key must be set together",
+            )
+            .into());
+        }
+        if !http.client_ca.is_empty() && http.tls_cert.is_empty() {
+            return Err(ApiError::new(
+                ErrorCode::InvalidArgument,
+                "server.http.client_ca requires tls_cert and tls_key",
+            )
+            .into());
+        }
+        for limit in &self.session.limits.hosts {
+            crate::policy::glob_regex(&limit.pattern).map_err(|err| {
+                ApiError::new(
+                    ErrorCode::InvalidArgument,
+                    format!("Invalid host limit pattern: {}", limit.pattern),
+                )
+                .with_details(err.to_string())
+            })?;
+        }
+        // Keepalives go out in whole seconds (ServerAliveInterval, TCP keepalive).
+        for (field, interval_ms) in [
+            (
+                "ssh.server_alive_interval_ms",
+                self.ssh.server_alive_interval_ms,
+            ),
+            (
+                "telnet.keepalive_interval_ms",
+                self.telnet.keepalive_interval_ms,
+            ),
+        ] {
+            if interval_ms > 0 && interval_ms < 1000 {
+                return Err(ApiError::new(
+                    ErrorCode::InvalidArgument,
+                    format!("{field} must be 0 (disabled) or at least 1000"),
+                )
+                .into());
+            }
+        }
+        let mut names = std::collections::HashSet::new();
+        for profile in &self.hosts {
+            if profile.name.trim().is_empty() || profile.host.trim().is_empty() {
+                return Err(ApiError::new(
+                    ErrorCode::InvalidArgument,
+                    "Host profiles require name and host",
+                )
+                .into());
+            }
+            if !names.insert(profile.name.as_str()) {
+                return Err(ApiError::new(
+                    ErrorCode::InvalidArgument,
+                    format!("Duplicate host profile: {}", profile.name),
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
     fn from_file(path: &Path) -> PtyResult<Self> {
         let content = fs::read_to_string(path).map_err(|err| {
             ApiError::new(ErrorCode::IoError, "Failed to read config file")
@@ -302,24 +920,42 @@ impl Config {
     }
 
     fn apply_env(&mut self) {
-        if let Ok(value) = env::var("PTYCTL_TRANSPORT") {
-            if let Some(transport) = parse_transport(&value) {
-                self.server.transport = transport;
-            }
+        if let Ok(value) = env::var("PTYCTL_TRANSPORT")
+            && let Some(transport) = parse_transport(&value)
+        {
+            self.server.transport = transport;
         }
         if let Ok(value) = env::var("PTYCTL_HTTP_LISTEN") {
             self.server.http.listen = value;
         }
+        if let Ok(value) = env::var("PTYCTL_TLS_CERT") {
+            self.server.http.tls_cert = value;
+        }
+        if let Ok(value) = env::var("PTYCTL_TLS_KEY") {
+            self.server.http.tls_key = value;
+        }
+        if let Ok(value) = env::var("PTYCTL_CLIENT_CA") {
+            self.server.http.client_ca = value;
+        }
+        if let Ok(value) = env::var("PTYCTL_METRICS_LISTEN") {
+            self.server.metrics_listen = value;
+        }
         if let Ok(value) = env::var("PTYCTL_LOG_LEVEL") {
             self.logging.level = value;
         }
+        if let Ok(value) = env::var("PTYCTL_AUDIT_LOG") {
+            self.logging.audit_path = value;
+        }
         if let Ok(value) = env::var("PTYCTL_CONTROL_SOCKET") {
             self.server.control.control_socket_path = value;
         }
-        if let Ok(value) = env::var("PTYCTL_CONTROL_MODE") {
-            if let Some(mode) = parse_control_mode(&value) {
-                self.server.control.control_mode = mode;
-            }
+        if let Ok(value) = env::var("PTYCTL_CONTROL_MODE")
+            && let Some(mode) = parse_control_mode(&value)
+        {
+            self.server.control.control_mode = mode;
+        }
+        if let Ok(value) = env::var("PTYCTL_CONTROL_AUTH_TOKEN") {
+            self.server.control.control_auth_token = value;
         }
     }
 
@@ -333,15 +969,33 @@ impl Config {
         if let Some(token) = &args.auth_token {
             self.server.http.auth_token = token.clone();
         }
+        if let Some(path) = &args.tls_cert {
+            self.server.http.tls_cert = path.clone();
+        }
+        if let Some(path) = &args.tls_key {
+            self.server.http.tls_key = path.clone();
+        }
+        if let Some(path) = &args.client_ca {
+            self.server.http.client_ca = path.clone();
+        }
+        if let Some(listen) = &args.metrics_listen {
+            self.server.metrics_listen = listen.clone();
+        }
         if let Some(path) = &args.control_socket {
             self.server.control.control_socket_path = path.clone();
         }
         if let Some(mode) = &args.control_mode {
             self.server.control.control_mode = mode.clone();
         }
+        if let Some(token) = &args.control_auth_token {
+            self.server.control.control_auth_token = token.clone();
+        }
         if let Some(level) = &args.log_level {
             self.logging.level = level.clone();
         }
+        if let Some(path) = &args.audit_log {
+            self.logging.audit_path = path.clone();
+        }
     }
 }
 
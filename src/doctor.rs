@@ -0,0 +1,382 @@
+use crate::config::{Config, ControlMode, HostKeyPolicy, SecretProviderKind, Transport};
+use crate::error::{ApiError, PtyError};
+use crate::policy::Policy;
+use crate::tls;
+use crate::tokens::TokenTable;
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// One `ptyctl doctor` finding; `fix` says what to change when it is not ok.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DoctorCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl DoctorCheck {
+    pub fn ok(name: &str, detail: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Ok, detail, None)
+    }
+
+    pub fn warn(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Warn, detail, Some(fix.into()))
+    }
+
+    pub fn fail(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Fail, detail, Some(fix.into()))
+    }
+
+    fn new(
+        name: &str,
+        status: CheckStatus,
+        detail: impl Into<String>,
+        fix: Option<String>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+            fix,
+        }
+    }
+}
+
+/// Checks the environment a server started with `config` depends on. The config
+/// itself has already been parsed and validated by `Config::load`.
+pub fn run_checks(config: &Config) -> Vec<DoctorCheck> {
+    let mut checks = vec![check_policy(config), check_ssh(config)];
+    checks.extend(check_ssh_files(config));
+    checks.push(DoctorCheck::ok(
+        "telnet",
+        "built-in client (telnet.telnet_path is not used)",
+    ));
+    checks.push(check_control_socket(config));
+    checks.extend(check_listeners(config));
+    checks.extend(check_audit_log(config));
+    checks.extend(check_secrets(config));
+    checks
+}
+
+fn check_policy(config: &Config) -> DoctorCheck {
+    match Policy::new(&config.policy) {
+        Ok(_) => DoctorCheck::ok(
+            "policy",
+            format!(
+                "{} rule(s){}",
+                config.policy.rules.len(),
+                if config.policy.dry_run {
+                    ", dry run"
+                } else {
+                    ""
+                }
+            ),
+        ),
+        Err(err) => DoctorCheck::fail(
+            "policy",
+            err.to_string(),
+            "Fix the pattern in [[policy.rules]]",
+        ),
+    }
+}
+
+fn check_ssh(config: &Config) -> DoctorCheck {
+    let path = &config.ssh.openssh_path;
+    let Some(binary) = find_executable(path) else {
+        return DoctorCheck::fail(
+            "ssh",
+            format!("{path} not found or not executable"),
+            "Install the OpenSSH client or set ssh.openssh_path",
+        );
+    };
+    // `ssh -V` prints its version to stderr.
+    match Command::new(&binary).arg("-V").output() {
+        Ok(output) => {
+            let version = String::from_utf8_lossy(&output.stderr);
+            let version = version.lines().next().unwrap_or_default().trim();
+            DoctorCheck::ok("ssh", format!("{} ({version})", binary.display()))
+        }
+        Err(err) => DoctorCheck::fail(
+            "ssh",
+            format!("{} failed to run: {err}", binary.display()),
+            "Check ssh.openssh_path points at a working ssh binary",
+        ),
+    }
+}
+
+fn check_ssh_files(config: &Config) -> Vec<DoctorCheck> {
+    let mut checks = Vec::new();
+    let ssh = &config.ssh;
+    if ssh.use_openssh_config
+        && !ssh.config_path.is_empty()
+        && !Path::new(&ssh.config_path).is_file()
+    {
+        checks.push(DoctorCheck::fail(
+            "ssh_config",
+            format!("{} does not exist", ssh.config_path),
+            "Create the file or clear ssh.config_path",
+        ));
+    }
+    let known_hosts = if ssh.known_hosts_path.is_empty() {
+        std::env::var("HOME")
+            .map(|home| format!("{home}/.ssh/known_hosts"))
+            .unwrap_or_default()
+    } else {
+        ssh.known_hosts_path.clone()
+    };
+    let strict = matches!(ssh.host_key_policy, HostKeyPolicy::Strict);
+    if Path::new(&known_hosts).is_file() {
+        checks.push(DoctorCheck::ok("known_hosts", known_hosts));
+    } else if strict {
+        checks.push(DoctorCheck::warn(
+            "known_hosts",
+            format!("{known_hosts} is missing; strict host key checking rejects unknown hosts"),
+            "Add device keys with ssh-keyscan, or set ssh.host_key_policy = \"acceptnew\"",
+        ));
+    }
+    checks
+}
+
+fn check_control_socket(config: &Config) -> DoctorCheck {
+    let control = &config.server.control;
+    if matches!(control.control_mode, ControlMode::Disabled) {
+        return DoctorCheck::ok("control_socket", "disabled");
+    }
+    let path = Path::new(&control.control_socket_path);
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    if !dir.is_dir() {
+        return DoctorCheck::fail(
+            "control_socket",
+            format!("directory {} does not exist", dir.display()),
+            "Create the directory or set server.control.control_socket_path",
+        );
+    }
+    if !is_writable(dir) {
+        return DoctorCheck::fail(
+            "control_socket",
+            format!("directory {} is not writable", dir.display()),
+            "Fix the directory permissions or set server.control.control_socket_path",
+        );
+    }
+    let mode = match control.control_mode {
+        ControlMode::Readwrite => "readwrite",
+        _ => "readonly",
+    };
+    DoctorCheck::ok("control_socket", format!("{} ({mode})", path.display()))
+}
+
+fn check_listeners(config: &Config) -> Vec<DoctorCheck> {
+    let mut checks = Vec::new();
+    if !matches!(config.server.transport, Transport::Stdio) {
+        let http = &config.server.http;
+        checks.push(match http.listen.parse::<SocketAddr>() {
+            Err(_) => DoctorCheck::fail(
+                "http",
+                format!("invalid listen address {}", http.listen),
+                "Set server.http.listen to host:port, e.g. 127.0.0.1:8765",
+            ),
+            Ok(addr)
+                if http.auth_token.is_empty()
+                    && http.tokens.is_empty()
+                    && !addr.ip().is_loopback() =>
+            {
+                DoctorCheck::warn(
+                    "http",
+                    format!("{addr} is reachable from the network without a token"),
+                    "Set server.http.auth_token or bind to 127.0.0.1",
+                )
+            }
+            Ok(addr) => DoctorCheck::ok("http", addr.to_string()),
+        });
+        if !http.tls_cert.is_empty() {
+            checks.push(match tls::acceptor(http) {
+                Ok(_) if http.client_ca.is_empty() => {
+                    DoctorCheck::ok("https", http.tls_cert.clone())
+                }
+                Ok(_) => DoctorCheck::ok(
+                    "https",
+                    format!(
+                        "{} (client certificates from {})",
+                        http.tls_cert, http.client_ca
+                    ),
+                ),
+                Err(err) => DoctorCheck::fail(
+                    "https",
+                    error_detail(&err),
+                    "Point server.http.tls_cert/tls_key/client_ca at readable PEM files",
+                ),
+            });
+        }
+        if !http.tokens.is_empty() {
+            checks.push(match TokenTable::new(http) {
+                Ok(_) => DoctorCheck::ok(
+                    "http_tokens",
+                    format!("{} scoped token(s)", http.tokens.len()),
+                ),
+                Err(err) => DoctorCheck::fail(
+                    "http_tokens",
+                    error_detail(&err),
+                    "Fix the entry in [[server.http.tokens]]",
+                ),
+            });
+        }
+    }
+    let metrics = &config.server.metrics_listen;
+    if !metrics.is_empty() {
+        checks.push(match metrics.parse::<SocketAddr>() {
+            Ok(addr) => DoctorCheck::ok("metrics", addr.to_string()),
+            Err(_) => DoctorCheck::fail(
+                "metrics",
+                format!("invalid listen address {metrics}"),
+                "Set server.metrics_listen to host:port, e.g. 127.0.0.1:9465",
+            ),
+        });
+    }
+    checks
+}
+
+fn check_audit_log(config: &Config) -> Option<DoctorCheck> {
+    let path = &config.logging.audit_path;
+    if path.is_empty() {
+        return None;
+    }
+    let file = Path::new(path);
+    let target = if file.exists() {
+        file
+    } else {
+        file.parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+    };
+    Some(if is_writable(target) {
+        DoctorCheck::ok("audit_log", path.clone())
+    } else {
+        DoctorCheck::fail(
+            "audit_log",
+            format!("{} is not writable", target.display()),
+            "Fix the permissions or change logging.audit_path",
+        )
+    })
+}
+
+fn check_secrets(config: &Config) -> Vec<DoctorCheck> {
+    let secrets = &config.secrets;
+    let mut checks = Vec::new();
+    if secrets.providers.contains(&SecretProviderKind::File) && !secrets.file_path.is_empty() {
+        if !Path::new(&secrets.file_path).is_file() {
+            checks.push(DoctorCheck::warn(
+                "secrets_file",
+                format!("{} does not exist yet", secrets.file_path),
+                "Create it with `ptyctl secrets set <name>`",
+            ));
+        } else if std::env::var(&secrets.file_key_env).is_err() {
+            checks.push(DoctorCheck::fail(
+                "secrets_file",
+                format!("{} is not set", secrets.file_key_env),
+                format!(
+                    "Export {} with the passphrase for {}",
+                    secrets.file_key_env, secrets.file_path
+                ),
+            ));
+        } else {
+            checks.push(DoctorCheck::ok("secrets_file", secrets.file_path.clone()));
+        }
+    }
+    if secrets.providers.contains(&SecretProviderKind::Command)
+        && let Some(program) = secrets.command.first()
+    {
+        checks.push(match find_executable(program) {
+            Some(path) => DoctorCheck::ok("secrets_command", path.display().to_string()),
+            None => DoctorCheck::fail(
+                "secrets_command",
+                format!("{program} not found or not executable"),
+                "Install it or fix secrets.command",
+            ),
+        });
+    }
+    checks
+}
+
+fn error_detail(err: &PtyError) -> String {
+    match err {
+        PtyError::Api(ApiError {
+            details: Some(details),
+            ..
+        }) => format!("{err} ({details})"),
+        _ => err.to_string(),
+    }
+}
+
+fn find_executable(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return is_executable(&path).then_some(path);
+    }
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(program))
+        .find(|path| is_executable(path))
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.is_file() && access(path, libc::X_OK)
+}
+
+fn is_writable(path: &Path) -> bool {
+    access(path, libc::W_OK)
+}
+
+fn access(path: &Path, mode: libc::c_int) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_encoded_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(path.as_ptr(), mode) == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find<'a>(checks: &'a [DoctorCheck], name: &str) -> &'a DoctorCheck {
+        checks
+            .iter()
+            .find(|check| check.name == name)
+            .unwrap_or_else(|| panic!("missing {name} check"))
+    }
+
+    #[test]
+    fn reports_missing_binaries_and_unwritable_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.ssh.openssh_path = dir.path().join("no-ssh").display().to_string();
+        config.server.control.control_socket_path =
+            dir.path().join("missing/ptyctl.sock").display().to_string();
+        config.server.transport = Transport::Http;
+        config.server.http.listen = "0.0.0.0:8765".to_string();
+        config.logging.audit_path = dir.path().join("audit.jsonl").display().to_string();
+
+        let checks = run_checks(&config);
+        assert_eq!(find(&checks, "ssh").status, CheckStatus::Fail);
+        assert!(find(&checks, "ssh").fix.is_some());
+        assert_eq!(find(&checks, "control_socket").status, CheckStatus::Fail);
+        assert_eq!(find(&checks, "http").status, CheckStatus::Warn);
+        assert_eq!(find(&checks, "audit_log").status, CheckStatus::Ok);
+        assert_eq!(find(&checks, "policy").status, CheckStatus::Ok);
+    }
+}
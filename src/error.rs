@@ -16,6 +16,10 @@ pub enum ErrorCode {
     RemoteClosed,
     ExecTimeout,
     Unsupported,
+    PolicyDenied,
+    PermissionDenied,
+    LimitExceeded,
+    ShuttingDown,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,6 +85,10 @@ impl ErrorCode {
             ErrorCode::RemoteClosed => "REMOTE_CLOSED",
             ErrorCode::ExecTimeout => "EXEC_TIMEOUT",
             ErrorCode::Unsupported => "UNSUPPORTED",
+            ErrorCode::PolicyDenied => "POLICY_DENIED",
+            ErrorCode::PermissionDenied => "PERMISSION_DENIED",
+            ErrorCode::LimitExceeded => "LIMIT_EXCEEDED",
+            ErrorCode::ShuttingDown => "SHUTTING_DOWN",
         }
     }
 }
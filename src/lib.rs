@@ -1,5 +1,15 @@
+pub mod approval;
+pub mod audit;
 pub mod config;
+pub mod doctor;
 pub mod error;
 pub mod mcp;
+pub mod metrics;
+pub mod policy;
+pub mod proxy;
+pub mod reload;
+pub mod secrets;
 pub mod session;
+pub mod tls;
+pub mod tokens;
 pub mod version;
@@ -1,97 +1,309 @@
+mod cli;
+mod terminal;
+mod top;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use clap::Parser;
 use ptyctl::config::{self, Cli, Command, ControlAttachArgs, ControlClientArgs, ControlTailArgs};
 use ptyctl::error::{ApiError, ErrorCode, PtyError, PtyResult};
-use ptyctl::mcp::{McpServer, serve_control_socket};
-use ptyctl::session::SessionManager;
+use ptyctl::mcp::{McpServer, bind_control_socket, serve_control_socket};
+use ptyctl::proxy::{DaemonEndpoint, McpProxy};
+use ptyctl::reload::{ConfigReloader, LogFilterHandle};
+use ptyctl::session::{SessionManager, ShutdownEntry, ShutdownOutcome};
+use ptyctl::tls;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::ffi::OsString;
+use std::io::Write;
+use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, interval};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
     match cli.command {
-        Command::Serve(args) | Command::Mcp(args) => run_server(args).await?,
+        Command::Serve(args) => exit_server(run_server(args, false).await),
+        Command::Daemon(args) => exit_server(run_server(args, true).await),
+        Command::Mcp(args) => exit_server(run_mcp_proxy(args).await),
         Command::Sessions(args) => run_sessions(args).await?,
         Command::Tail(args) => run_tail(args).await?,
         Command::Attach(args) => run_attach(args).await?,
+        Command::Open(args) => cli::run_open(args).await?,
+        Command::Exec(args) => cli::run_exec(args).await?,
+        Command::Send(args) => cli::run_send(args).await?,
+        Command::Read(args) => cli::run_read(args).await?,
+        Command::Close(args) => cli::run_close(args).await?,
+        Command::Lock(args) => cli::run_lock(args).await?,
+        Command::Unlock(args) => cli::run_unlock(args).await?,
+        Command::Top(args) => top::run_top(args).await?,
+        Command::Approve(args) => cli::run_approve(args).await?,
+        Command::Reload(args) => cli::run_reload(args).await?,
+        Command::Secrets(args) => cli::run_secrets(args)?,
+        Command::Token(args) => cli::run_token(args)?,
+        Command::Doctor(args) => cli::run_doctor(args).await?,
     }
     Ok(())
 }
 
-async fn run_server(args: config::ServeArgs) -> PtyResult<()> {
+/// Exits right away: dropping the runtime would wait on the blocked stdin reader.
+fn exit_server(result: PtyResult<()>) -> ! {
+    if let Err(err) = result {
+        eprintln!("Error: {err:?}");
+        std::process::exit(1);
+    }
+    std::process::exit(0);
+}
+
+/// Runs the MCP server. As a `daemon` it serves `ptyctl mcp` proxies on the
+/// control socket instead of stdio, and a control socket failure is fatal.
+async fn run_server(args: config::ServeArgs, daemon: bool) -> PtyResult<()> {
     let config = config::Config::load(&args)?;
-    init_logging(&config.logging);
+    let log_filter = init_logging(&config.logging);
+    let control_enabled = !matches!(
+        config.server.control.control_mode,
+        config::ControlMode::Disabled
+    );
+    if daemon
+        && !matches!(
+            config.server.control.control_mode,
+            config::ControlMode::Readwrite
+        )
+    {
+        return Err(ApiError::new(
+            ErrorCode::InvalidArgument,
+            "ptyctl daemon needs the control socket in readwrite mode",
+        )
+        .with_details("pass --control-mode readwrite or set server.control.control_mode")
+        .into());
+    }
+    let mut signals = ShutdownSignals::new()?;
+    let tls = tls::acceptor(&config.server.http)?;
 
     let session_manager = SessionManager::new(
         config.session.clone(),
         config.ssh.clone(),
-        config.telnet.line_ending.clone(),
+        config.telnet.clone(),
     );
-    let server = McpServer::new(session_manager.clone(), config.session.clone());
+    let (reload_tx, reload_rx) = mpsc::channel(4);
+    let server = McpServer::new(session_manager.clone(), &config)?.with_reload_requests(reload_tx);
+    let reloader = ConfigReloader::new(args, config.clone(), server.clone(), Some(log_filter));
+    tokio::spawn(async move {
+        if let Err(err) = reloader.run(reload_rx).await {
+            tracing::error!(error = %err, "Config reload task failed");
+        }
+    });
 
-    if !matches!(
-        config.server.control.control_mode,
-        config::ControlMode::Disabled
-    ) {
-        let control_path = config.server.control.control_socket_path.clone();
-        let mode = config.server.control.control_mode.clone();
+    // The daemon serves the control socket as its transport; otherwise it is a side channel.
+    let mut daemon_control = None;
+    let mut control_bound = false;
+    if control_enabled {
+        match bind_control_socket(&config.server.control).await {
+            Ok(listener) if daemon => {
+                daemon_control = Some(listener);
+                control_bound = true;
+            }
+            Ok(listener) => {
+                let control = config.server.control.clone();
+                let server_clone = server.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = serve_control_socket(server_clone, listener, control).await {
+                        tracing::error!(error = %err, "Control socket task failed");
+                    }
+                });
+                control_bound = true;
+            }
+            Err(err) if daemon => return Err(err),
+            Err(err) => tracing::error!(error = %err, "Control socket task failed"),
+        }
+    }
+
+    if !config.server.metrics_listen.is_empty() {
+        let listen = config.server.metrics_listen.clone();
         let server_clone = server.clone();
         tokio::spawn(async move {
-            if let Err(err) = serve_control_socket(server_clone, &control_path, mode).await {
-                tracing::error!(error = %err, "Control socket task failed");
+            if let Err(err) = server_clone.serve_metrics(&listen).await {
+                tracing::error!(error = %err, "Metrics listener failed");
             }
         });
     }
 
-    match config.server.transport {
-        config::Transport::Stdio => server.clone().serve_stdio().await,
-        config::Transport::Http => {
-            server
-                .clone()
-                .serve_http(&config.server.http.listen, &config.server.http.auth_token)
-                .await
+    let (stdio, http) = match config.server.transport {
+        config::Transport::Stdio => (!daemon, false),
+        config::Transport::Http => (false, true),
+        config::Transport::Both => (!daemon, true),
+    };
+    let transports = {
+        let server = server.clone();
+        let control = config.server.control.clone();
+        let http_listen = config.server.http.listen.clone();
+        async move {
+            let control = async {
+                match daemon_control {
+                    Some(listener) => serve_control_socket(server.clone(), listener, control).await,
+                    None => Ok(()),
+                }
+            };
+            let stdio = async {
+                if stdio {
+                    server.clone().serve_stdio().await
+                } else {
+                    Ok(())
+                }
+            };
+            let http = async {
+                if http {
+                    server.clone().serve_http(&http_listen, tls).await
+                } else {
+                    Ok(())
+                }
+            };
+            tokio::try_join!(control, stdio, http).map(|_| ())
         }
-        config::Transport::Both => {
-            let server_clone = server.clone();
-            let http_listen = config.server.http.listen.clone();
-            let auth_token = config.server.http.auth_token.clone();
-            let stdio_task = tokio::spawn(async move { server_clone.serve_stdio().await });
-            let http_task =
-                tokio::spawn(async move { server.serve_http(&http_listen, &auth_token).await });
-            let (stdio_res, http_res) = tokio::join!(stdio_task, http_task);
-            stdio_res.map_err(|err| {
-                ApiError::new(ErrorCode::IoError, "STDIO task failed").with_details(err.to_string())
-            })??;
-            http_res.map_err(|err| {
-                ApiError::new(ErrorCode::IoError, "HTTP task failed").with_details(err.to_string())
-            })??;
+    };
+    let result = tokio::select! {
+        result = transports => result,
+        name = signals.recv() => {
+            tracing::info!(signal = name, "Shutting down");
             Ok(())
         }
+    };
+
+    tokio::select! {
+        report = server.shutdown(&config.server.shutdown) => report_shutdown(&report),
+        name = signals.recv() => {
+            tracing::warn!(signal = name, "Second signal; skipping graceful session close");
+        }
+    }
+    if control_bound {
+        let _ = std::fs::remove_file(&config.server.control.control_socket_path);
+    }
+    result
+}
+
+/// SIGTERM and SIGINT, either of which starts a graceful shutdown.
+struct ShutdownSignals {
+    term: tokio::signal::unix::Signal,
+    int: tokio::signal::unix::Signal,
+}
+
+impl ShutdownSignals {
+    fn new() -> PtyResult<Self> {
+        let watch = |kind| {
+            signal(kind).map_err(|err| {
+                ApiError::new(ErrorCode::IoError, "Failed to watch shutdown signals")
+                    .with_details(err.to_string())
+            })
+        };
+        Ok(Self {
+            term: watch(SignalKind::terminate())?,
+            int: watch(SignalKind::interrupt())?,
+        })
+    }
+
+    async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.term.recv() => "SIGTERM",
+            _ = self.int.recv() => "SIGINT",
+        }
     }
 }
 
-fn init_logging(logging: &config::LoggingConfig) {
-    let filter = tracing_subscriber::EnvFilter::new(logging.level.clone());
+fn report_shutdown(report: &[ShutdownEntry]) {
+    for entry in report {
+        match &entry.error {
+            Some(error) => tracing::warn!(
+                session_id = %entry.session_id,
+                host = %entry.host,
+                error = %error,
+                "Failed to close session at shutdown"
+            ),
+            None => tracing::info!(
+                session_id = %entry.session_id,
+                host = %entry.host,
+                outcome = entry.outcome.as_str(),
+                "Closed session at shutdown"
+            ),
+        }
+    }
+    let count = |outcome| {
+        report
+            .iter()
+            .filter(|entry| entry.outcome == outcome)
+            .count()
+    };
+    tracing::info!(
+        logged_out = count(ShutdownOutcome::LoggedOut),
+        closed = count(ShutdownOutcome::Closed),
+        failed = count(ShutdownOutcome::Failed),
+        "Shutdown complete"
+    );
+}
+
+/// Installs the subscriber; the returned handle swaps `logging.level` on reload.
+fn init_logging(logging: &config::LoggingConfig) -> LogFilterHandle {
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    let (filter, handle) = tracing_subscriber::reload::Layer::new(
+        tracing_subscriber::EnvFilter::new(logging.level.clone()),
+    );
+    let registry = tracing_subscriber::registry().with(filter);
+    let layer = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
     if logging.format == "json" {
-        tracing_subscriber::fmt()
-            .with_env_filter(filter)
-            .json()
-            .with_writer(std::io::stderr)
-            .init();
+        registry.with(layer.json()).init();
     } else {
-        tracing_subscriber::fmt()
-            .with_env_filter(filter)
-            .with_writer(std::io::stderr)
-            .init();
+        registry.with(layer).init();
     }
+    handle
+}
+
+/// `ptyctl mcp`: a stdio proxy to `ptyctl daemon`, which it starts with the
+/// same arguments when none is running, so sessions outlive the MCP client.
+async fn run_mcp_proxy(args: config::ServeArgs) -> PtyResult<()> {
+    let config = config::Config::load(&args)?;
+    let _ = init_logging(&config.logging);
+    let control = &config.server.control;
+    if matches!(control.control_mode, config::ControlMode::Disabled) {
+        return Err(ApiError::new(
+            ErrorCode::InvalidArgument,
+            "ptyctl mcp reaches the daemon over the control socket, which is disabled",
+        )
+        .with_details("run `ptyctl serve --transport stdio` to serve without a daemon")
+        .into());
+    }
+    if matches!(args.control_mode, Some(config::ControlMode::Readonly)) {
+        return Err(ApiError::new(
+            ErrorCode::InvalidArgument,
+            "ptyctl mcp needs the daemon's control socket in readwrite mode",
+        )
+        .with_details("drop --control-mode or pass --control-mode readwrite")
+        .into());
+    }
+    let mut spawn_args: Vec<OsString> = std::env::args_os().skip(2).collect();
+    if args.control_mode.is_none() {
+        spawn_args.extend(["--control-mode".into(), "readwrite".into()]);
+    }
+    let mut proxy = McpProxy::new(DaemonEndpoint {
+        socket_path: control.control_socket_path.clone(),
+        auth_token: Some(control.control_auth_token.clone()).filter(|token| !token.is_empty()),
+        spawn_args: Some(spawn_args),
+    });
+    if let Ok(client_id) = std::env::var("PTYCTL_CLIENT_ID")
+        && !client_id.is_empty()
+    {
+        proxy = proxy.with_client_id(client_id);
+    }
+    proxy.serve_stdio().await
 }
 
 async fn run_sessions(args: ControlClientArgs) -> PtyResult<()> {
-    let socket_path = args.control_socket.unwrap_or_else(default_control_socket);
+    let endpoint = ControlEndpoint::new(args.control_socket, args.control_token);
     let response =
-        control_request_or_exit(&socket_path, "ptyctl_session", json!({ "action": "list" }))
-            .await?;
+        control_request_or_exit(&endpoint, "ptyctl_session", json!({ "action": "list" })).await?;
     println!(
         "{}",
         serde_json::to_string_pretty(&response).unwrap_or_default()
@@ -100,10 +312,10 @@ async fn run_sessions(args: ControlClientArgs) -> PtyResult<()> {
 }
 
 async fn run_tail(args: ControlTailArgs) -> PtyResult<()> {
-    let socket_path = args.control_socket.unwrap_or_else(default_control_socket);
+    let endpoint = ControlEndpoint::new(args.control_socket, args.control_token);
     let encoding = args.encoding.unwrap_or_else(|| "utf-8".to_string());
     let response = control_request(
-        &socket_path,
+        &endpoint,
         "ptyctl_session_io",
         json!({
             "action": "read",
@@ -122,16 +334,19 @@ async fn run_tail(args: ControlTailArgs) -> PtyResult<()> {
 }
 
 async fn run_attach(args: ControlAttachArgs) -> PtyResult<()> {
-    let socket_path = args.control_socket.unwrap_or_else(default_control_socket);
+    let endpoint = ControlEndpoint::new(args.control_socket.clone(), args.control_token.clone());
     let session_id = match args.session_id.as_deref().map(str::trim) {
         Some(value) if !value.is_empty() => value.to_string(),
         _ => {
-            report_missing_session_id(&socket_path).await?;
+            report_missing_session_id(&endpoint).await?;
             std::process::exit(2);
         }
     };
+    if args.interactive {
+        return run_interactive_attach(endpoint, session_id, &args).await;
+    }
     let tail_response = control_request_or_exit(
-        &socket_path,
+        &endpoint,
         "ptyctl_session_io",
         json!({
             "action": "read",
@@ -142,36 +357,318 @@ async fn run_attach(args: ControlAttachArgs) -> PtyResult<()> {
         }),
     )
     .await?;
-    let mut cursor = tail_response
-        .get("next_cursor")
-        .and_then(|v| v.as_str())
-        .unwrap_or("0")
-        .to_string();
-    if let Some(chunk) = tail_response.get("chunk").and_then(|v| v.as_str()) {
-        print!("{chunk}");
+    write_chunk(&tail_response)?;
+    let mut subscription = control_subscribe(
+        &endpoint,
+        json!({
+            "session_id": session_id.as_str(),
+            "cursor": tail_response.get("next_cursor"),
+            "max_bytes": args.max_bytes,
+            "encoding": "utf-8"
+        }),
+    )
+    .await
+    .map_err(|err| exit_on_control_error(&endpoint, err))?;
+    while let Some(notification) = subscription.next().await? {
+        match notification.method.as_str() {
+            "ptyctl/output" => write_chunk(&notification.params)?,
+            "ptyctl/closed" => {
+                eprintln!("\nSession {session_id} closed.");
+                break;
+            }
+            _ => {}
+        }
     }
-    loop {
-        let response = control_request_or_exit(
-            &socket_path,
-            "ptyctl_session_io",
-            json!({
-                "action": "read",
-                "session_id": session_id.as_str(),
-                "cursor": cursor,
-                "timeout_ms": 2000,
-                "max_bytes": args.max_bytes,
-                "encoding": "utf-8"
-            }),
+    Ok(())
+}
+
+#[derive(Clone)]
+struct ControlEndpoint {
+    socket_path: String,
+    auth_token: Option<String>,
+}
+
+impl ControlEndpoint {
+    fn new(socket_path: Option<String>, auth_token: Option<String>) -> Self {
+        Self {
+            socket_path: socket_path.unwrap_or_else(default_control_socket),
+            auth_token: auth_token
+                .or_else(|| std::env::var("PTYCTL_CONTROL_AUTH_TOKEN").ok())
+                .filter(|token| !token.is_empty()),
+        }
+    }
+}
+
+async fn run_interactive_attach(
+    endpoint: ControlEndpoint,
+    session_id: String,
+    args: &ControlAttachArgs,
+) -> PtyResult<()> {
+    let detach_key = terminal::parse_detach_key(&args.detach_key).ok_or_else(|| {
+        ApiError::new(
+            ErrorCode::InvalidArgument,
+            "Invalid --detach-key; expected ctrl-<key>",
+        )
+    })?;
+    if !terminal::is_terminal(libc::STDIN_FILENO) {
+        return Err(ApiError::new(
+            ErrorCode::InvalidArgument,
+            "Interactive attach requires a terminal on stdin",
         )
-        .await?;
-        if let Some(chunk) = response.get("chunk").and_then(|v| v.as_str()) {
-            if !chunk.is_empty() {
-                print!("{chunk}");
+        .into());
+    }
+    let task_id = args.task_id.clone().unwrap_or_else(default_human_task_id);
+    let lock_ttl_ms = args.lock_ttl_ms.max(1_000);
+    let lock_params = json!({
+        "action": "lock",
+        "session_id": session_id.as_str(),
+        "task_id": task_id.as_str(),
+        "lock_ttl_ms": lock_ttl_ms,
+        "force": args.force,
+    });
+    let lock = match control_request(&endpoint, "ptyctl_session", lock_params).await {
+        Ok(response) => response,
+        Err(err) => {
+            print_interactive_lock_hint(&err);
+            return Err(exit_on_control_error(&endpoint, err));
+        }
+    };
+    if let Some(message) = lock.get("message").and_then(|v| v.as_str()) {
+        eprintln!("{message}");
+    }
+    eprintln!(
+        "Attached to {session_id} as {task_id}. Press {} to detach.",
+        args.detach_key
+    );
+
+    let endpoint = Arc::new(endpoint);
+    let result = interactive_loop(
+        endpoint.clone(),
+        &session_id,
+        &task_id,
+        detach_key,
+        lock_ttl_ms,
+        args.max_bytes,
+    )
+    .await;
+    let _ = control_request(
+        &endpoint,
+        "ptyctl_session",
+        json!({
+            "action": "unlock",
+            "session_id": session_id.as_str(),
+            "task_id": task_id.as_str(),
+        }),
+    )
+    .await;
+    eprintln!("Detached from {session_id}; lock released.");
+    result
+}
+
+async fn interactive_loop(
+    endpoint: Arc<ControlEndpoint>,
+    session_id: &str,
+    task_id: &str,
+    detach_key: u8,
+    lock_ttl_ms: u64,
+    max_bytes: usize,
+) -> PtyResult<()> {
+    let raw_mode = terminal::RawMode::enable(libc::STDIN_FILENO).map_err(|err| {
+        ApiError::new(ErrorCode::IoError, "Failed to enable raw mode").with_details(err.to_string())
+    })?;
+    resize_to_terminal(&endpoint, session_id).await;
+
+    let tail = control_request(
+        &endpoint,
+        "ptyctl_session_io",
+        json!({
+            "action": "read",
+            "mode": "tail",
+            "session_id": session_id,
+            "max_bytes": max_bytes,
+            "encoding": "base64"
+        }),
+    )
+    .await?;
+    write_chunk(&tail)?;
+    let mut subscription = control_subscribe(
+        &endpoint,
+        json!({
+            "session_id": session_id,
+            "cursor": tail.get("next_cursor"),
+            "max_bytes": max_bytes,
+            "encoding": "base64"
+        }),
+    )
+    .await?;
+
+    let (input_tx, mut input_rx) = mpsc::channel::<Vec<u8>>(64);
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 1024];
+        while !input_tx.is_closed() {
+            match terminal::read_timeout(libc::STDIN_FILENO, &mut buf, 100) {
+                Ok(None) => {}
+                Ok(Some(0)) | Err(_) => break,
+                Ok(Some(n)) => {
+                    if input_tx.blocking_send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let lock_task = task_id.to_string();
+    let mut output_task = tokio::spawn(async move {
+        while let Some(notification) = subscription.next().await? {
+            let params = &notification.params;
+            match notification.method.as_str() {
+                "ptyctl/output" => write_chunk(params)?,
+                "ptyctl/state" if params.get("eof").and_then(|v| v.as_bool()) == Some(true) => {
+                    return Ok::<_, PtyError>("Session closed by remote".to_string());
+                }
+                "ptyctl/lock" => {
+                    let holder = params.get("lock_holder").and_then(|v| v.as_str());
+                    if holder != Some(lock_task.as_str()) {
+                        return Ok(format!(
+                            "Session lock taken over by {}",
+                            holder.unwrap_or("nobody")
+                        ));
+                    }
+                }
+                "ptyctl/closed" => return Ok("Session closed".to_string()),
+                _ => {}
             }
         }
-        if let Some(next_cursor) = response.get("next_cursor").and_then(|v| v.as_str()) {
-            cursor = next_cursor.to_string();
+        Ok("Control connection closed".to_string())
+    });
+
+    let mut winch = signal(SignalKind::window_change()).map_err(|err| {
+        ApiError::new(ErrorCode::IoError, "Failed to watch window size")
+            .with_details(err.to_string())
+    })?;
+    let mut heartbeat = interval(Duration::from_millis(lock_ttl_ms / 3));
+    heartbeat.tick().await;
+
+    let reason = loop {
+        tokio::select! {
+            output = &mut output_task => {
+                break match output {
+                    Ok(Ok(reason)) => reason,
+                    Ok(Err(err)) => format!("Output stream ended: {}", err),
+                    Err(err) => format!("Output stream ended: {}", err),
+                };
+            }
+            input = input_rx.recv() => {
+                let Some(input) = input else {
+                    break "Local input closed".to_string();
+                };
+                let (data, detach) = match input.iter().position(|byte| *byte == detach_key) {
+                    Some(index) => (&input[..index], true),
+                    None => (&input[..], false),
+                };
+                if !data.is_empty() {
+                    control_request(
+                        &endpoint,
+                        "ptyctl_session_io",
+                        json!({
+                            "action": "write",
+                            "session_id": session_id,
+                            "task_id": task_id,
+                            "data": BASE64.encode(data),
+                            "encoding": "base64"
+                        }),
+                    )
+                    .await?;
+                }
+                if detach {
+                    break String::new();
+                }
+            }
+            _ = winch.recv() => {
+                resize_to_terminal(&endpoint, session_id).await;
+            }
+            _ = heartbeat.tick() => {
+                if let Err(err) = control_request(
+                    &endpoint,
+                    "ptyctl_session",
+                    json!({
+                        "action": "heartbeat",
+                        "session_id": session_id,
+                        "task_id": task_id,
+                        "lock_ttl_ms": lock_ttl_ms,
+                    }),
+                )
+                .await
+                {
+                    break format!("Lost session lock: {}", err);
+                }
+            }
         }
+    };
+    output_task.abort();
+    drop(raw_mode);
+    eprintln!();
+    if !reason.is_empty() {
+        eprintln!("{reason}");
+    }
+    Ok(())
+}
+
+async fn resize_to_terminal(endpoint: &ControlEndpoint, session_id: &str) {
+    if let Some((cols, rows)) = terminal::window_size(libc::STDIN_FILENO) {
+        let _ = control_request(
+            endpoint,
+            "ptyctl_session_config",
+            json!({
+                "action": "resize",
+                "session_id": session_id,
+                "cols": cols,
+                "rows": rows,
+            }),
+        )
+        .await;
+    }
+}
+
+fn write_chunk(response: &serde_json::Value) -> PtyResult<()> {
+    let Some(chunk) = response.get("chunk").and_then(|v| v.as_str()) else {
+        return Ok(());
+    };
+    if chunk.is_empty() {
+        return Ok(());
+    }
+    let bytes = if response.get("encoding").and_then(|v| v.as_str()) == Some("base64") {
+        BASE64.decode(chunk).map_err(|err| {
+            ApiError::new(ErrorCode::IoError, "Invalid output chunk").with_details(err.to_string())
+        })?
+    } else {
+        chunk.as_bytes().to_vec()
+    };
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(&bytes)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn default_human_task_id() -> String {
+    let user = std::env::var("USER")
+        .ok()
+        .filter(|user| !user.is_empty())
+        .unwrap_or_else(|| unsafe { libc::geteuid() }.to_string());
+    format!("human:{user}")
+}
+
+fn print_interactive_lock_hint(err: &PtyError) {
+    let PtyError::Api(api) = err else {
+        return;
+    };
+    if api.error_code == ErrorCode::Unsupported {
+        eprintln!(
+            "Interactive attach needs the control socket in readwrite mode (`--control-mode readwrite`)."
+        );
+    } else if api.message.starts_with("Session locked by task") {
+        eprintln!("{}. Use `--force` to take the lock over.", api.message);
     }
 }
 
@@ -181,6 +678,8 @@ struct ControlRequest<'a> {
     id: u64,
     method: &'a str,
     params: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auth_token: Option<&'a str>,
 }
 
 #[derive(Deserialize)]
@@ -195,24 +694,72 @@ struct ControlErrorPayload {
     data: Option<ApiError>,
 }
 
+type ControlLines = tokio::io::Lines<tokio::io::BufReader<tokio::net::unix::OwnedReadHalf>>;
+
+struct ControlSubscription {
+    lines: ControlLines,
+    _writer: tokio::net::unix::OwnedWriteHalf,
+}
+
+#[derive(Deserialize)]
+struct ControlNotification {
+    method: String,
+    #[serde(default)]
+    params: serde_json::Value,
+}
+
+impl ControlSubscription {
+    async fn next(&mut self) -> PtyResult<Option<ControlNotification>> {
+        let line = self.lines.next_line().await.map_err(|err| {
+            ApiError::new(ErrorCode::IoError, "Control read failed").with_details(err.to_string())
+        })?;
+        match line {
+            Some(line) => Ok(Some(serde_json::from_str(&line)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+async fn control_subscribe(
+    endpoint: &ControlEndpoint,
+    params: serde_json::Value,
+) -> PtyResult<ControlSubscription> {
+    let (mut lines, writer) = control_send(endpoint, "subscribe", params).await?;
+    read_control_response(&mut lines).await?;
+    Ok(ControlSubscription {
+        lines,
+        _writer: writer,
+    })
+}
+
 async fn control_request(
-    socket_path: &str,
+    endpoint: &ControlEndpoint,
     method: &str,
     params: serde_json::Value,
 ) -> PtyResult<serde_json::Value> {
-    let stream = tokio::net::UnixStream::connect(socket_path)
+    let (mut lines, _writer) = control_send(endpoint, method, params).await?;
+    read_control_response(&mut lines).await
+}
+
+async fn control_send(
+    endpoint: &ControlEndpoint,
+    method: &str,
+    params: serde_json::Value,
+) -> PtyResult<(ControlLines, tokio::net::unix::OwnedWriteHalf)> {
+    let stream = tokio::net::UnixStream::connect(&endpoint.socket_path)
         .await
         .map_err(|err| {
             ApiError::new(ErrorCode::IoError, "Failed to connect control socket")
                 .with_details(err.to_string())
         })?;
     let (reader, mut writer) = stream.into_split();
-    let mut reader = tokio::io::BufReader::new(reader).lines();
+    let lines = tokio::io::BufReader::new(reader).lines();
     let request = ControlRequest {
         jsonrpc: "2.0",
         id: 1,
         method,
         params,
+        auth_token: endpoint.auth_token.as_deref(),
     };
     let payload = serde_json::to_string(&request)?;
     writer.write_all(payload.as_bytes()).await.map_err(|err| {
@@ -224,8 +771,11 @@ async fn control_request(
     writer.flush().await.map_err(|err| {
         ApiError::new(ErrorCode::IoError, "Control write failed").with_details(err.to_string())
     })?;
+    Ok((lines, writer))
+}
 
-    if let Some(line) = reader.next_line().await.map_err(|err| {
+async fn read_control_response(lines: &mut ControlLines) -> PtyResult<serde_json::Value> {
+    if let Some(line) = lines.next_line().await.map_err(|err| {
         ApiError::new(ErrorCode::IoError, "Control read failed").with_details(err.to_string())
     })? {
         let response: ControlResponse = serde_json::from_str(&line)?;
@@ -244,9 +794,9 @@ fn default_control_socket() -> String {
     config::Config::default().server.control.control_socket_path
 }
 
-async fn report_missing_session_id(socket_path: &str) -> PtyResult<()> {
+async fn report_missing_session_id(endpoint: &ControlEndpoint) -> PtyResult<()> {
     eprintln!("Missing session id.");
-    match list_session_ids(socket_path).await {
+    match list_session_ids(endpoint).await {
         Ok(ids) => {
             if ids.is_empty() {
                 eprintln!("No sessions are available.");
@@ -261,7 +811,10 @@ async fn report_missing_session_id(socket_path: &str) -> PtyResult<()> {
         }
         Err(err) => {
             if is_control_socket_connect_error(&err) {
-                print_control_socket_hint(socket_path, &err);
+                print_control_socket_hint(&endpoint.socket_path, &err);
+                Ok(())
+            } else if is_control_auth_error(&err) {
+                print_control_auth_hint();
                 Ok(())
             } else {
                 Err(err)
@@ -270,9 +823,8 @@ async fn report_missing_session_id(socket_path: &str) -> PtyResult<()> {
     }
 }
 
-async fn list_session_ids(socket_path: &str) -> PtyResult<Vec<String>> {
-    let response =
-        control_request(socket_path, "ptyctl_session", json!({ "action": "list" })).await?;
+async fn list_session_ids(endpoint: &ControlEndpoint) -> PtyResult<Vec<String>> {
+    let response = control_request(endpoint, "ptyctl_session", json!({ "action": "list" })).await?;
     let mut ids = Vec::new();
     if let Some(entries) = response.get("sessions").and_then(|value| value.as_array()) {
         for entry in entries {
@@ -285,24 +837,29 @@ async fn list_session_ids(socket_path: &str) -> PtyResult<Vec<String>> {
 }
 
 async fn control_request_or_exit(
-    socket_path: &str,
+    endpoint: &ControlEndpoint,
     method: &str,
     params: serde_json::Value,
 ) -> PtyResult<serde_json::Value> {
-    match control_request(socket_path, method, params).await {
-        Ok(response) => Ok(response),
-        Err(err) => {
-            if is_control_socket_connect_error(&err) {
-                print_control_socket_hint(socket_path, &err);
-                std::process::exit(1);
-            }
-            if is_session_not_found_error(&err) {
-                print_session_not_found_hint();
-                std::process::exit(2);
-            }
-            Err(err)
-        }
+    control_request(endpoint, method, params)
+        .await
+        .map_err(|err| exit_on_control_error(endpoint, err))
+}
+
+fn exit_on_control_error(endpoint: &ControlEndpoint, err: PtyError) -> PtyError {
+    if is_control_socket_connect_error(&err) {
+        print_control_socket_hint(&endpoint.socket_path, &err);
+        std::process::exit(1);
+    }
+    if is_control_auth_error(&err) {
+        print_control_auth_hint();
+        std::process::exit(1);
     }
+    if is_session_not_found_error(&err) {
+        print_session_not_found_hint();
+        std::process::exit(2);
+    }
+    err
 }
 
 fn is_control_socket_connect_error(err: &PtyError) -> bool {
@@ -313,6 +870,13 @@ fn is_control_socket_connect_error(err: &PtyError) -> bool {
     )
 }
 
+fn is_control_auth_error(err: &PtyError) -> bool {
+    matches!(
+        err,
+        PtyError::Api(api) if api.error_code == ErrorCode::AuthFailed
+    )
+}
+
 fn is_session_not_found_error(err: &PtyError) -> bool {
     matches!(
         err,
@@ -326,13 +890,20 @@ fn print_control_socket_hint(socket_path: &str, err: &PtyError) {
         "Confirm the service is running (`ptyctl serve`), or use `--control-socket` / `PTYCTL_CONTROL_SOCKET` to point at the correct socket."
     );
     eprintln!("Use `ls -l {socket_path}` to check whether the socket exists and its permissions.");
-    if let PtyError::Api(api) = err {
-        if let Some(details) = &api.details {
-            eprintln!("Details: {details}");
-        }
+    if let PtyError::Api(api) = err
+        && let Some(details) = &api.details
+    {
+        eprintln!("Details: {details}");
     }
 }
 
+fn print_control_auth_hint() {
+    eprintln!("Control socket rejected the request.");
+    eprintln!(
+        "Pass the server's control token with `--control-token` or `PTYCTL_CONTROL_AUTH_TOKEN`, and make sure your uid is in `control_allowed_uids`."
+    );
+}
+
 fn print_session_not_found_hint() {
     eprintln!("Session not found or already closed.");
     eprintln!("Use `ptyctl sessions` to list active sessions.");
@@ -1,56 +1,396 @@
-use crate::config::{ControlMode, SessionConfig};
+use crate::approval::{
+    ApprovalQueue, ApprovalsAction, ApprovalsRequest, ApprovalsResponse, PendingApproval,
+};
+use crate::audit::{AuditClient, AuditEvent, AuditLog, AuditTransport};
+use crate::config::{
+    Config, ConfigSummary, ControlConfig, ControlMode, HostProfile, SessionConfig, ShutdownConfig,
+};
 use crate::error::{ApiError, ErrorCode, PtyError, PtyResult};
+use crate::metrics::Metrics;
+use crate::policy::{Policy, PolicyDecision};
+use crate::reload::{ReloadReport, ReloadRequest};
+use crate::secrets::SecretStore;
 use crate::session::{
-    Capabilities, ConfigAction, Encoding, InputHints, IoAction, RcMode, ReadMode, ReadParams,
-    SessionAction, SessionConfigRequest, SessionConfigResponse, SessionExecRequest,
-    SessionExecResponse, SessionIoRequest, SessionIoResponse, SessionManager, SessionOpenRequest,
-    SessionOpenResponse, SessionReadRequest, SessionReadResponse, SessionRequest, SessionResponse,
-    SessionTailRequest, SessionTailResponse, encode_chunk, format_cursor, parse_cursor,
-    read_from_session,
+    Capabilities, ConfigAction, ElicitInput, ElicitKind, ElicitationOutcome, Encoding, InputHints,
+    IoAction, KnownSecrets, ProfileSummary, RcMode, ReadMode, ReadParams, Redactor, Session,
+    SessionAccess, SessionAction, SessionConfigRequest, SessionConfigResponse, SessionEvent,
+    SessionExecRequest, SessionExecResponse, SessionIoRequest, SessionIoResponse, SessionKey,
+    SessionManager, SessionOpenRequest, SessionOpenResponse, SessionReadRequest,
+    SessionReadResponse, SessionRequest, SessionResponse, SessionSubscribeRequest,
+    SessionTailRequest, SessionTailResponse, SessionWatcher, ShutdownEntry, SshOptions,
+    encode_chunk, format_cursor, parse_cursor, read_from_session, render_screen,
 };
+use crate::tls::{self, ClientIdentity};
+use crate::tokens::{TokenGrant, TokenScope, TokenTable};
+use crate::version::VERSION;
 use axum::{
     Router,
     http::{
         HeaderValue, StatusCode,
-        header::{AUTHORIZATION, WWW_AUTHENTICATE},
+        header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE},
+        request::Parts,
     },
     middleware,
     response::IntoResponse,
+    routing::get,
 };
 use regex::Regex;
 use rmcp::handler::server::tool::ToolRouter;
 use rmcp::handler::server::wrapper::Parameters;
-use rmcp::model::{CallToolResult, Implementation, ServerCapabilities, ServerInfo};
+use rmcp::model::{
+    AnnotateAble, CallToolResult, CreateElicitationRequestParam, CreateElicitationResult,
+    ElicitationAction, ElicitationSchema, Extensions, Implementation, InitializeRequestParam,
+    InitializeResult, ListResourceTemplatesResult, ListResourcesResult, PaginatedRequestParam,
+    RawResource, RawResourceTemplate, ReadResourceRequestParam, ReadResourceResult,
+    ResourceContents, ResourceUpdatedNotificationParam, ServerCapabilities, ServerInfo,
+    SubscribeRequestParam, Tool, UnsubscribeRequestParam,
+};
+use rmcp::service::{Peer, RequestContext, RoleServer};
 use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
 use rmcp::transport::{StreamableHttpServerConfig, StreamableHttpService, stdio};
 use rmcp::{ErrorData as McpError, ServiceExt, tool, tool_handler, tool_router};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::os::unix::fs::PermissionsExt;
+use std::sync::{Arc, Mutex};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot};
 use tokio::time::{Duration, Instant};
+use tokio_rustls::TlsAcceptor;
 use uuid::Uuid;
 
 const DEFAULT_LOCK_TTL_MS: u64 = 60_000;
+const SESSION_RESOURCE_PREFIX: &str = "ptyctl://sessions/";
+const RESOURCE_TAIL_BYTES: usize = 65536;
+/// Output before a read that redaction looks at, enough for a private key.
+const REDACT_CONTEXT_BYTES: usize = 8192;
+pub(crate) const RESOURCE_UPDATE_DEBOUNCE: Duration = Duration::from_millis(250);
 
 #[derive(Clone)]
 pub struct McpServer {
     session_manager: Arc<SessionManager>,
-    session_config: SessionConfig,
+    live: Arc<std::sync::RwLock<Arc<LiveConfig>>>,
+    approvals: Arc<ApprovalQueue>,
+    audit: Arc<AuditLog>,
+    metrics: Arc<Metrics>,
+    reload_requests: Option<mpsc::Sender<ReloadRequest>>,
+    scope: Option<Arc<TokenScope>>,
+    /// Verified client certificate identity of the current HTTPS request.
+    peer_identity: Option<String>,
+    /// Who owns the sessions this client opens: `token:<name>`, `cert:<cn>`,
+    /// `mcp:<id>` per HTTP connection, `proxy:<id>` for a `ptyctl mcp` client
+    /// or `stdio`. `None` on the control socket, which sees every session.
+    principal: Option<String>,
+    started_at: Instant,
+    started_at_ms: u64,
+    client: AuditClient,
+    resource_subscriptions: Arc<Mutex<HashMap<String, tokio::task::AbortHandle>>>,
     tool_router: ToolRouter<Self>,
 }
 
+/// The settings a config reload replaces, swapped as a whole so each request
+/// sees one version.
+struct LiveConfig {
+    session_config: SessionConfig,
+    hosts: Vec<HostProfile>,
+    secrets: SecretStore,
+    redactor: Redactor,
+    policy: Policy,
+    tokens: TokenTable,
+    summary: ConfigSummary,
+}
+
+impl LiveConfig {
+    /// `known` carries resolved secret values over a reload so they stay masked.
+    fn new(config: &Config, known: KnownSecrets) -> PtyResult<Self> {
+        Ok(Self {
+            session_config: config.session.clone(),
+            hosts: config.hosts.clone(),
+            secrets: SecretStore::new(config.secrets.clone(), known.clone())?,
+            redactor: Redactor::new(&config.session).with_known_secrets(known),
+            policy: Policy::new(&config.policy)?,
+            tokens: TokenTable::new(&config.server.http)?,
+            summary: config.summary(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SessionResourceKind {
+    Tail,
+    Screen,
+    Transcript,
+}
+
+impl SessionResourceKind {
+    const ALL: [Self; 3] = [Self::Tail, Self::Screen, Self::Transcript];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Tail => "tail",
+            Self::Screen => "screen",
+            Self::Transcript => "transcript",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Self::Tail => "Most recent output (up to 64 KiB)",
+            Self::Screen => "Approximate terminal screen rendered at the current PTY size",
+            Self::Transcript => "All buffered output since the buffer start",
+        }
+    }
+}
+
+fn session_resource_uri(session_id: &str, kind: SessionResourceKind) -> String {
+    format!("{SESSION_RESOURCE_PREFIX}{session_id}/{}", kind.as_str())
+}
+
+pub(crate) fn parse_session_resource_uri(uri: &str) -> PtyResult<(String, SessionResourceKind)> {
+    let invalid = || {
+        ApiError::new(
+            ErrorCode::InvalidArgument,
+            format!("Unknown resource: {uri}"),
+        )
+    };
+    let rest = uri
+        .strip_prefix(SESSION_RESOURCE_PREFIX)
+        .ok_or_else(invalid)?;
+    let (session_id, kind) = rest.split_once('/').ok_or_else(invalid)?;
+    let kind = SessionResourceKind::ALL
+        .into_iter()
+        .find(|candidate| candidate.as_str() == kind)
+        .ok_or_else(invalid)?;
+    if session_id.is_empty() {
+        return Err(invalid().into());
+    }
+    Ok((session_id.to_string(), kind))
+}
+
 impl McpServer {
-    pub fn new(session_manager: Arc<SessionManager>, session_config: SessionConfig) -> Self {
-        Self {
+    pub fn new(session_manager: Arc<SessionManager>, config: &Config) -> PtyResult<Self> {
+        let live = LiveConfig::new(config, KnownSecrets::default())?;
+        Ok(Self {
             session_manager,
-            session_config,
+            approvals: Arc::new(ApprovalQueue::new(live.policy.approval_timeout_ms())),
+            live: Arc::new(std::sync::RwLock::new(Arc::new(live))),
+            audit: Arc::new(AuditLog::open(&config.logging.audit_path)?),
+            metrics: Arc::new(Metrics::default()),
+            reload_requests: None,
+            scope: None,
+            peer_identity: None,
+            principal: Some("stdio".to_string()),
+            started_at: Instant::now(),
+            started_at_ms: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or_default(),
+            client: AuditClient::new(AuditTransport::Stdio, None),
+            resource_subscriptions: Arc::new(Mutex::new(HashMap::new())),
             tool_router: Self::tool_router(),
+        })
+    }
+
+    /// Serves the control `reload` method by asking the config reloader.
+    pub fn with_reload_requests(mut self, requests: mpsc::Sender<ReloadRequest>) -> Self {
+        self.reload_requests = Some(requests);
+        self
+    }
+
+    /// Swaps in reloaded settings: session defaults and limits, host profiles,
+    /// secret providers, redaction, policy and HTTP tokens. Nothing changes if
+    /// any of them is invalid.
+    pub fn reconfigure(&self, config: &Config) -> PtyResult<()> {
+        let live = LiveConfig::new(config, self.live().redactor.known_secrets().clone())?;
+        self.approvals
+            .set_timeout_ms(live.policy.approval_timeout_ms());
+        self.session_manager.reconfigure(
+            config.session.clone(),
+            config.ssh.clone(),
+            config.telnet.clone(),
+        );
+        *self.live.write().expect("live config lock poisoned") = Arc::new(live);
+        Ok(())
+    }
+
+    /// Redacts output that starts at `cursor`, matching it together with the
+    /// output before it so a secret split across two reads is still masked.
+    fn redact_output<'a>(
+        &self,
+        session: &Session,
+        cursor: u64,
+        bytes: &'a [u8],
+    ) -> (Cow<'a, [u8]>, usize) {
+        let context = session.output_before(cursor, REDACT_CONTEXT_BYTES);
+        self.live().redactor.redact_after(&context, bytes)
+    }
+
+    fn live(&self) -> Arc<LiveConfig> {
+        self.live.read().expect("live config lock poisoned").clone()
+    }
+
+    /// Clone for a new client connection; resource subscriptions and the audit
+    /// identity are per connection.
+    fn for_connection(&self, transport: AuditTransport, identity: Option<String>) -> Self {
+        Self {
+            resource_subscriptions: Arc::new(Mutex::new(HashMap::new())),
+            client: AuditClient::new(transport, identity),
+            principal: match transport {
+                AuditTransport::Stdio => Some("stdio".to_string()),
+                AuditTransport::Http => Some(format!("mcp:{}", Uuid::new_v4().simple())),
+                AuditTransport::Control => None,
+            },
+            ..self.clone()
+        }
+    }
+
+    /// A control connection acting for the MCP client behind a `ptyctl mcp`
+    /// proxy: it owns what it opens, so client isolation and `per_client`
+    /// limits apply as for any other MCP client.
+    fn for_proxy_client(&self, client_id: &str) -> Self {
+        let identity = match self.client.identity() {
+            Some(peer) => format!("proxy:{client_id} {peer}"),
+            None => format!("proxy:{client_id}"),
+        };
+        Self {
+            client: AuditClient::new(self.client.transport, Some(identity)),
+            principal: Some(format!("proxy:{client_id}")),
+            ..self.clone()
         }
     }
 
+    /// This server as seen by the HTTP caller: the token scope and client
+    /// certificate identity of the request, if any.
+    fn scoped(&self, extensions: &Extensions) -> Cow<'_, Self> {
+        let Some(parts) = extensions.get::<Parts>() else {
+            return Cow::Borrowed(self);
+        };
+        let scope = parts.extensions.get::<Arc<TokenScope>>();
+        let identity = parts.extensions.get::<ClientIdentity>();
+        if scope.is_none() && identity.is_none() {
+            return Cow::Borrowed(self);
+        }
+        let mut client = match identity {
+            Some(ClientIdentity(identity)) => {
+                AuditClient::new(self.client.transport, Some(format!("cert:{identity}")))
+            }
+            None => self.client.clone(),
+        };
+        if let Some(scope) = scope {
+            client = client.with_token(&scope.name);
+        }
+        let principal = match (scope, identity) {
+            (Some(scope), _) => Some(format!("token:{}", scope.name)),
+            (None, Some(ClientIdentity(identity))) => Some(format!("cert:{identity}")),
+            (None, None) => self.principal.clone(),
+        };
+        Cow::Owned(Self {
+            client,
+            principal,
+            scope: scope.cloned(),
+            peer_identity: identity.map(|ClientIdentity(identity)| identity.clone()),
+            ..self.clone()
+        })
+    }
+
+    /// Lock-owning calls from a client certificate default to its identity as
+    /// `task_id`.
+    fn default_task_id(&self, task_id: &mut Option<String>) {
+        if task_id.is_none() {
+            task_id.clone_from(&self.peer_identity);
+        }
+    }
+
+    /// Checks a tool call against the caller's token scope (tool list and read-only mode).
+    fn authorize_tool<T: Serialize>(
+        &self,
+        extensions: &Extensions,
+        tool: &str,
+        req: &T,
+    ) -> PtyResult<Cow<'_, Self>> {
+        let server = self.scoped(extensions);
+        if let Some(scope) = &server.scope {
+            let params = serde_json::to_value(req)?;
+            let action = params.get("action").and_then(Value::as_str);
+            if !scope.allows_tool(tool, action) {
+                return Err(ApiError::new(
+                    ErrorCode::PermissionDenied,
+                    format!("Token '{}' may not call {tool}", scope.name),
+                )
+                .into());
+            }
+            if scope.is_readonly() && !is_readonly_call(tool, Some(&params)) {
+                return Err(ApiError::new(
+                    ErrorCode::PermissionDenied,
+                    format!("Token '{}' is read-only", scope.name),
+                )
+                .into());
+            }
+        }
+        Ok(server)
+    }
+
+    fn check_host(&self, host: &str) -> PtyResult<()> {
+        match &self.scope {
+            Some(scope) if !scope.allows_host(host) => Err(ApiError::new(
+                ErrorCode::PermissionDenied,
+                format!("Token '{}' may not access host {host}", scope.name),
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The caller's identity when client isolation applies to it.
+    fn isolated_client(&self) -> Option<&str> {
+        self.principal
+            .as_deref()
+            .filter(|_| self.live().session_config.isolate_clients)
+    }
+
+    fn may_use(&self, host: &str, access: &SessionAccess) -> bool {
+        self.check_host(host).is_ok()
+            && self
+                .isolated_client()
+                .is_none_or(|client| access.allows(client))
+    }
+
+    /// Looks up a session the caller's token may reach and that it owns or was
+    /// shared with.
+    async fn session(&self, session_id: &str) -> PtyResult<Arc<Session>> {
+        let session = self.session_manager.get_session(session_id).await?;
+        self.check_host(&session.host)?;
+        if let Some(client) = self.isolated_client()
+            && !session.access().allows(client)
+        {
+            return Err(ApiError::new(
+                ErrorCode::PermissionDenied,
+                format!("Session {session_id} belongs to another client"),
+            )
+            .into());
+        }
+        Ok(session)
+    }
+
+    /// Like [`Self::session`], but only for the owner (close, share, transfer).
+    async fn owned_session(&self, session_id: &str) -> PtyResult<Arc<Session>> {
+        let session = self.session(session_id).await?;
+        if let Some(client) = self.isolated_client()
+            && !session.access().is_owner(client)
+        {
+            return Err(ApiError::new(
+                ErrorCode::PermissionDenied,
+                format!("Only the owner of session {session_id} may do this"),
+            )
+            .into());
+        }
+        Ok(session)
+    }
+
     pub async fn serve_stdio(self) -> PtyResult<()> {
         let running = self.serve(stdio()).await.map_err(|err| {
             ApiError::new(ErrorCode::IoError, "MCP stdio initialization failed")
@@ -62,7 +402,9 @@ impl McpServer {
         Ok(())
     }
 
-    pub async fn serve_http(self, listen: &str, auth_token: &str) -> PtyResult<()> {
+    /// Serves MCP over HTTP(S). Bearer tokens are checked against the live
+    /// config, so reloaded tokens apply to the next request.
+    pub async fn serve_http(self, listen: &str, tls: Option<TlsAcceptor>) -> PtyResult<()> {
         let addr: SocketAddr = listen.parse().map_err(|_| {
             ApiError::new(ErrorCode::InvalidArgument, "Invalid HTTP listen address")
         })?;
@@ -70,26 +412,33 @@ impl McpServer {
         let config = StreamableHttpServerConfig::default();
         let service_factory = {
             let server = self.clone();
-            move || Ok(server.clone())
+            move || Ok(server.for_connection(AuditTransport::Http, None))
         };
         let service = StreamableHttpService::new(service_factory, session_manager, config);
-        let auth_token = auth_token.to_string();
+        let live = self.live.clone();
+        let health = self.health_router();
         let router = Router::new()
             .route_service("/mcp", service)
+            .merge(self.metrics_router())
             .layer(middleware::from_fn(
-                move |req: axum::http::Request<axum::body::Body>, next: axum::middleware::Next| {
-                    let auth_token = auth_token.clone();
+                move |mut req: axum::http::Request<axum::body::Body>,
+                      next: axum::middleware::Next| {
+                    let live = live.read().expect("live config lock poisoned").clone();
                     async move {
-                        if auth_token.is_empty() {
+                        let tokens = &live.tokens;
+                        if !tokens.requires_auth() {
                             return next.run(req).await;
                         }
-                        let expected = format!("Bearer {}", auth_token);
-                        let authorized = req
+                        let grant = req
                             .headers()
                             .get(AUTHORIZATION)
                             .and_then(|value| value.to_str().ok())
-                            .is_some_and(|value| value == expected);
-                        if authorized {
+                            .and_then(|value| value.strip_prefix("Bearer "))
+                            .and_then(|token| tokens.authenticate(token));
+                        if let Some(grant) = grant {
+                            if let TokenGrant::Scoped(scope) = grant {
+                                req.extensions_mut().insert(scope);
+                            }
                             next.run(req).await
                         } else {
                             let mut response =
@@ -101,16 +450,82 @@ impl McpServer {
                         }
                     }
                 },
-            ));
+            ))
+            .merge(health);
         let listener = tokio::net::TcpListener::bind(addr).await.map_err(|err| {
             ApiError::new(ErrorCode::IoError, "HTTP bind failed").with_details(err.to_string())
         })?;
+        if let Some(acceptor) = tls {
+            return tls::serve(listener, acceptor, router).await;
+        }
         axum::serve(listener, router).await.map_err(|err| {
             ApiError::new(ErrorCode::IoError, "HTTP server failed").with_details(err.to_string())
         })?;
         Ok(())
     }
 
+    /// Serves `/metrics`, `/healthz` and `/readyz` on `listen` (for stdio mode, where
+    /// there is no HTTP server).
+    pub async fn serve_metrics(self, listen: &str) -> PtyResult<()> {
+        let addr: SocketAddr = listen.parse().map_err(|_| {
+            ApiError::new(ErrorCode::InvalidArgument, "Invalid metrics listen address")
+        })?;
+        let listener = tokio::net::TcpListener::bind(addr).await.map_err(|err| {
+            ApiError::new(ErrorCode::IoError, "Metrics bind failed").with_details(err.to_string())
+        })?;
+        axum::serve(listener, self.metrics_router().merge(self.health_router()))
+            .await
+            .map_err(|err| {
+                ApiError::new(ErrorCode::IoError, "Metrics server failed")
+                    .with_details(err.to_string())
+            })?;
+        Ok(())
+    }
+
+    /// Probe routes; mounted outside the bearer-token layer.
+    fn health_router(&self) -> Router {
+        let server = self.clone();
+        Router::new()
+            .route("/healthz", get(|| async { "ok\n" }))
+            .route(
+                "/readyz",
+                get(move || async move {
+                    let sessions = server.session_manager.list_sessions().await.sessions.len();
+                    let max_sessions = server.live().session_config.max_sessions;
+                    let ready =
+                        sessions < max_sessions && !server.session_manager.is_shutting_down();
+                    let status = if ready {
+                        StatusCode::OK
+                    } else {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    };
+                    (
+                        status,
+                        axum::Json(json!({
+                            "ready": ready,
+                            "sessions": sessions,
+                            "max_sessions": max_sessions,
+                        })),
+                    )
+                }),
+            )
+    }
+
+    fn metrics_router(&self) -> Router {
+        let server = self.clone();
+        Router::new().route(
+            "/metrics",
+            get(move || async move {
+                let sessions = server.session_manager.list_sessions().await;
+                let totals = server.session_manager.totals().await;
+                (
+                    [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+                    server.metrics.render(&sessions, totals),
+                )
+            }),
+        )
+    }
+
     async fn open_with_lock(&self, req: SessionOpenRequest) -> PtyResult<SessionOpenResponse> {
         let acquire_lock = req.acquire_lock.unwrap_or(false);
         if acquire_lock && req.task_id.is_none() {
@@ -120,7 +535,13 @@ impl McpServer {
             )
             .into());
         }
-        let mut response = self.session_manager.open_session(req.clone()).await?;
+        let started = Instant::now();
+        let opened = self.session_manager.open_session(req.clone()).await;
+        if !matches!(&opened, Ok(response) if response.existing_session_id.is_some()) {
+            self.metrics
+                .record_connect(started.elapsed(), opened.is_ok());
+        }
+        let mut response = opened?;
         if acquire_lock {
             if response.existing_session_id.is_none() {
                 let task_id = req.task_id.as_deref().expect("task_id checked before");
@@ -138,9 +559,126 @@ impl McpServer {
         Ok(response)
     }
 
+    /// Closes every session for a process shutdown (see [`SessionManager::shutdown`]),
+    /// audits each close and flushes the audit log.
+    pub async fn shutdown(&self, config: &ShutdownConfig) -> Vec<ShutdownEntry> {
+        let report = self
+            .session_manager
+            .shutdown(
+                &config.logout_commands,
+                Duration::from_millis(config.grace_period_ms),
+            )
+            .await;
+        let client = AuditClient::new(AuditTransport::Control, Some("shutdown".to_string()));
+        for entry in &report {
+            let event = AuditEvent {
+                event: "close",
+                session_id: Some(entry.session_id.clone()),
+                host: Some(entry.host.clone()),
+                command: Some(format!("shutdown={}", entry.outcome.as_str())),
+                ..AuditEvent::default()
+            };
+            let result = match &entry.error {
+                Some(error) => Err(ApiError::new(ErrorCode::IoError, error.clone()).into()),
+                None => Ok(()),
+            };
+            self.audit.record(&client, &event, Duration::ZERO, &result);
+        }
+        self.audit.flush();
+        report
+    }
+
+    /// Runs `action` and appends its outcome to the audit log (if enabled).
+    async fn audited<T>(
+        &self,
+        mut event: AuditEvent,
+        action: impl Future<Output = PtyResult<T>>,
+        finish: impl FnOnce(&mut AuditEvent, &T),
+    ) -> PtyResult<T> {
+        if !self.audit.is_enabled() {
+            return action.await;
+        }
+        self.fill_audit_target(&mut event).await;
+        let started = Instant::now();
+        let result = action.await;
+        if let Ok(value) = &result {
+            finish(&mut event, value);
+            self.fill_audit_target(&mut event).await;
+        }
+        self.audit
+            .record(&self.client, &event, started.elapsed(), &result);
+        result
+    }
+
+    async fn fill_audit_target(&self, event: &mut AuditEvent) {
+        if event.host.is_some() {
+            return;
+        }
+        let Some(session_id) = event.session_id.as_deref() else {
+            return;
+        };
+        if let Ok(session) = self.session_manager.get_session(session_id).await {
+            event.host = Some(session.host.clone());
+            event.port = Some(session.port);
+        }
+    }
+
     async fn handle_session(&self, req: SessionRequest) -> PtyResult<SessionResponse> {
+        let name = match req.action {
+            SessionAction::Open => "open",
+            SessionAction::Close => "close",
+            SessionAction::Lock => "lock",
+            SessionAction::Unlock => "unlock",
+            SessionAction::Heartbeat => "heartbeat",
+            SessionAction::Share => "share",
+            SessionAction::Transfer => "transfer",
+            SessionAction::List | SessionAction::Status | SessionAction::Profiles => {
+                return self.handle_session_inner(req).await;
+            }
+        };
+        let event = AuditEvent {
+            event: name,
+            session_id: req.session_id.clone(),
+            task_id: req.task_id.clone(),
+            host: req.host.clone(),
+            port: req.port,
+            command: match (&req.profile, &req.client_id) {
+                (Some(profile), _) => Some(format!("profile={profile}")),
+                (None, Some(client)) => Some(format!("client_id={client}")),
+                (None, None) => None,
+            },
+            ..AuditEvent::default()
+        };
+        self.audited(event, self.handle_session_inner(req), |event, response| {
+            if event.session_id.is_none() {
+                event.session_id = response.session_id.clone();
+            }
+        })
+        .await
+    }
+
+    async fn handle_session_inner(&self, mut req: SessionRequest) -> PtyResult<SessionResponse> {
         match req.action {
             SessionAction::Open => {
+                // Taken before the profile merges in its own, trusted options.
+                let ssh_redirect = req
+                    .ssh_options
+                    .as_ref()
+                    .and_then(SshOptions::redirecting_option);
+                if let Some(name) = req.profile.clone() {
+                    let live = self.live();
+                    let profile = live
+                        .hosts
+                        .iter()
+                        .find(|profile| profile.name == name)
+                        .ok_or_else(|| {
+                            ApiError::new(
+                                ErrorCode::NotFound,
+                                format!("Profile not found: {}", name),
+                            )
+                        })?;
+                    req.apply_profile(profile)?;
+                }
                 let protocol = req.protocol.ok_or_else(|| {
                     ApiError::new(ErrorCode::InvalidArgument, "protocol is required")
                 })?;
@@ -148,6 +686,32 @@ impl McpServer {
                     .host
                     .clone()
                     .ok_or_else(|| ApiError::new(ErrorCode::InvalidArgument, "host is required"))?;
+                self.check_host(&host)?;
+                for hop in req.jump_hosts.iter().flatten() {
+                    self.check_host(&hop.host)?;
+                }
+                if let (Some(scope), Some(option)) = (&self.scope, ssh_redirect) {
+                    return Err(ApiError::new(
+                        ErrorCode::PermissionDenied,
+                        format!("Token '{}' may not set {option}", scope.name),
+                    )
+                    .with_details("it can point ssh at a host outside the token's hosts")
+                    .into());
+                }
+                if let Some(auth) = req.auth.as_mut() {
+                    self.live()
+                        .secrets
+                        .resolve_auth(auth, &host, ssh_redirect)
+                        .await?;
+                }
+                for hop in req.jump_hosts.iter_mut().flatten() {
+                    if let Some(auth) = hop.auth.as_mut() {
+                        self.live()
+                            .secrets
+                            .resolve_auth(auth, &hop.host, ssh_redirect)
+                            .await?;
+                    }
+                }
                 let open_req = SessionOpenRequest {
                     protocol,
                     host,
@@ -157,14 +721,31 @@ impl McpServer {
                     pty: req.pty,
                     timeouts: req.timeouts,
                     ssh_options: req.ssh_options,
+                    jump_hosts: req.jump_hosts,
+                    reconnect: req.reconnect,
                     expect: req.expect,
                     session_type: req.session_type,
                     device_id: req.device_id,
                     acquire_lock: req.acquire_lock,
                     lock_ttl_ms: req.lock_ttl_ms,
                     task_id: req.task_id.clone(),
+                    profile: req.profile.clone(),
+                    opened_by: self
+                        .scope
+                        .as_ref()
+                        .map(|scope| scope.name.clone())
+                        .or_else(|| self.peer_identity.clone()),
+                    owner: self.principal.clone(),
+                    token_limit: self
+                        .scope
+                        .as_ref()
+                        .filter(|scope| scope.max_sessions > 0)
+                        .map(|scope| (scope.name.clone(), scope.max_sessions)),
                 };
                 let response = self.open_with_lock(open_req).await?;
+                if let Some(existing) = &response.existing_session_id {
+                    self.session(existing).await?;
+                }
                 Ok(SessionResponse {
                     action: SessionAction::Open,
                     success: true,
@@ -175,10 +756,12 @@ impl McpServer {
                     lock_acquired: response.lock_acquired,
                     existing_session_id: response.existing_session_id.clone(),
                     sessions: None,
+                    profiles: None,
                     capabilities: None,
                     lock_holder: None,
                     lock_expires_at: None,
                     message: None,
+                    client_id: None,
                 })
             }
             SessionAction::Close => {
@@ -186,6 +769,7 @@ impl McpServer {
                     ApiError::new(ErrorCode::InvalidArgument, "session_id is required")
                 })?;
                 let force = req.force.unwrap_or(false);
+                self.owned_session(session_id).await?;
                 self.session_manager
                     .close_session(session_id, force)
                     .await?;
@@ -199,14 +783,18 @@ impl McpServer {
                     lock_acquired: None,
                     existing_session_id: None,
                     sessions: None,
+                    profiles: None,
                     capabilities: None,
                     lock_holder: None,
                     lock_expires_at: None,
                     message: None,
+                    client_id: None,
                 })
             }
             SessionAction::List => {
-                let list = self.session_manager.list_sessions().await;
+                let mut list = self.session_manager.list_sessions().await;
+                list.sessions
+                    .retain(|entry| self.may_use(&entry.host, &entry.access));
                 Ok(SessionResponse {
                     action: SessionAction::List,
                     success: true,
@@ -217,10 +805,12 @@ impl McpServer {
                     lock_acquired: None,
                     existing_session_id: None,
                     sessions: Some(list.sessions),
+                    profiles: None,
                     capabilities: Some(session_capabilities()),
                     lock_holder: None,
                     lock_expires_at: None,
                     message: None,
+                    client_id: self.principal.clone(),
                 })
             }
             SessionAction::Lock => {
@@ -231,8 +821,21 @@ impl McpServer {
                     ApiError::new(ErrorCode::InvalidArgument, "task_id is required")
                 })?;
                 let ttl_ms = req.lock_ttl_ms.unwrap_or(DEFAULT_LOCK_TTL_MS);
-                let session = self.session_manager.get_session(session_id).await?;
-                let info = session.lock(task_id, ttl_ms).await?;
+                let force = req.force.unwrap_or(false);
+                if force && self.principal.is_some() {
+                    return Err(ApiError::new(
+                        ErrorCode::PermissionDenied,
+                        "force takes a lock over only from the control socket",
+                    )
+                    .with_details("wait for the holder to unlock or for the lock to expire")
+                    .into());
+                }
+                let session = self.session(session_id).await?;
+                let (info, previous) = if force {
+                    session.take_over_lock(task_id, ttl_ms).await
+                } else {
+                    (session.lock(task_id, ttl_ms).await?, None)
+                };
                 Ok(SessionResponse {
                     action: SessionAction::Lock,
                     success: true,
@@ -243,10 +846,12 @@ impl McpServer {
                     lock_acquired: Some(true),
                     existing_session_id: None,
                     sessions: None,
+                    profiles: None,
                     capabilities: None,
                     lock_holder: Some(info.task_id),
                     lock_expires_at: Some(info.expires_at),
-                    message: None,
+                    message: previous.map(|task| format!("Lock taken over from task {}", task)),
+                    client_id: None,
                 })
             }
             SessionAction::Unlock => {
@@ -256,7 +861,7 @@ impl McpServer {
                 let task_id = req.task_id.as_deref().ok_or_else(|| {
                     ApiError::new(ErrorCode::InvalidArgument, "task_id is required")
                 })?;
-                let session = self.session_manager.get_session(session_id).await?;
+                let session = self.session(session_id).await?;
                 session.unlock(task_id).await?;
                 Ok(SessionResponse {
                     action: SessionAction::Unlock,
@@ -268,10 +873,12 @@ impl McpServer {
                     lock_acquired: None,
                     existing_session_id: None,
                     sessions: None,
+                    profiles: None,
                     capabilities: None,
                     lock_holder: None,
                     lock_expires_at: None,
                     message: None,
+                    client_id: None,
                 })
             }
             SessionAction::Heartbeat => {
@@ -281,7 +888,7 @@ impl McpServer {
                 let task_id = req.task_id.as_deref().ok_or_else(|| {
                     ApiError::new(ErrorCode::InvalidArgument, "task_id is required")
                 })?;
-                let session = self.session_manager.get_session(session_id).await?;
+                let session = self.session(session_id).await?;
                 let info = session.heartbeat(task_id, req.lock_ttl_ms).await?;
                 Ok(SessionResponse {
                     action: SessionAction::Heartbeat,
@@ -293,17 +900,19 @@ impl McpServer {
                     lock_acquired: None,
                     existing_session_id: None,
                     sessions: None,
+                    profiles: None,
                     capabilities: None,
                     lock_holder: Some(info.task_id),
                     lock_expires_at: Some(info.expires_at),
                     message: None,
+                    client_id: None,
                 })
             }
             SessionAction::Status => {
                 let session_id = req.session_id.as_deref().ok_or_else(|| {
                     ApiError::new(ErrorCode::InvalidArgument, "session_id is required")
                 })?;
-                let session = self.session_manager.get_session(session_id).await?;
+                let session = self.session(session_id).await?;
                 let info = session.lock_status().await;
                 Ok(SessionResponse {
                     action: SessionAction::Status,
@@ -315,44 +924,314 @@ impl McpServer {
                     lock_acquired: None,
                     existing_session_id: None,
                     sessions: None,
+                    profiles: None,
                     capabilities: None,
                     lock_holder: info.as_ref().map(|lock| lock.task_id.clone()),
                     lock_expires_at: info.map(|lock| lock.expires_at),
                     message: None,
+                    client_id: None,
+                })
+            }
+            SessionAction::Share | SessionAction::Transfer => {
+                let session_id = req.session_id.as_deref().ok_or_else(|| {
+                    ApiError::new(ErrorCode::InvalidArgument, "session_id is required")
+                })?;
+                let client = req
+                    .client_id
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|client| !client.is_empty())
+                    .ok_or_else(|| {
+                        ApiError::new(ErrorCode::InvalidArgument, "client_id is required")
+                    })?;
+                let session = self.owned_session(session_id).await?;
+                let message = if matches!(req.action, SessionAction::Share) {
+                    session.share(client);
+                    format!("Shared with {client}")
+                } else {
+                    session.transfer(client);
+                    format!("Transferred to {client}")
+                };
+                Ok(SessionResponse {
+                    action: req.action,
+                    success: true,
+                    session_id: Some(session_id.to_string()),
+                    protocol: None,
+                    pty_enabled: None,
+                    security_warning: None,
+                    lock_acquired: None,
+                    existing_session_id: None,
+                    sessions: None,
+                    profiles: None,
+                    capabilities: None,
+                    lock_holder: None,
+                    lock_expires_at: None,
+                    message: Some(message),
+                    client_id: Some(client.to_string()),
                 })
             }
+            SessionAction::Profiles => Ok(SessionResponse {
+                action: SessionAction::Profiles,
+                success: true,
+                session_id: None,
+                protocol: None,
+                pty_enabled: None,
+                security_warning: None,
+                lock_acquired: None,
+                existing_session_id: None,
+                sessions: None,
+                profiles: Some(
+                    self.live()
+                        .hosts
+                        .iter()
+                        .map(ProfileSummary::from_profile)
+                        .collect(),
+                ),
+                capabilities: None,
+                lock_holder: None,
+                lock_expires_at: None,
+                message: None,
+                client_id: None,
+            }),
+        }
+    }
+
+    async fn handle_elicited_write(
+        &self,
+        req: SessionIoRequest,
+        elicit: ElicitInput,
+        elicitor: Elicitor,
+    ) -> PtyResult<SessionIoResponse> {
+        let event = AuditEvent {
+            event: "write",
+            session_id: Some(req.session_id.clone()),
+            task_id: req.task_id.clone(),
+            command: Some("[elicited]".to_string()),
+            ..AuditEvent::default()
+        };
+        self.audited(
+            event,
+            self.handle_elicited_write_inner(req, elicit, elicitor),
+            |_, _| {},
+        )
+        .await
+    }
+
+    async fn handle_elicited_write_inner(
+        &self,
+        req: SessionIoRequest,
+        elicit: ElicitInput,
+        elicitor: Elicitor,
+    ) -> PtyResult<SessionIoResponse> {
+        if req.data.is_some() || req.key.is_some() || req.secret_ref.is_some() {
+            return Err(ApiError::new(
+                ErrorCode::InvalidArgument,
+                "elicit cannot be combined with data, key or secret_ref",
+            )
+            .into());
+        }
+        let session = self.session(&req.session_id).await?;
+        session.ensure_write_access(req.task_id.as_deref()).await?;
+        session.ensure_empty_input_line()?;
+        if !elicitor.supports_elicitation() {
+            return Err(ApiError::new(
+                ErrorCode::Unsupported,
+                "The MCP client does not support elicitation",
+            )
+            .into());
+        }
+
+        let tail = session.tail(4096, Some(5));
+        let (tail_bytes, _) = self.live().redactor.redact(&tail.bytes);
+        let device_output = device_output_excerpt(&tail_bytes);
+        let mut message = match elicit.kind {
+            ElicitKind::Secret => "The remote session is asking for a secret.",
+            ElicitKind::Confirm => "The remote session is asking for confirmation.",
+        }
+        .to_string();
+        if let Some(note) = elicit.message.as_deref().map(str::trim)
+            && !note.is_empty()
+        {
+            message.push_str(&format!(
+                "\n\nThe agent says (quoted):\n{}",
+                quoted_excerpt(note, ELICIT_NOTE_MAX_CHARS)
+            ));
+        }
+        message.push_str(&format!(
+            "\n\nLast line of output from {}:{} (untrusted device output, quoted):\n{device_output}",
+            session.host, session.port
+        ));
+        let schema = match elicit.kind {
+            ElicitKind::Secret => ElicitationSchema::builder()
+                .required_string_property("value", |schema| {
+                    schema.description("Sent to the session; never shown to the model")
+                }),
+            ElicitKind::Confirm => ElicitationSchema::builder()
+                .required_bool_property("confirm", |schema| schema.description("Answer yes")),
+        }
+        .build()
+        .map_err(|err| {
+            ApiError::new(ErrorCode::IoError, "Invalid elicitation schema").with_details(err)
+        })?;
+
+        let result = elicitor
+            .create_elicitation(CreateElicitationRequestParam {
+                message,
+                requested_schema: schema,
+            })
+            .await?;
+        let (outcome, bytes_written) = match result.action {
+            ElicitationAction::Accept => {
+                let content = result.content.unwrap_or_default();
+                let answer = match elicit.kind {
+                    ElicitKind::Secret => content
+                        .get("value")
+                        .and_then(|value| value.as_str())
+                        .map(str::to_string),
+                    ElicitKind::Confirm => content
+                        .get("confirm")
+                        .and_then(|value| value.as_bool())
+                        .map(|confirm| if confirm { "y" } else { "n" }.to_string()),
+                }
+                .ok_or_else(|| {
+                    ApiError::new(
+                        ErrorCode::InvalidArgument,
+                        "Elicitation response is missing the answer",
+                    )
+                })?;
+                let written = session.write_secret(answer.as_bytes()).await?
+                    + session.send_key(SessionKey::Enter).await?;
+                (ElicitationOutcome::Accepted, written)
+            }
+            ElicitationAction::Decline => (ElicitationOutcome::Declined, 0),
+            ElicitationAction::Cancel => (ElicitationOutcome::Cancelled, 0),
+        };
+        Ok(SessionIoResponse {
+            action: IoAction::Write,
+            bytes_written: Some(bytes_written),
+            chunk: None,
+            encoding: None,
+            next_cursor: None,
+            buffer_start_cursor: None,
+            buffer_end_cursor: None,
+            matched: None,
+            idle_reached: None,
+            timed_out: None,
+            eof: None,
+            waiting_for_input: None,
+            truncated: None,
+            dropped_bytes: None,
+            buffered_bytes: None,
+            buffer_limit_bytes: None,
+            elicitation: Some(outcome),
+            redactions: None,
+        })
+    }
+
+    async fn handle_session_io(
+        &self,
+        req: SessionIoRequest,
+        elicitor: Option<&Elicitor>,
+    ) -> PtyResult<SessionIoResponse> {
+        if !matches!(req.action, IoAction::Write) {
+            return self.handle_session_io_inner(req, elicitor).await;
         }
+        let event = AuditEvent {
+            event: "write",
+            session_id: Some(req.session_id.clone()),
+            task_id: req.task_id.clone(),
+            command: Some(self.audit_write_text(&req)),
+            sensitive: req.sensitive.filter(|sensitive| *sensitive),
+            ..AuditEvent::default()
+        };
+        self.audited(
+            event,
+            self.handle_session_io_inner(req, elicitor),
+            |_, _| {},
+        )
+        .await
     }
 
-    async fn handle_session_io(&self, req: SessionIoRequest) -> PtyResult<SessionIoResponse> {
-        let session = self.session_manager.get_session(&req.session_id).await?;
+    /// What a write sent, for the audit log. Data is redacted like output,
+    /// `sensitive` or not; secret references are recorded by name.
+    fn audit_write_text(&self, req: &SessionIoRequest) -> String {
+        if let Some(reference) = &req.secret_ref {
+            return format!("secret_ref={reference}");
+        }
+        if let Some(key) = &req.key {
+            return serde_json::to_value(key)
+                .ok()
+                .and_then(|value| value.as_str().map(|key| format!("key={key}")))
+                .unwrap_or_else(|| "key".to_string());
+        }
+        let bytes = req
+            .data
+            .as_deref()
+            .and_then(|data| decode_payload(data, req.encoding.clone().unwrap_or_default()).ok())
+            .unwrap_or_default();
+        self.live()
+            .redactor
+            .redact_text(&String::from_utf8_lossy(&bytes))
+            .0
+    }
+
+    async fn handle_session_io_inner(
+        &self,
+        req: SessionIoRequest,
+        elicitor: Option<&Elicitor>,
+    ) -> PtyResult<SessionIoResponse> {
+        let session = self.session(&req.session_id).await?;
         match req.action {
             IoAction::Write => {
                 session.ensure_write_access(req.task_id.as_deref()).await?;
-                match (&req.data, &req.key) {
-                    (Some(_), Some(_)) => {
+                if req.elicit.is_some() {
+                    return Err(ApiError::new(
+                        ErrorCode::Unsupported,
+                        "elicit requires an MCP client that supports elicitation",
+                    )
+                    .into());
+                }
+                let inputs = [
+                    req.data.is_some(),
+                    req.key.is_some(),
+                    req.secret_ref.is_some(),
+                ];
+                match inputs.iter().filter(|set| **set).count() {
+                    0 => {
                         return Err(ApiError::new(
                             ErrorCode::InvalidArgument,
-                            "Specify either data or key, not both",
+                            "data, key or secret_ref is required",
                         )
                         .into());
                     }
-                    (None, None) => {
+                    1 => {}
+                    _ => {
                         return Err(ApiError::new(
                             ErrorCode::InvalidArgument,
-                            "data or key is required",
+                            "Specify only one of data, key or secret_ref",
                         )
                         .into());
                     }
-                    _ => {}
                 }
                 let sensitive = req.sensitive.unwrap_or(false);
-                let bytes_written = if let Some(data) = &req.data {
+                let bytes_written = if let Some(reference) = &req.secret_ref {
+                    let value = self
+                        .live()
+                        .secrets
+                        .resolve(reference, &session.host)
+                        .await?;
+                    session.write_secret(value.as_bytes()).await?
+                } else if let Some(data) = &req.data {
                     let encoding = req.encoding.unwrap_or_default();
                     let bytes = decode_payload(data, encoding)?;
+                    self.enforce_input_policy(&session, &bytes, req.task_id.as_deref(), elicitor)
+                        .await?;
                     session.write(&bytes, sensitive).await?
                 } else if let Some(key) = req.key {
-                    session.send_key(key).await?
+                    let bytes = session.key_bytes(key)?;
+                    self.enforce_input_policy(&session, &bytes, req.task_id.as_deref(), elicitor)
+                        .await?;
+                    session.write(&bytes, false).await?
                 } else {
                     0
                 };
@@ -373,6 +1252,8 @@ impl McpServer {
                     dropped_bytes: None,
                     buffered_bytes: None,
                     buffer_limit_bytes: None,
+                    elicitation: None,
+                    redactions: None,
                 })
             }
             IoAction::Read => {
@@ -408,6 +1289,8 @@ impl McpServer {
                             dropped_bytes: Some(read.dropped_bytes),
                             buffered_bytes: Some(read.buffered_bytes),
                             buffer_limit_bytes: Some(read.buffer_limit_bytes),
+                            elicitation: None,
+                            redactions: Some(read.redactions),
                         })
                     }
                     ReadMode::Tail => {
@@ -435,6 +1318,8 @@ impl McpServer {
                             dropped_bytes: None,
                             buffered_bytes: Some(tail.buffered_bytes),
                             buffer_limit_bytes: Some(tail.buffer_limit_bytes),
+                            elicitation: None,
+                            redactions: Some(tail.redactions),
                         })
                     }
                 }
@@ -442,11 +1327,132 @@ impl McpServer {
         }
     }
 
+    /// Applies the command policy. `require_approval` matches, and confirmations when
+    /// no elicitation-capable client is connected, wait in the approval queue.
+    /// Checks the lines a write submits, together with what was typed before
+    /// it since the last Enter. Writes that do not press Enter pass; their
+    /// text is checked with the Enter that submits it.
+    async fn enforce_input_policy(
+        &self,
+        session: &Session,
+        bytes: &[u8],
+        task_id: Option<&str>,
+        elicitor: Option<&Elicitor>,
+    ) -> PtyResult<()> {
+        let submitted = session.submitted_input(bytes);
+        if submitted.is_empty() {
+            return Ok(());
+        }
+        self.enforce_policy(session, "write", &submitted, task_id, elicitor)
+            .await
+    }
+
+    async fn enforce_policy(
+        &self,
+        session: &Session,
+        action: &str,
+        command: &str,
+        task_id: Option<&str>,
+        elicitor: Option<&Elicitor>,
+    ) -> PtyResult<()> {
+        let (matched, confirm_elicitor) =
+            match self
+                .live()
+                .policy
+                .check(&session.host, session.profile.as_deref(), command)?
+            {
+                PolicyDecision::Allow => return Ok(()),
+                PolicyDecision::Approve(matched) => (matched, None),
+                PolicyDecision::Confirm(matched) => (
+                    matched,
+                    elicitor.filter(|elicitor| elicitor.supports_elicitation()),
+                ),
+            };
+        let Some(elicitor) = confirm_elicitor else {
+            return self
+                .approvals
+                .wait_for_decision(PendingApproval {
+                    approval_id: String::new(),
+                    session_id: session.id.clone(),
+                    host: session.host.clone(),
+                    port: session.port,
+                    profile: session.profile.clone(),
+                    task_id: task_id.map(str::to_string),
+                    action: action.to_string(),
+                    command: command.trim_end().to_string(),
+                    rule: matched.rule,
+                    pattern: matched.pattern,
+                    requested_at_ms: 0,
+                    expires_at_ms: 0,
+                })
+                .await;
+        };
+        let schema = ElicitationSchema::builder()
+            .required_bool_property("confirm", |schema| schema.description("Run the command"))
+            .build()
+            .map_err(|err| {
+                ApiError::new(ErrorCode::IoError, "Invalid elicitation schema").with_details(err)
+            })?;
+        let result = elicitor
+            .create_elicitation(CreateElicitationRequestParam {
+                message: format!(
+                    "Policy rule '{}' requires confirmation before sending to {}:{}:\n\n{}",
+                    matched.rule,
+                    session.host,
+                    session.port,
+                    command.trim_end()
+                ),
+                requested_schema: schema,
+            })
+            .await?;
+        let confirmed = result.action == ElicitationAction::Accept
+            && result
+                .content
+                .as_ref()
+                .and_then(|content| content.get("confirm"))
+                .and_then(|value| value.as_bool())
+                == Some(true);
+        if confirmed {
+            Ok(())
+        } else {
+            Err(ApiError::new(
+                ErrorCode::PolicyDenied,
+                format!(
+                    "Command requires confirmation by policy rule '{}': the human declined",
+                    matched.rule
+                ),
+            )
+            .with_details(format!("matched: {}", matched.pattern))
+            .into())
+        }
+    }
+
     async fn handle_session_config(
         &self,
         req: SessionConfigRequest,
     ) -> PtyResult<SessionConfigResponse> {
-        let session = self.session_manager.get_session(&req.session_id).await?;
+        if !matches!(req.action, ConfigAction::Resize) {
+            return self.handle_session_config_inner(req).await;
+        }
+        let event = AuditEvent {
+            event: "resize",
+            session_id: Some(req.session_id.clone()),
+            command: Some(format!(
+                "{}x{}",
+                req.cols.unwrap_or_default(),
+                req.rows.unwrap_or_default()
+            )),
+            ..AuditEvent::default()
+        };
+        self.audited(event, self.handle_session_config_inner(req), |_, _| {})
+            .await
+    }
+
+    async fn handle_session_config_inner(
+        &self,
+        req: SessionConfigRequest,
+    ) -> PtyResult<SessionConfigResponse> {
+        let session = self.session(&req.session_id).await?;
         match req.action {
             ConfigAction::Resize => {
                 let cols = req
@@ -494,10 +1500,10 @@ impl McpServer {
     }
 
     async fn handle_read(&self, req: SessionReadRequest) -> PtyResult<SessionReadResponse> {
-        let session = self.session_manager.get_session(&req.session_id).await?;
+        let session = self.session(&req.session_id).await?;
         let timeout_ms = req
             .timeout_ms
-            .unwrap_or(self.session_config.default_read_timeout_ms);
+            .unwrap_or(self.live().session_config.default_read_timeout_ms);
         let max_bytes = req.max_bytes.unwrap_or(65536);
         let include_match = req.include_match.unwrap_or(true);
 
@@ -523,8 +1529,11 @@ impl McpServer {
         )
         .await?;
 
-        let (text, actual_encoding) =
-            encode_chunk(&read.slice.bytes, req.encoding.unwrap_or_default());
+        let start = read
+            .next_cursor
+            .saturating_sub(read.slice.bytes.len() as u64);
+        let (bytes, redactions) = self.redact_output(&session, start, &read.slice.bytes);
+        let (text, actual_encoding) = encode_chunk(&bytes, req.encoding.unwrap_or_default());
         Ok(SessionReadResponse {
             chunk: text,
             encoding: actual_encoding,
@@ -540,16 +1549,18 @@ impl McpServer {
             dropped_bytes: read.slice.dropped_bytes,
             buffered_bytes: read.slice.buffered_bytes,
             buffer_limit_bytes: read.slice.buffer_limit_bytes,
+            redactions,
         })
     }
 
     async fn handle_tail(&self, req: SessionTailRequest) -> PtyResult<SessionTailResponse> {
-        let session = self.session_manager.get_session(&req.session_id).await?;
+        let session = self.session(&req.session_id).await?;
         let max_bytes = req.max_bytes.unwrap_or(65536);
         let max_lines = req.max_lines;
         let encoding = req.encoding.unwrap_or_default();
         let tail = session.tail(max_bytes, max_lines);
-        let (tail_text, actual_encoding) = encode_chunk(&tail.bytes, encoding);
+        let (bytes, redactions) = self.redact_output(&session, tail.start_cursor, &tail.bytes);
+        let (tail_text, actual_encoding) = encode_chunk(&bytes, encoding);
         Ok(SessionTailResponse {
             tail: tail_text,
             encoding: actual_encoding,
@@ -560,15 +1571,52 @@ impl McpServer {
             truncated: tail.truncated,
             buffered_bytes: tail.buffered_bytes,
             buffer_limit_bytes: tail.buffer_limit_bytes,
+            redactions,
         })
     }
 
-    async fn handle_exec(&self, req: SessionExecRequest) -> PtyResult<SessionExecResponse> {
-        let session = self.session_manager.get_session(&req.session_id).await?;
+    async fn handle_exec(
+        &self,
+        req: SessionExecRequest,
+        elicitor: Option<&Elicitor>,
+    ) -> PtyResult<SessionExecResponse> {
+        let event = AuditEvent {
+            event: "exec",
+            session_id: Some(req.session_id.clone()),
+            task_id: req.task_id.clone(),
+            command: Some(self.live().redactor.redact_text(&req.cmd).0),
+            ..AuditEvent::default()
+        };
+        let started = Instant::now();
+        let result = self
+            .audited(
+                event,
+                self.handle_exec_inner(req, elicitor),
+                |event, response| {
+                    event.exit_code = response.exit_code;
+                },
+            )
+            .await;
+        let done_reason = result
+            .as_ref()
+            .map_or("error", |response| response.done_reason.as_str());
+        self.metrics.record_exec(done_reason, started.elapsed());
+        result
+    }
+
+    async fn handle_exec_inner(
+        &self,
+        req: SessionExecRequest,
+        elicitor: Option<&Elicitor>,
+    ) -> PtyResult<SessionExecResponse> {
+        let session = self.session(&req.session_id).await?;
         session.ensure_write_access(req.task_id.as_deref()).await?;
+        let command = session.submitted_input(format!("{}\n", req.cmd).as_bytes());
+        self.enforce_policy(&session, "exec", &command, req.task_id.as_deref(), elicitor)
+            .await?;
         let timeout_ms = req
             .timeout_ms
-            .unwrap_or(self.session_config.default_exec_timeout_ms);
+            .unwrap_or(self.live().session_config.default_exec_timeout_ms);
         let until_idle_ms = req.until_idle_ms;
         let rc_mode = req.rc_mode.unwrap_or(RcMode {
             enabled: Some(true),
@@ -694,6 +1742,7 @@ impl McpServer {
             (output_text, None, Some("unsupported".to_string()))
         };
 
+        let (stdout, redactions) = self.live().redactor.redact_text(&stdout);
         let error_hints = extract_error_hints(&stdout, &error_regexes);
         Ok(SessionExecResponse {
             stdout,
@@ -709,12 +1758,17 @@ impl McpServer {
             },
             timed_out,
             duration_ms: start_time.elapsed().as_millis() as u64,
+            redactions,
         })
     }
 
-    async fn handle_control_request(&self, request: ControlRpcRequest) -> ControlRpcResponse {
+    async fn handle_control_request(
+        &self,
+        request: ControlRpcRequest,
+        elicitor: Option<Elicitor>,
+    ) -> ControlRpcResponse {
         let id = request.id.clone().unwrap_or(Value::Null);
-        let result = self.dispatch_control_method(request).await;
+        let result = self.dispatch_control_method(request, elicitor).await;
         match result {
             Ok(value) => ControlRpcResponse::success(id, value),
             Err(err) => ControlRpcResponse::error(id, err),
@@ -725,42 +1779,130 @@ impl McpServer {
         &self,
         request: ControlRpcRequest,
         mode: ControlMode,
+        elicitor: Option<Elicitor>,
     ) -> ControlRpcResponse {
-        if matches!(mode, ControlMode::Readonly) && !self.is_readonly_control_request(&request) {
+        if matches!(mode, ControlMode::Readonly)
+            && !is_readonly_call(&request.method, request.params.as_ref())
+        {
             return ControlRpcResponse::error(
                 request.id.clone().unwrap_or(Value::Null),
                 ApiError::new(ErrorCode::Unsupported, "Control mode is readonly").into(),
             );
         }
-        self.handle_control_request(request).await
+        self.handle_control_request(request, elicitor).await
     }
 
-    fn is_readonly_control_request(&self, request: &ControlRpcRequest) -> bool {
-        match request.method.as_str() {
-            "ptyctl_session" => request
-                .params
-                .as_ref()
-                .and_then(|params| serde_json::from_value::<SessionRequest>(params.clone()).ok())
-                .is_some_and(|req| {
-                    matches!(req.action, SessionAction::List | SessionAction::Status)
-                }),
-            "ptyctl_session_io" => request
-                .params
-                .as_ref()
-                .and_then(|params| serde_json::from_value::<SessionIoRequest>(params.clone()).ok())
-                .is_some_and(|req| matches!(req.action, IoAction::Read)),
-            "ptyctl_session_config" => request
-                .params
-                .as_ref()
-                .and_then(|params| {
-                    serde_json::from_value::<SessionConfigRequest>(params.clone()).ok()
-                })
-                .is_some_and(|req| matches!(req.action, ConfigAction::Get)),
-            _ => false,
+    async fn list_session_resources(&self) -> ListResourcesResult {
+        let list = self.session_manager.list_sessions().await;
+        let mut resources = Vec::with_capacity(list.sessions.len() * 3);
+        for entry in list.sessions {
+            if !self.may_use(&entry.host, &entry.access) {
+                continue;
+            }
+            for kind in SessionResourceKind::ALL {
+                let mut resource = RawResource::new(
+                    session_resource_uri(&entry.session_id, kind),
+                    format!("{} {}", entry.session_id, kind.as_str()),
+                );
+                resource.description = Some(format!(
+                    "{} of {}:{}",
+                    kind.description(),
+                    entry.host,
+                    entry.port
+                ));
+                resource.mime_type = Some("text/plain".to_string());
+                resources.push(resource.no_annotation());
+            }
+        }
+        ListResourcesResult::with_all_items(resources)
+    }
+
+    async fn read_session_resource(&self, uri: &str) -> PtyResult<ReadResourceResult> {
+        let (session_id, kind) = parse_session_resource_uri(uri)?;
+        let session = self.session(&session_id).await?;
+        let bytes = match kind {
+            SessionResourceKind::Tail | SessionResourceKind::Screen => {
+                session.tail(RESOURCE_TAIL_BYTES, None).bytes
+            }
+            SessionResourceKind::Transcript => session.buffer_snapshot().bytes,
+        };
+        let (bytes, _) = self.live().redactor.redact(&bytes);
+        let text = match kind {
+            SessionResourceKind::Screen => {
+                let (cols, rows) = session.pty_size();
+                render_screen(&bytes, cols, rows)
+            }
+            _ => String::from_utf8_lossy(&bytes).into_owned(),
+        };
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some("text/plain".to_string()),
+                text,
+                meta: None,
+            }],
+        })
+    }
+
+    async fn subscribe_session_resource(
+        &self,
+        uri: String,
+        peer: Peer<RoleServer>,
+    ) -> PtyResult<()> {
+        let (session_id, _) = parse_session_resource_uri(&uri)?;
+        let session = self.session(&session_id).await?;
+        let task_uri = uri.clone();
+        let task = tokio::spawn(async move {
+            let mut watcher = SessionWatcher::new(session, None, RESOURCE_TAIL_BYTES);
+            loop {
+                let events = watcher.next_events().await;
+                let closed = watcher.is_closed();
+                let changed = events
+                    .iter()
+                    .any(|event| matches!(event, SessionEvent::Output { .. }));
+                if (changed || closed)
+                    && peer
+                        .notify_resource_updated(ResourceUpdatedNotificationParam {
+                            uri: task_uri.clone(),
+                        })
+                        .await
+                        .is_err()
+                {
+                    break;
+                }
+                if closed {
+                    break;
+                }
+                tokio::time::sleep(RESOURCE_UPDATE_DEBOUNCE).await;
+            }
+        });
+        let previous = self
+            .resource_subscriptions
+            .lock()
+            .expect("subscription mutex poisoned")
+            .insert(uri, task.abort_handle());
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    fn unsubscribe_session_resource(&self, uri: &str) {
+        let removed = self
+            .resource_subscriptions
+            .lock()
+            .expect("subscription mutex poisoned")
+            .remove(uri);
+        if let Some(task) = removed {
+            task.abort();
         }
     }
 
-    async fn dispatch_control_method(&self, request: ControlRpcRequest) -> PtyResult<Value> {
+    async fn dispatch_control_method(
+        &self,
+        request: ControlRpcRequest,
+        elicitor: Option<Elicitor>,
+    ) -> PtyResult<Value> {
         let method = request.method.as_str();
         let params = request.params.unwrap_or(Value::Null);
         match method {
@@ -770,8 +1912,14 @@ impl McpServer {
                 Ok(serde_json::to_value(resp)?)
             }
             "ptyctl_session_io" => {
-                let req: SessionIoRequest = serde_json::from_value(params)?;
-                let resp = self.handle_session_io(req).await?;
+                let mut req: SessionIoRequest = serde_json::from_value(params)?;
+                let resp = match (req.action, elicitor) {
+                    (IoAction::Write, Some(elicitor)) if req.elicit.is_some() => {
+                        let elicit = req.elicit.take().expect("elicit is set");
+                        self.handle_elicited_write(req, elicit, elicitor).await?
+                    }
+                    (_, elicitor) => self.handle_session_io(req, elicitor.as_ref()).await?,
+                };
                 Ok(serde_json::to_value(resp)?)
             }
             "ptyctl_session_config" => {
@@ -781,12 +1929,73 @@ impl McpServer {
             }
             "ptyctl_session_exec" => {
                 let req: SessionExecRequest = serde_json::from_value(params)?;
-                let resp = self.handle_exec(req).await?;
+                let resp = self.handle_exec(req, elicitor.as_ref()).await?;
+                Ok(serde_json::to_value(resp)?)
+            }
+            "ptyctl_approvals" => {
+                let req: ApprovalsRequest = serde_json::from_value(params)?;
+                let resp = self.handle_approvals(req)?;
+                Ok(serde_json::to_value(resp)?)
+            }
+            "server_info" => Ok(serde_json::to_value(self.server_info().await)?),
+            "reload" => Ok(serde_json::to_value(self.request_reload().await?)?),
+            "resources/list" => Ok(serde_json::to_value(self.list_session_resources().await)?),
+            "resources/read" => {
+                let req: ReadResourceRequestParam = serde_json::from_value(params)?;
+                let resp = self.read_session_resource(&req.uri).await?;
                 Ok(serde_json::to_value(resp)?)
             }
             _ => Err(ApiError::new(ErrorCode::InvalidArgument, "Unknown method").into()),
         }
     }
+
+    /// The tools this server exposes, for clients that forward calls to it.
+    pub(crate) fn tool_definitions() -> Vec<Tool> {
+        Self::tool_router().list_all()
+    }
+
+    async fn request_reload(&self) -> PtyResult<ReloadReport> {
+        let requests = self.reload_requests.as_ref().ok_or_else(|| {
+            ApiError::new(
+                ErrorCode::Unsupported,
+                "This server does not reload its config",
+            )
+        })?;
+        let not_running = || ApiError::new(ErrorCode::IoError, "Config reloader is not running");
+        let (reply, outcome) = oneshot::channel();
+        requests.send(reply).await.map_err(|_| not_running())?;
+        outcome.await.map_err(|_| not_running())?
+    }
+
+    async fn server_info(&self) -> ServerInfoResponse {
+        ServerInfoResponse {
+            version: VERSION.to_string(),
+            pid: std::process::id(),
+            started_at_ms: self.started_at_ms,
+            uptime_ms: self.started_at.elapsed().as_millis() as u64,
+            sessions: self.session_manager.list_sessions().await.sessions.len(),
+            pending_approvals: self.approvals.list().len(),
+            config: self.live().summary.clone(),
+        }
+    }
+
+    fn handle_approvals(&self, req: ApprovalsRequest) -> PtyResult<ApprovalsResponse> {
+        let decided = match req.action {
+            ApprovalsAction::List => None,
+            ApprovalsAction::Approve | ApprovalsAction::Reject => {
+                let approval_id = req.approval_id.ok_or_else(|| {
+                    ApiError::new(ErrorCode::InvalidArgument, "approval_id is required")
+                })?;
+                let approve = matches!(req.action, ApprovalsAction::Approve);
+                Some(self.approvals.decide(&approval_id, approve, req.reason)?)
+            }
+        };
+        Ok(ApprovalsResponse {
+            action: req.action,
+            pending: decided.is_none().then(|| self.approvals.list()),
+            decided,
+        })
+    }
 }
 
 #[tool_router]
@@ -798,8 +2007,14 @@ impl McpServer {
     async fn session_tool(
         &self,
         params: Parameters<SessionRequest>,
+        extensions: Extensions,
     ) -> Result<CallToolResult, McpError> {
-        let response = self.handle_session(params.0).await.map_err(map_pty_error)?;
+        let mut req = params.0;
+        let server = self
+            .authorize_tool(&extensions, "ptyctl_session", &req)
+            .map_err(map_pty_error)?;
+        server.default_task_id(&mut req.task_id);
+        let response = server.handle_session(req).await.map_err(map_pty_error)?;
         structured_result(response)
     }
 
@@ -810,11 +2025,22 @@ impl McpServer {
     async fn session_io_tool(
         &self,
         params: Parameters<SessionIoRequest>,
+        peer: Peer<RoleServer>,
+        extensions: Extensions,
     ) -> Result<CallToolResult, McpError> {
-        let response = self
-            .handle_session_io(params.0)
-            .await
+        let mut req = params.0;
+        let server = self
+            .authorize_tool(&extensions, "ptyctl_session_io", &req)
             .map_err(map_pty_error)?;
+        server.default_task_id(&mut req.task_id);
+        let elicitor = Elicitor::Peer(peer);
+        let response = match (req.action, req.elicit.take()) {
+            (IoAction::Write, Some(elicit)) => {
+                server.handle_elicited_write(req, elicit, elicitor).await
+            }
+            _ => server.handle_session_io(req, Some(&elicitor)).await,
+        }
+        .map_err(map_pty_error)?;
         structured_result(response)
     }
 
@@ -825,8 +2051,12 @@ impl McpServer {
     async fn session_config_tool(
         &self,
         params: Parameters<SessionConfigRequest>,
+        extensions: Extensions,
     ) -> Result<CallToolResult, McpError> {
-        let response = self
+        let server = self
+            .authorize_tool(&extensions, "ptyctl_session_config", &params.0)
+            .map_err(map_pty_error)?;
+        let response = server
             .handle_session_config(params.0)
             .await
             .map_err(map_pty_error)?;
@@ -840,48 +2070,156 @@ impl McpServer {
     async fn session_exec_tool(
         &self,
         params: Parameters<SessionExecRequest>,
+        peer: Peer<RoleServer>,
+        extensions: Extensions,
     ) -> Result<CallToolResult, McpError> {
-        let response = self.handle_exec(params.0).await.map_err(map_pty_error)?;
+        let mut req = params.0;
+        let server = self
+            .authorize_tool(&extensions, "ptyctl_session_exec", &req)
+            .map_err(map_pty_error)?;
+        server.default_task_id(&mut req.task_id);
+        let response = server
+            .handle_exec(req, Some(&Elicitor::Peer(peer)))
+            .await
+            .map_err(map_pty_error)?;
         structured_result(response)
     }
 }
 
 #[tool_handler]
 impl rmcp::ServerHandler for McpServer {
-    fn get_info(&self) -> ServerInfo {
-        let server_info = Implementation {
-            name: "ptyctl".to_string(),
-            title: Some("ptyctl".to_string()),
-            version: env!("PTYCTL_VERSION_LABEL").to_string(),
-            icons: None,
-            website_url: None,
-        };
-        ServerInfo {
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
-            instructions: Some(
-                concat!(
-                    "Tool inputs are validated against the JSON schema; incorrect types or enum values return invalid_params.\n",
-                    "Use ptyctl_session action=open to create a session_id; other tools require it.\n",
-                    "Open parameters:\n",
-                    "- protocol: \"ssh\" or \"telnet\" (no \"local\").\n",
-                    "- auth: object (SshAuth). For password auth: {\"password\":\"...\"}. Do not pass JSON-encoded strings.\n",
-                    "- pty: object with enabled/cols/rows/term; omit to use defaults.\n",
-                    "- expect: object with optional prompt_regex/pager_regexes/error_regexes; do not pass a raw string.\n",
-                    "- For action=open, protocol and host are required; for other actions, session_id is required.\n",
-                    "- action=open only establishes the transport; use ptyctl_session_io to respond to login prompts.\n",
-                    "- ptyctl_session_io write key values: enter/tab/backspace/delete/home/end/esc/arrow_*/page_* and ctrl_* (aliases like ctrl+c, ctrl-c, arrow-up, page-up are accepted).\n",
-                    "Example (telnet): {\"action\":\"open\",\"protocol\":\"telnet\",\"host\":\"10.0.0.1\",\"port\":23,\"username\":\"admin\",\"auth\":{\"password\":\"...\"}}\n",
-                    "Example (ssh password): {\"action\":\"open\",\"protocol\":\"ssh\",\"host\":\"10.0.0.1\",\"username\":\"root\",\"auth\":{\"password\":\"...\"}}\n",
-                    "Example (expect): {\"action\":\"open\",\"protocol\":\"ssh\",\"host\":\"10.0.0.1\",\"expect\":{\"prompt_regex\":\"[#>$]\"}}\n",
-                )
-                .to_string(),
-            ),
-            server_info,
-            ..Default::default()
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<InitializeResult, McpError> {
+        self.client.identify(format!(
+            "{}/{}",
+            request.client_info.name, request.client_info.version
+        ));
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
         }
+        Ok(self.get_info())
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        mcp_server_info()
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        Ok(self
+            .scoped(&context.extensions)
+            .list_session_resources()
+            .await)
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        Ok(session_resource_templates())
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        self.scoped(&context.extensions)
+            .read_session_resource(&request.uri)
+            .await
+            .map_err(map_pty_error)
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.scoped(&context.extensions)
+            .subscribe_session_resource(request.uri, context.peer)
+            .await
+            .map_err(map_pty_error)
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.unsubscribe_session_resource(&request.uri);
+        Ok(())
     }
 }
 
+/// What ptyctl tells MCP clients at initialize; shared with the stdio proxy.
+pub(crate) fn mcp_server_info() -> ServerInfo {
+    let server_info = Implementation {
+        name: "ptyctl".to_string(),
+        title: Some("ptyctl".to_string()),
+        version: env!("PTYCTL_VERSION_LABEL").to_string(),
+        icons: None,
+        website_url: None,
+    };
+    ServerInfo {
+        capabilities: ServerCapabilities::builder()
+            .enable_tools()
+            .enable_resources()
+            .enable_resources_subscribe()
+            .build(),
+        instructions: Some(
+            concat!(
+                "Tool inputs are validated against the JSON schema; incorrect types or enum values return invalid_params.\n",
+                "Use ptyctl_session action=open to create a session_id; other tools require it.\n",
+                "Open parameters:\n",
+                "- protocol: \"ssh\" or \"telnet\" (no \"local\").\n",
+                "- auth: object (SshAuth). For password auth: {\"password\":\"...\"}, or prefer {\"password_ref\":\"secret://name\"} (also passphrase_ref/private_key_ref) so the secret stays on the server. Do not pass JSON-encoded strings.\n",
+                "- pty: object with enabled/cols/rows/term; omit to use defaults.\n",
+                "- expect: object with optional prompt_regex/pager_regexes/error_regexes; do not pass a raw string.\n",
+                "- For action=open, protocol and host are required; for other actions, session_id is required.\n",
+                "- jump_hosts: optional list of SSH bastions (first hop first), each with host/port/username/auth/host_key_policy.\n",
+                "- profile: optional name of a configured host profile (list them with action=profiles); explicit fields override the profile, and protocol/host may then be omitted.\n",
+                "- Sessions belong to the client that opened them. To collaborate, the owner calls action=share (or transfer) with the other client's client_id, which that client reads from its action=list result.\n",
+                "- reconnect: optional policy (max_attempts/backoff_ms/login_script) to re-establish a dropped transport under the same session_id.\n",
+                "- action=open only establishes the transport; use ptyctl_session_io to respond to login prompts.\n",
+                "- Password or [y/N] prompts (see input_hints/waiting_for_input): call ptyctl_session_io action=write with elicit={\"kind\":\"secret\"} or {\"kind\":\"confirm\"} so the human answers directly; never ask for secrets in chat. To type a stored secret, use write with secret_ref=\"secret://name\".\n",
+                "- Commands may be checked against an operator policy: POLICY_DENIED errors name the rule; do not try to work around them. Some commands ask the human to confirm first or wait for operator approval, so the call may block.\n",
+                "- ptyctl_session_io write key values: enter/tab/backspace/delete/home/end/esc/arrow_*/page_* and ctrl_* (aliases like ctrl+c, ctrl-c, arrow-up, page-up are accepted).\n",
+                "Example (telnet): {\"action\":\"open\",\"protocol\":\"telnet\",\"host\":\"10.0.0.1\",\"port\":23,\"username\":\"admin\",\"auth\":{\"password\":\"...\"}}\n",
+                "Example (ssh password): {\"action\":\"open\",\"protocol\":\"ssh\",\"host\":\"10.0.0.1\",\"username\":\"root\",\"auth\":{\"password\":\"...\"}}\n",
+                "Example (expect): {\"action\":\"open\",\"protocol\":\"ssh\",\"host\":\"10.0.0.1\",\"expect\":{\"prompt_regex\":\"[#>$]\"}}\n",
+                "Resources: every open session is published as ptyctl://sessions/{session_id}/tail, /screen and /transcript; subscribe to receive resources/updated when new output arrives.\n",
+            )
+            .to_string(),
+        ),
+        server_info,
+        ..Default::default()
+    }
+}
+
+pub(crate) fn session_resource_templates() -> ListResourceTemplatesResult {
+    let templates = SessionResourceKind::ALL
+        .into_iter()
+        .map(|kind| {
+            RawResourceTemplate {
+                uri_template: format!("{SESSION_RESOURCE_PREFIX}{{session_id}}/{}", kind.as_str()),
+                name: format!("session {}", kind.as_str()),
+                title: None,
+                description: Some(kind.description().to_string()),
+                mime_type: Some("text/plain".to_string()),
+            }
+            .no_annotation()
+        })
+        .collect();
+    ListResourceTemplatesResult::with_all_items(templates)
+}
+
 fn structured_result<T: Serialize>(value: T) -> Result<CallToolResult, McpError> {
     let json_value = serde_json::to_value(value).map_err(|err| {
         McpError::internal_error(
@@ -900,13 +2238,42 @@ fn session_capabilities() -> Capabilities {
     }
 }
 
-fn map_pty_error(error: PtyError) -> McpError {
+/// Whether `method` with `params` only reads state; the calls allowed in `readonly`
+/// control mode and for read-only HTTP tokens.
+fn is_readonly_call(method: &str, params: Option<&Value>) -> bool {
+    match method {
+        "ptyctl_session" => params
+            .and_then(|params| serde_json::from_value::<SessionRequest>(params.clone()).ok())
+            .is_some_and(|req| {
+                matches!(
+                    req.action,
+                    SessionAction::List | SessionAction::Status | SessionAction::Profiles
+                )
+            }),
+        "ptyctl_session_io" => params
+            .and_then(|params| serde_json::from_value::<SessionIoRequest>(params.clone()).ok())
+            .is_some_and(|req| matches!(req.action, IoAction::Read)),
+        "ptyctl_session_config" => params
+            .and_then(|params| serde_json::from_value::<SessionConfigRequest>(params.clone()).ok())
+            .is_some_and(|req| matches!(req.action, ConfigAction::Get)),
+        "ptyctl_approvals" => params
+            .and_then(|params| serde_json::from_value::<ApprovalsRequest>(params.clone()).ok())
+            .is_some_and(|req| matches!(req.action, ApprovalsAction::List)),
+        "server_info" | "resources/list" | "resources/read" => true,
+        _ => false,
+    }
+}
+
+pub(crate) fn map_pty_error(error: PtyError) -> McpError {
     match error {
         PtyError::Api(api) => {
             let data = api_error_data(&api);
             match api.error_code {
                 ErrorCode::InvalidArgument => McpError::invalid_params(api.message, data),
                 ErrorCode::NotFound => McpError::resource_not_found(api.message, data),
+                ErrorCode::PolicyDenied
+                | ErrorCode::PermissionDenied
+                | ErrorCode::LimitExceeded => McpError::invalid_request(api.message, data),
                 _ => McpError::internal_error(api.message, data),
             }
         }
@@ -935,6 +2302,18 @@ fn api_error_data(error: &ApiError) -> Option<Value> {
     Some(Value::Object(data))
 }
 
+/// Result of the `server_info` control-socket method.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerInfoResponse {
+    pub version: String,
+    pub pid: u32,
+    pub started_at_ms: u64,
+    pub uptime_ms: u64,
+    pub sessions: usize,
+    pub pending_approvals: usize,
+    pub config: ConfigSummary,
+}
+
 #[derive(Debug, Deserialize)]
 pub(crate) struct ControlRpcRequest {
     #[allow(dead_code)]
@@ -942,6 +2321,60 @@ pub(crate) struct ControlRpcRequest {
     pub id: Option<Value>,
     pub method: String,
     pub params: Option<Value>,
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    /// Set by `ptyctl mcp`: the MCP client the proxy forwards for.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// Set by `ptyctl mcp` when its client supports elicitation; prompts are
+    /// then sent back over this connection as `elicitation/create` requests.
+    #[serde(default)]
+    pub elicitation: bool,
+}
+
+/// Where prompts for the human go: the MCP client, or the client behind a
+/// `ptyctl mcp` proxy, reached over the proxy's control connection.
+#[derive(Clone)]
+pub(crate) enum Elicitor {
+    Peer(Peer<RoleServer>),
+    Proxy(tokio::sync::mpsc::Sender<ProxyElicitation>),
+}
+
+type ProxyElicitation = (
+    CreateElicitationRequestParam,
+    tokio::sync::oneshot::Sender<PtyResult<CreateElicitationResult>>,
+);
+
+impl Elicitor {
+    fn supports_elicitation(&self) -> bool {
+        match self {
+            Self::Peer(peer) => peer.supports_elicitation(),
+            Self::Proxy(_) => true,
+        }
+    }
+
+    async fn create_elicitation(
+        &self,
+        request: CreateElicitationRequestParam,
+    ) -> PtyResult<CreateElicitationResult> {
+        match self {
+            Self::Peer(peer) => peer.create_elicitation(request).await.map_err(|err| {
+                ApiError::new(ErrorCode::IoError, "Elicitation request failed")
+                    .with_details(err.to_string())
+                    .into()
+            }),
+            Self::Proxy(relay) => {
+                let (reply, answer) = tokio::sync::oneshot::channel();
+                let closed = || -> PtyError {
+                    ApiError::new(ErrorCode::IoError, "Elicitation request failed")
+                        .with_details("the ptyctl mcp connection closed")
+                        .into()
+                };
+                relay.send((request, reply)).await.map_err(|_| closed())?;
+                answer.await.map_err(|_| closed())?
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -985,6 +2418,81 @@ impl ControlRpcResponse {
     }
 }
 
+#[derive(Debug, Serialize)]
+struct ControlRpcNotification {
+    jsonrpc: &'static str,
+    method: &'static str,
+    params: Value,
+}
+
+impl ControlRpcNotification {
+    fn new(method: &'static str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            method,
+            params,
+        }
+    }
+
+    /// Output is redacted like reads, with the output before it as context.
+    fn from_event(server: &McpServer, subscription: &Subscription, event: SessionEvent) -> Self {
+        let session_id = subscription.session_id.as_str();
+        match event {
+            SessionEvent::Output {
+                bytes,
+                cursor,
+                next_cursor,
+                dropped_bytes,
+            } => {
+                let (bytes, redactions) =
+                    server.redact_output(&subscription.session, cursor, &bytes);
+                let (chunk, encoding) = encode_chunk(&bytes, subscription.encoding.clone());
+                Self::new(
+                    "ptyctl/output",
+                    json!({
+                        "session_id": session_id,
+                        "chunk": chunk,
+                        "encoding": encoding,
+                        "cursor": format_cursor(cursor),
+                        "next_cursor": format_cursor(next_cursor),
+                        "dropped_bytes": dropped_bytes,
+                        "redactions": redactions,
+                    }),
+                )
+            }
+            SessionEvent::State {
+                state,
+                eof,
+                error_reason,
+            } => Self::new(
+                "ptyctl/state",
+                json!({
+                    "session_id": session_id,
+                    "state": state,
+                    "eof": eof,
+                    "error_reason": error_reason,
+                }),
+            ),
+            SessionEvent::Lock { holder, expires_at } => Self::new(
+                "ptyctl/lock",
+                json!({
+                    "session_id": session_id,
+                    "lock_holder": holder,
+                    "lock_expires_at": expires_at,
+                }),
+            ),
+            SessionEvent::Resize { cols, rows } => Self::new(
+                "ptyctl/resize",
+                json!({
+                    "session_id": session_id,
+                    "cols": cols,
+                    "rows": rows,
+                }),
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub(crate) struct ControlRpcError {
     pub code: i64,
@@ -1044,21 +2552,18 @@ fn extract_exit_code(
     });
 
     let mut exit_code = None;
-    if let Some(regex) = &primary_regex {
-        if let Some(caps) = regex.captures(output) {
-            if let Some(rc) = caps.name("rc").and_then(|m| m.as_str().parse::<i32>().ok()) {
-                exit_code = Some(rc);
-            }
-        }
+    if let Some(regex) = &primary_regex
+        && let Some(caps) = regex.captures(output)
+        && let Some(rc) = caps.name("rc").and_then(|m| m.as_str().parse::<i32>().ok())
+    {
+        exit_code = Some(rc);
     }
-    if exit_code.is_none() {
-        if let Some(regex) = &fallback_regex {
-            if let Some(caps) = regex.captures(output) {
-                if let Some(rc) = caps.name("rc").and_then(|m| m.as_str().parse::<i32>().ok()) {
-                    exit_code = Some(rc);
-                }
-            }
-        }
+    if exit_code.is_none()
+        && let Some(regex) = &fallback_regex
+        && let Some(caps) = regex.captures(output)
+        && let Some(rc) = caps.name("rc").and_then(|m| m.as_str().parse::<i32>().ok())
+    {
+        exit_code = Some(rc);
     }
 
     let mut cleaned = output.to_string();
@@ -1103,28 +2608,118 @@ fn extract_error_hints(output: &str, error_regexes: &[Regex]) -> Vec<String> {
     hints
 }
 
-pub async fn serve_control_socket(
-    server: McpServer,
-    socket_path: &str,
+struct ControlAccess {
     mode: ControlMode,
-) -> PtyResult<()> {
+    auth_token: String,
+    allowed_uids: Vec<u32>,
+}
+
+impl ControlAccess {
+    fn new(control: &ControlConfig) -> Self {
+        let mut allowed_uids = control.control_allowed_uids.clone();
+        allowed_uids.push(unsafe { libc::geteuid() });
+        Self {
+            mode: control.control_mode.clone(),
+            auth_token: control.control_auth_token.clone(),
+            allowed_uids,
+        }
+    }
+
+    /// Returns the peer identity ("uid=… pid=…") when the peer is allowed.
+    fn check_peer(&self, stream: &UnixStream) -> PtyResult<String> {
+        let cred = stream.peer_cred().map_err(|err| {
+            ApiError::new(
+                ErrorCode::AuthFailed,
+                "Failed to read control peer credentials",
+            )
+            .with_details(err.to_string())
+        })?;
+        if self.allowed_uids.contains(&cred.uid()) {
+            return Ok(match cred.pid() {
+                Some(pid) => format!("uid={} pid={pid}", cred.uid()),
+                None => format!("uid={}", cred.uid()),
+            });
+        }
+        tracing::warn!(uid = cred.uid(), pid = ?cred.pid(), "Rejected control socket peer");
+        Err(ApiError::new(ErrorCode::AuthFailed, "Control peer uid is not allowed").into())
+    }
+
+    fn check_token(&self, request: &ControlRpcRequest) -> PtyResult<()> {
+        if self.auth_token.is_empty() {
+            return Ok(());
+        }
+        let provided = request.auth_token.as_deref().unwrap_or_default();
+        if constant_time_eq(provided.as_bytes(), self.auth_token.as_bytes()) {
+            return Ok(());
+        }
+        Err(ApiError::new(ErrorCode::AuthFailed, "Control authentication failed").into())
+    }
+}
+
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+    left.iter()
+        .zip(right)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+/// Binds the control socket, replacing a stale socket file but refusing to
+/// take over one another server is still listening on.
+pub async fn bind_control_socket(control: &ControlConfig) -> PtyResult<UnixListener> {
+    let socket_path = control.control_socket_path.as_str();
     if std::path::Path::new(socket_path).exists() {
+        if UnixStream::connect(socket_path).await.is_ok() {
+            return Err(ApiError::new(
+                ErrorCode::InvalidArgument,
+                "Another ptyctl server is already listening on the control socket",
+            )
+            .with_details(socket_path.to_string())
+            .into());
+        }
         let _ = std::fs::remove_file(socket_path);
     }
-    let listener = UnixListener::bind(socket_path).map_err(|err| {
+    // Create the socket with its final mode rather than chmod it after bind,
+    // so it is never reachable with the umask's looser permissions.
+    let mask = !control.control_socket_mode & 0o777;
+    let previous = unsafe { libc::umask(mask as libc::mode_t) };
+    let bound = UnixListener::bind(socket_path);
+    unsafe { libc::umask(previous) };
+    let listener = bound.map_err(|err| {
         ApiError::new(ErrorCode::IoError, "Failed to bind control socket")
             .with_details(err.to_string())
     })?;
+    std::fs::set_permissions(
+        socket_path,
+        std::fs::Permissions::from_mode(control.control_socket_mode),
+    )
+    .map_err(|err| {
+        ApiError::new(
+            ErrorCode::IoError,
+            "Failed to set control socket permissions",
+        )
+        .with_details(err.to_string())
+    })?;
+    Ok(listener)
+}
 
+pub async fn serve_control_socket(
+    server: McpServer,
+    listener: UnixListener,
+    control: ControlConfig,
+) -> PtyResult<()> {
+    let access = Arc::new(ControlAccess::new(&control));
     loop {
         let (stream, _) = listener.accept().await.map_err(|err| {
             ApiError::new(ErrorCode::IoError, "Failed to accept control socket")
                 .with_details(err.to_string())
         })?;
         let server = server.clone();
-        let mode = mode.clone();
+        let access = access.clone();
         tokio::spawn(async move {
-            let _ = handle_control_stream(stream, server, mode).await;
+            let _ = handle_control_stream(stream, server, access).await;
         });
     }
 }
@@ -1132,9 +2727,17 @@ pub async fn serve_control_socket(
 async fn handle_control_stream(
     stream: UnixStream,
     server: McpServer,
-    mode: ControlMode,
+    access: Arc<ControlAccess>,
 ) -> PtyResult<()> {
+    let peer = access.check_peer(&stream);
     let (reader, mut writer) = stream.into_split();
+    let server = match peer {
+        Ok(identity) => server.for_connection(AuditTransport::Control, Some(identity)),
+        Err(err) => {
+            return write_control_line(&mut writer, &ControlRpcResponse::error(Value::Null, err))
+                .await;
+        }
+    };
     let mut reader = BufReader::new(reader).lines();
     while let Some(line) = reader.next_line().await.map_err(PtyError::Io)? {
         let line = line.trim();
@@ -1142,24 +2745,202 @@ async fn handle_control_stream(
             continue;
         }
         let request: ControlRpcRequest = serde_json::from_str(line)?;
-        let response = server
-            .handle_control_request_filtered(request, mode.clone())
-            .await;
-        let payload = serde_json::to_string(&response)?;
-        writer
-            .write_all(payload.as_bytes())
-            .await
-            .map_err(PtyError::Io)?;
-        writer.write_all(b"\n").await.map_err(PtyError::Io)?;
-        writer.flush().await.map_err(PtyError::Io)?;
+        if let Err(err) = access.check_token(&request) {
+            let response = ControlRpcResponse::error(request.id.unwrap_or(Value::Null), err);
+            write_control_line(&mut writer, &response).await?;
+            continue;
+        }
+        let server = match request.client_id.as_deref() {
+            Some(client_id) => Cow::Owned(server.for_proxy_client(client_id)),
+            None => Cow::Borrowed(&server),
+        };
+        if request.method == "subscribe" {
+            let id = request.id.clone().unwrap_or(Value::Null);
+            match server.start_subscription(request.params).await {
+                Ok(subscription) => {
+                    let response = ControlRpcResponse::success(
+                        id,
+                        json!({
+                            "subscribed": true,
+                            "session_id": subscription.session_id,
+                            "cursor": format_cursor(subscription.watcher.cursor()),
+                        }),
+                    );
+                    write_control_line(&mut writer, &response).await?;
+                    return stream_subscription(&server, subscription, reader, writer).await;
+                }
+                Err(err) => {
+                    write_control_line(&mut writer, &ControlRpcResponse::error(id, err)).await?;
+                    continue;
+                }
+            }
+        }
+        let (relay, mut prompts) = tokio::sync::mpsc::channel(1);
+        let elicitor = request.elicitation.then(|| Elicitor::Proxy(relay));
+        let handling =
+            server.handle_control_request_filtered(request, access.mode.clone(), elicitor);
+        tokio::pin!(handling);
+        let response = loop {
+            tokio::select! {
+                response = &mut handling => break response,
+                Some((prompt, reply)) = prompts.recv() => {
+                    let _ = reply.send(relay_elicitation(&mut reader, &mut writer, prompt).await);
+                }
+            }
+        };
+        write_control_line(&mut writer, &response).await?;
+    }
+    Ok(())
+}
+
+struct Subscription {
+    session_id: String,
+    session: Arc<Session>,
+    encoding: Encoding,
+    watcher: SessionWatcher,
+}
+
+impl McpServer {
+    async fn start_subscription(&self, params: Option<Value>) -> PtyResult<Subscription> {
+        let req: SessionSubscribeRequest = serde_json::from_value(params.unwrap_or(Value::Null))?;
+        let session = self.session(&req.session_id).await?;
+        let cursor = req.cursor.as_deref().map(parse_cursor).transpose()?;
+        Ok(Subscription {
+            session_id: req.session_id,
+            session: session.clone(),
+            encoding: req.encoding.unwrap_or_default(),
+            watcher: SessionWatcher::new(session, cursor, req.max_bytes.unwrap_or(65536)),
+        })
+    }
+}
+
+async fn stream_subscription(
+    server: &McpServer,
+    mut subscription: Subscription,
+    mut reader: tokio::io::Lines<BufReader<tokio::net::unix::OwnedReadHalf>>,
+    mut writer: tokio::net::unix::OwnedWriteHalf,
+) -> PtyResult<()> {
+    let (closed_tx, mut closed_rx) = tokio::sync::oneshot::channel::<()>();
+    tokio::spawn(async move {
+        while let Ok(Some(_)) = reader.next_line().await {}
+        let _ = closed_tx.send(());
+    });
+    loop {
+        let events = tokio::select! {
+            events = subscription.watcher.next_events() => events,
+            _ = &mut closed_rx => return Ok(()),
+        };
+        for event in events {
+            let notification = ControlRpcNotification::from_event(server, &subscription, event);
+            write_control_line(&mut writer, &notification).await?;
+        }
+        if subscription.watcher.is_closed() {
+            let notification = ControlRpcNotification::new(
+                "ptyctl/closed",
+                json!({ "session_id": subscription.session_id }),
+            );
+            write_control_line(&mut writer, &notification).await?;
+            return Ok(());
+        }
+    }
+}
+
+/// Asks the `ptyctl mcp` proxy at the other end of a control connection to
+/// put a prompt to its MCP client, and waits for the answer.
+async fn relay_elicitation(
+    reader: &mut tokio::io::Lines<BufReader<tokio::net::unix::OwnedReadHalf>>,
+    writer: &mut tokio::net::unix::OwnedWriteHalf,
+    prompt: CreateElicitationRequestParam,
+) -> PtyResult<CreateElicitationResult> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": "elicitation",
+        "method": "elicitation/create",
+        "params": prompt,
+    });
+    write_control_line(writer, &request).await?;
+    let line = reader
+        .next_line()
+        .await
+        .map_err(PtyError::Io)?
+        .ok_or_else(|| {
+            ApiError::new(ErrorCode::IoError, "Elicitation request failed")
+                .with_details("the ptyctl mcp connection closed")
+        })?;
+    let mut reply: Value = serde_json::from_str(&line)?;
+    if let Some(error) = reply.get("error") {
+        return Err(
+            ApiError::new(ErrorCode::IoError, "Elicitation request failed")
+                .with_details(
+                    error
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default(),
+                )
+                .into(),
+        );
     }
+    Ok(serde_json::from_value(reply["result"].take())?)
+}
+
+async fn write_control_line<T: Serialize>(
+    writer: &mut tokio::net::unix::OwnedWriteHalf,
+    message: &T,
+) -> PtyResult<()> {
+    let payload = serde_json::to_string(message)?;
+    writer
+        .write_all(payload.as_bytes())
+        .await
+        .map_err(PtyError::Io)?;
+    writer.write_all(b"\n").await.map_err(PtyError::Io)?;
+    writer.flush().await.map_err(PtyError::Io)?;
     Ok(())
 }
 
+/// Longest excerpt of device output placed in an elicitation prompt.
+const ELICIT_OUTPUT_MAX_CHARS: usize = 120;
+/// Longest agent-supplied note placed in an elicitation prompt.
+const ELICIT_NOTE_MAX_CHARS: usize = 300;
+
+/// The last non-empty output line as a quoted, escaped and truncated string,
+/// so a remote device cannot pass its output off as ptyctl's own words.
+fn device_output_excerpt(tail: &[u8]) -> String {
+    let line = String::from_utf8_lossy(tail)
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string();
+    quoted_excerpt(&line, ELICIT_OUTPUT_MAX_CHARS)
+}
+
+/// `text` truncated to `max_chars`, then quoted and escaped.
+fn quoted_excerpt(text: &str, max_chars: usize) -> String {
+    let mut excerpt: String = text.chars().take(max_chars).collect();
+    if excerpt.len() < text.len() {
+        excerpt.push_str("...");
+    }
+    format!("{excerpt:?}")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn device_output_excerpt_quotes_and_truncates() {
+        let excerpt = device_output_excerpt(b"ok\nPassword: \x1b[2J\"approve\"\n\n");
+        assert_eq!(excerpt, r#""Password: \u{1b}[2J\"approve\"""#);
+        let excerpt = device_output_excerpt("x".repeat(500).as_bytes());
+        assert_eq!(excerpt.len(), ELICIT_OUTPUT_MAX_CHARS + 5);
+        assert!(excerpt.ends_with("...\""));
+        assert_eq!(
+            quoted_excerpt("Approve?\nThe session is safe", 12),
+            r#""Approve?\nThe...""#
+        );
+    }
+
     #[test]
     fn extract_exit_code_from_marker() {
         let output = "ok\n\x1eRC=3\x1f\n";
@@ -0,0 +1,191 @@
+use crate::session::{SessionListResponse, SessionTotals};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Histogram bucket upper bounds, in seconds.
+const BUCKETS: [f64; 11] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// Server-wide counters and histograms rendered in the Prometheus text format.
+///
+/// Session gauges and byte counters are not stored here; they are read from the
+/// `SessionManager` at scrape time.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    execs: BTreeMap<String, u64>,
+    exec_duration: Histogram,
+    connect_latency: Histogram,
+    open_failures: u64,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {bucket}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", self.count);
+        let _ = writeln!(out, "{name}_sum {}", self.sum);
+        let _ = writeln!(out, "{name}_count {}", self.count);
+    }
+}
+
+impl Metrics {
+    /// Records a finished `exec`; failed calls are counted with `done_reason="error"`.
+    pub fn record_exec(&self, done_reason: &str, elapsed: Duration) {
+        let mut inner = self.lock();
+        *inner.execs.entry(done_reason.to_string()).or_default() += 1;
+        inner.exec_duration.observe(elapsed);
+    }
+
+    /// Records how long a new session took to open, or that opening it failed.
+    pub fn record_connect(&self, elapsed: Duration, ok: bool) {
+        let mut inner = self.lock();
+        if ok {
+            inner.connect_latency.observe(elapsed);
+        } else {
+            inner.open_failures += 1;
+        }
+    }
+
+    pub fn render(&self, sessions: &SessionListResponse, totals: SessionTotals) -> String {
+        let mut out = String::new();
+        let mut open: BTreeMap<(String, String), u64> = BTreeMap::new();
+        for entry in &sessions.sessions {
+            *open
+                .entry((label(&entry.protocol), label(&entry.state)))
+                .or_default() += 1;
+        }
+        out.push_str("# HELP ptyctl_sessions Sessions currently tracked, by protocol and state.\n");
+        out.push_str("# TYPE ptyctl_sessions gauge\n");
+        for ((protocol, state), count) in open {
+            let _ = writeln!(
+                out,
+                "ptyctl_sessions{{protocol=\"{protocol}\",state=\"{state}\"}} {count}"
+            );
+        }
+        counter(
+            &mut out,
+            "ptyctl_bytes_in_total",
+            "Bytes received from devices.",
+            totals.bytes_in,
+        );
+        counter(
+            &mut out,
+            "ptyctl_bytes_out_total",
+            "Bytes written to devices.",
+            totals.bytes_out,
+        );
+        counter(
+            &mut out,
+            "ptyctl_dropped_bytes_total",
+            "Output bytes dropped because a session buffer overflowed.",
+            totals.dropped_bytes,
+        );
+        counter(
+            &mut out,
+            "ptyctl_lock_conflicts_total",
+            "Lock or write attempts refused because another task held the session lock.",
+            totals.lock_conflicts,
+        );
+
+        let inner = self.lock();
+        out.push_str("# HELP ptyctl_exec_total Exec calls, by done_reason.\n");
+        out.push_str("# TYPE ptyctl_exec_total counter\n");
+        for (done_reason, count) in &inner.execs {
+            let _ = writeln!(
+                out,
+                "ptyctl_exec_total{{done_reason=\"{done_reason}\"}} {count}"
+            );
+        }
+        inner.exec_duration.render(
+            &mut out,
+            "ptyctl_exec_duration_seconds",
+            "Exec call duration.",
+        );
+        inner.connect_latency.render(
+            &mut out,
+            "ptyctl_connect_duration_seconds",
+            "Time to open a new session.",
+        );
+        counter(
+            &mut out,
+            "ptyctl_session_open_failures_total",
+            "Session opens that failed.",
+            inner.open_failures,
+        );
+        out
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().expect("metrics mutex poisoned")
+    }
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} counter");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+fn label<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counters_and_cumulative_buckets() {
+        let metrics = Metrics::default();
+        metrics.record_exec("prompt_seen", Duration::from_millis(200));
+        metrics.record_exec("prompt_seen", Duration::from_secs(3));
+        metrics.record_exec("timeout", Duration::from_secs(200));
+        metrics.record_connect(Duration::from_millis(40), true);
+        metrics.record_connect(Duration::ZERO, false);
+        let totals = SessionTotals {
+            bytes_in: 10,
+            dropped_bytes: 4,
+            ..SessionTotals::default()
+        };
+        let text = metrics.render(&SessionListResponse { sessions: vec![] }, totals);
+
+        assert!(text.contains("ptyctl_bytes_in_total 10\n"));
+        assert!(text.contains("ptyctl_dropped_bytes_total 4\n"));
+        assert!(text.contains("ptyctl_exec_total{done_reason=\"prompt_seen\"} 2\n"));
+        assert!(text.contains("ptyctl_exec_duration_seconds_bucket{le=\"0.25\"} 1\n"));
+        assert!(text.contains("ptyctl_exec_duration_seconds_bucket{le=\"5\"} 2\n"));
+        assert!(text.contains("ptyctl_exec_duration_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("ptyctl_connect_duration_seconds_count 1\n"));
+        assert!(text.contains("ptyctl_session_open_failures_total 1\n"));
+    }
+}
@@ -0,0 +1,300 @@
+use crate::config::{PolicyConfig, PolicyRule};
+use crate::error::{ApiError, ErrorCode, PtyResult};
+use regex::Regex;
+
+/// Command policy evaluated before `exec` and non-sensitive `write` requests.
+///
+/// A rule applies to a session when its `hosts` globs match the session host or its
+/// `profiles` list contains the profile the session was opened from (a rule with
+/// neither applies everywhere). Across all applicable rules a `deny` match wins,
+/// then commands outside a non-empty `allow` list are denied, then a
+/// `require_approval` match parks the command for an operator and a
+/// `require_confirmation` match asks the human before the command is sent.
+#[derive(Debug, Default)]
+pub struct Policy {
+    dry_run: bool,
+    approval_timeout_ms: u64,
+    rules: Vec<CompiledRule>,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    name: String,
+    hosts: Vec<Regex>,
+    profiles: Vec<String>,
+    allow: Vec<Regex>,
+    deny: Vec<Regex>,
+    require_confirmation: Vec<Regex>,
+    require_approval: Vec<Regex>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyDecision {
+    Allow,
+    Confirm(PolicyMatch),
+    Approve(PolicyMatch),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyMatch {
+    pub rule: String,
+    pub pattern: String,
+}
+
+impl Policy {
+    pub fn new(config: &PolicyConfig) -> PtyResult<Self> {
+        let rules = config
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| CompiledRule::compile(index, rule))
+            .collect::<PtyResult<Vec<_>>>()?;
+        Ok(Self {
+            dry_run: config.dry_run,
+            approval_timeout_ms: config.approval_timeout_ms,
+            rules,
+        })
+    }
+
+    pub fn approval_timeout_ms(&self) -> u64 {
+        self.approval_timeout_ms
+    }
+
+    /// Checks every line of `command` sent to `host` (opened from `profile`).
+    pub fn check(
+        &self,
+        host: &str,
+        profile: Option<&str>,
+        command: &str,
+    ) -> PtyResult<PolicyDecision> {
+        let rules: Vec<&CompiledRule> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.applies_to(host, profile))
+            .collect();
+        if rules.is_empty() {
+            return Ok(PolicyDecision::Allow);
+        }
+        let mut confirm = None;
+        let mut approve = None;
+        for line in command
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+        {
+            if let Some(denied) = evaluate_line(&rules, line) {
+                if self.dry_run {
+                    tracing::warn!(
+                        host,
+                        rule = %denied.rule,
+                        pattern = %denied.pattern,
+                        "Policy dry run: command would be denied"
+                    );
+                    continue;
+                }
+                return Err(ApiError::new(
+                    ErrorCode::PolicyDenied,
+                    format!("Command denied by policy rule '{}'", denied.rule),
+                )
+                .with_details(format!("matched: {}", denied.pattern))
+                .into());
+            }
+            if approve.is_none() {
+                approve = find_rule_match(&rules, line, |rule| &rule.require_approval);
+            }
+            if confirm.is_none() {
+                confirm = find_rule_match(&rules, line, |rule| &rule.require_confirmation);
+            }
+        }
+        let decision = match (approve, confirm) {
+            (Some(matched), _) => PolicyDecision::Approve(matched),
+            (None, Some(matched)) => PolicyDecision::Confirm(matched),
+            (None, None) => PolicyDecision::Allow,
+        };
+        match decision {
+            PolicyDecision::Approve(matched) | PolicyDecision::Confirm(matched) if self.dry_run => {
+                tracing::warn!(
+                    host,
+                    rule = %matched.rule,
+                    pattern = %matched.pattern,
+                    "Policy dry run: command would need a human decision"
+                );
+                Ok(PolicyDecision::Allow)
+            }
+            decision => Ok(decision),
+        }
+    }
+}
+
+fn evaluate_line(rules: &[&CompiledRule], line: &str) -> Option<PolicyMatch> {
+    for rule in rules {
+        if let Some(pattern) = first_match(&rule.deny, line) {
+            return Some(PolicyMatch {
+                rule: rule.name.clone(),
+                pattern,
+            });
+        }
+    }
+    rules
+        .iter()
+        .find(|rule| !rule.allow.is_empty() && first_match(&rule.allow, line).is_none())
+        .map(|rule| PolicyMatch {
+            rule: rule.name.clone(),
+            pattern: "not in allow list".to_string(),
+        })
+}
+
+fn find_rule_match(
+    rules: &[&CompiledRule],
+    line: &str,
+    patterns: impl Fn(&CompiledRule) -> &Vec<Regex>,
+) -> Option<PolicyMatch> {
+    rules.iter().find_map(|rule| {
+        first_match(patterns(rule), line).map(|pattern| PolicyMatch {
+            rule: rule.name.clone(),
+            pattern,
+        })
+    })
+}
+
+fn first_match(patterns: &[Regex], line: &str) -> Option<String> {
+    patterns
+        .iter()
+        .find(|regex| regex.is_match(line))
+        .map(|regex| regex.as_str().to_string())
+}
+
+impl CompiledRule {
+    fn compile(index: usize, rule: &PolicyRule) -> PtyResult<Self> {
+        let name = if rule.name.is_empty() {
+            format!("rule-{}", index + 1)
+        } else {
+            rule.name.clone()
+        };
+        let regexes = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    Regex::new(pattern).map_err(|err| {
+                        ApiError::new(
+                            ErrorCode::InvalidArgument,
+                            format!("Invalid pattern in policy rule '{name}': {pattern}"),
+                        )
+                        .with_details(err.to_string())
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let hosts = rule
+            .hosts
+            .iter()
+            .map(|glob| glob_regex(glob))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            hosts,
+            profiles: rule.profiles.clone(),
+            allow: regexes(&rule.allow)?,
+            deny: regexes(&rule.deny)?,
+            require_confirmation: regexes(&rule.require_confirmation)?,
+            require_approval: regexes(&rule.require_approval)?,
+            name,
+        })
+    }
+
+    fn applies_to(&self, host: &str, profile: Option<&str>) -> bool {
+        if self.hosts.is_empty() && self.profiles.is_empty() {
+            return true;
+        }
+        self.hosts.iter().any(|glob| glob.is_match(host))
+            || profile.is_some_and(|profile| self.profiles.iter().any(|name| name == profile))
+    }
+}
+
+pub(crate) fn glob_regex(glob: &str) -> Result<Regex, regex::Error> {
+    let pattern = regex::escape(glob).replace(r"\*", ".*").replace(r"\?", ".");
+    Regex::new(&format!("^{pattern}$"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str) -> PolicyRule {
+        PolicyRule {
+            name: name.to_string(),
+            ..PolicyRule::default()
+        }
+    }
+
+    #[test]
+    fn deny_allow_and_confirmation_rules() {
+        let policy = Policy::new(&PolicyConfig {
+            dry_run: false,
+            approval_timeout_ms: 1_000,
+            rules: vec![
+                PolicyRule {
+                    deny: vec![r"rm\s+-rf\s+/".to_string(), r"^write erase".to_string()],
+                    require_confirmation: vec![r"^reload\b".to_string()],
+                    require_approval: vec![r"^commit\b".to_string()],
+                    ..rule("destructive")
+                },
+                PolicyRule {
+                    hosts: vec!["core-*".to_string()],
+                    profiles: vec!["lab-fw".to_string()],
+                    allow: vec![r"^show\b".to_string()],
+                    ..rule("read-only-core")
+                },
+            ],
+        })
+        .unwrap();
+
+        assert_eq!(
+            policy.check("edge-1", None, "ls -la").unwrap(),
+            PolicyDecision::Allow
+        );
+        let err = policy
+            .check("edge-1", None, "cd /tmp\nrm -rf /")
+            .unwrap_err();
+        assert!(err.to_string().contains("destructive"));
+        assert_eq!(
+            policy.check("edge-1", None, "reload in 5").unwrap(),
+            PolicyDecision::Confirm(PolicyMatch {
+                rule: "destructive".to_string(),
+                pattern: r"^reload\b".to_string(),
+            })
+        );
+        assert!(matches!(
+            policy.check("edge-1", None, "reload\ncommit").unwrap(),
+            PolicyDecision::Approve(_)
+        ));
+        assert!(policy.check("core-sw1", None, "show run").is_ok());
+        assert!(policy.check("core-sw1", None, "conf t").is_err());
+        assert!(policy.check("10.0.0.9", Some("lab-fw"), "conf t").is_err());
+        assert!(policy.check("10.0.0.9", None, "conf t").is_ok());
+    }
+
+    #[test]
+    fn dry_run_allows_but_still_parses() {
+        let config = PolicyConfig {
+            dry_run: true,
+            approval_timeout_ms: 1_000,
+            rules: vec![PolicyRule {
+                deny: vec!["^reload".to_string()],
+                ..rule("no-reload")
+            }],
+        };
+        let policy = Policy::new(&config).unwrap();
+        assert_eq!(
+            policy.check("r1", None, "reload").unwrap(),
+            PolicyDecision::Allow
+        );
+        let invalid = PolicyConfig {
+            rules: vec![PolicyRule {
+                deny: vec!["(".to_string()],
+                ..rule("broken")
+            }],
+            ..config
+        };
+        assert!(Policy::new(&invalid).is_err());
+    }
+}
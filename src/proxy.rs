@@ -0,0 +1,454 @@
+use crate::error::{ApiError, ErrorCode, PtyError, PtyResult};
+use crate::mcp::{
+    McpServer, RESOURCE_UPDATE_DEBOUNCE, map_pty_error, mcp_server_info,
+    parse_session_resource_uri, session_resource_templates,
+};
+use crate::tokens::hash_token;
+use crate::version::VERSION;
+use rmcp::model::{
+    CallToolRequestParam, CallToolResult, ListResourceTemplatesResult, ListResourcesResult,
+    ListToolsResult, PaginatedRequestParam, ReadResourceRequestParam, ReadResourceResult,
+    ResourceUpdatedNotificationParam, ServerInfo, SubscribeRequestParam, UnsubscribeRequestParam,
+};
+use rmcp::service::{RequestContext, RoleServer};
+use rmcp::transport::stdio;
+use rmcp::{ErrorData as McpError, ServiceExt};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::UnixStream;
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::time::{Duration, sleep, timeout};
+
+const DAEMON_START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The `ptyctl daemon` a proxy talks to over its control socket.
+pub struct DaemonEndpoint {
+    pub socket_path: String,
+    pub auth_token: Option<String>,
+    /// Arguments for `ptyctl daemon` when nothing is listening yet; `None`
+    /// never starts one.
+    pub spawn_args: Option<Vec<OsString>>,
+}
+
+/// MCP stdio server that forwards every request to a daemon, so sessions
+/// outlive the MCP client that opened them.
+#[derive(Clone)]
+pub struct McpProxy {
+    daemon: Arc<DaemonEndpoint>,
+    /// Who the daemon sees as the caller: `PTYCTL_CLIENT_ID`, or else the MCP
+    /// client's name qualified by user and working directory, so it stays the
+    /// same across restarts without merging unrelated clients.
+    client_id: Arc<OnceLock<String>>,
+    spawn_lock: Arc<tokio::sync::Mutex<()>>,
+    resource_subscriptions: Arc<Mutex<HashMap<String, tokio::task::AbortHandle>>>,
+}
+
+#[derive(Deserialize)]
+struct ControlReply {
+    result: Option<Value>,
+    error: Option<ControlReplyError>,
+}
+
+#[derive(Deserialize)]
+struct ControlReplyError {
+    message: String,
+    data: Option<ApiError>,
+}
+
+impl McpProxy {
+    pub fn new(daemon: DaemonEndpoint) -> Self {
+        Self {
+            daemon: Arc::new(daemon),
+            client_id: Arc::new(OnceLock::new()),
+            spawn_lock: Arc::new(tokio::sync::Mutex::new(())),
+            resource_subscriptions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Forwards calls as `client_id` instead of the MCP client's name.
+    pub fn with_client_id(self, client_id: String) -> Self {
+        let _ = self.client_id.set(client_id);
+        self
+    }
+
+    /// Settles the client identity from the MCP `clientInfo` unless one was
+    /// configured.
+    fn identify(&self, peer: &rmcp::service::Peer<RoleServer>) {
+        if self.client_id.get().is_none()
+            && let Some(info) = peer.peer_info()
+        {
+            let _ = self
+                .client_id
+                .set(default_client_id(&info.client_info.name));
+        }
+    }
+
+    pub async fn serve_stdio(self) -> PtyResult<()> {
+        let info = self.request("server_info", Value::Null).await?;
+        let version = info.get("version").and_then(Value::as_str);
+        if version != Some(VERSION) {
+            tracing::warn!(
+                daemon = version.unwrap_or("unknown"),
+                proxy = VERSION,
+                "ptyctl daemon runs a different build; restart it to upgrade"
+            );
+        }
+        let running = self.serve(stdio()).await.map_err(|err| {
+            ApiError::new(ErrorCode::IoError, "MCP stdio initialization failed")
+                .with_details(err.to_string())
+        })?;
+        running.waiting().await.map_err(|err| {
+            ApiError::new(ErrorCode::IoError, "MCP stdio task failed").with_details(err.to_string())
+        })?;
+        Ok(())
+    }
+
+    async fn request(&self, method: &str, params: Value) -> PtyResult<Value> {
+        let (mut lines, _writer) = self.send(method, params, false).await?;
+        read_reply(&mut lines).await
+    }
+
+    /// Forwards a tool call, putting any prompt the daemon sends back to the
+    /// MCP client when it supports elicitation.
+    async fn call(
+        &self,
+        method: &str,
+        params: Value,
+        peer: &rmcp::service::Peer<RoleServer>,
+    ) -> PtyResult<Value> {
+        let elicitation = peer.supports_elicitation();
+        let (mut lines, mut writer) = self.send(method, params, elicitation).await?;
+        loop {
+            let line = next_reply_line(&mut lines).await?;
+            let message: Value = serde_json::from_str(&line)?;
+            if message.get("method").and_then(Value::as_str) != Some("elicitation/create") {
+                return reply_value(&line);
+            }
+            let answer = match serde_json::from_value(message["params"].clone()) {
+                Ok(prompt) => peer
+                    .create_elicitation(prompt)
+                    .await
+                    .map_err(|err| err.to_string()),
+                Err(err) => Err(err.to_string()),
+            };
+            let reply = match answer {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": message["id"], "result": result }),
+                Err(details) => json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": { "code": -32603, "message": details },
+                }),
+            };
+            let mut payload = serde_json::to_vec(&reply)?;
+            payload.push(b'\n');
+            writer.write_all(&payload).await.map_err(PtyError::Io)?;
+        }
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        params: Value,
+        elicitation: bool,
+    ) -> PtyResult<(Lines<BufReader<OwnedReadHalf>>, OwnedWriteHalf)> {
+        let stream = self.connect().await?;
+        let (reader, mut writer) = stream.into_split();
+        let mut payload = serde_json::to_vec(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+            "auth_token": self.daemon.auth_token,
+            "client_id": self.client_id.get(),
+            "elicitation": elicitation,
+        }))?;
+        payload.push(b'\n');
+        writer.write_all(&payload).await.map_err(PtyError::Io)?;
+        Ok((BufReader::new(reader).lines(), writer))
+    }
+
+    /// Connects to the daemon, starting it first if nothing is listening.
+    async fn connect(&self) -> PtyResult<UnixStream> {
+        if let Ok(stream) = UnixStream::connect(&self.daemon.socket_path).await {
+            return Ok(stream);
+        }
+        let Some(args) = &self.daemon.spawn_args else {
+            return UnixStream::connect(&self.daemon.socket_path)
+                .await
+                .map_err(|err| connect_error(&self.daemon.socket_path, err));
+        };
+        let _guard = self.spawn_lock.lock().await;
+        if let Ok(stream) = UnixStream::connect(&self.daemon.socket_path).await {
+            return Ok(stream);
+        }
+        spawn_daemon(args, &daemon_log_path(&self.daemon.socket_path))?;
+        let mut waited = Duration::ZERO;
+        loop {
+            match UnixStream::connect(&self.daemon.socket_path).await {
+                Ok(stream) => return Ok(stream),
+                Err(err) if waited >= DAEMON_START_TIMEOUT => {
+                    return Err(connect_error(&self.daemon.socket_path, err));
+                }
+                Err(_) => {
+                    sleep(Duration::from_millis(100)).await;
+                    waited += Duration::from_millis(100);
+                }
+            }
+        }
+    }
+
+    async fn subscribe_session_resource(
+        &self,
+        uri: String,
+        peer: rmcp::service::Peer<RoleServer>,
+    ) -> PtyResult<()> {
+        let (session_id, _) = parse_session_resource_uri(&uri)?;
+        let (mut lines, writer) = self
+            .send("subscribe", json!({ "session_id": session_id }), false)
+            .await?;
+        read_reply(&mut lines).await?;
+        let task_uri = uri.clone();
+        let task = tokio::spawn(async move {
+            let _writer = writer;
+            while let Ok(Some(line)) = lines.next_line().await {
+                let (mut changed, mut closed) = notification_kind(&line);
+                // Coalesce whatever else is already waiting into one update.
+                while let Ok(Ok(Some(line))) = timeout(Duration::ZERO, lines.next_line()).await {
+                    let (more, end) = notification_kind(&line);
+                    changed |= more;
+                    closed |= end;
+                }
+                if (changed || closed)
+                    && peer
+                        .notify_resource_updated(ResourceUpdatedNotificationParam {
+                            uri: task_uri.clone(),
+                        })
+                        .await
+                        .is_err()
+                {
+                    break;
+                }
+                if closed {
+                    break;
+                }
+                sleep(RESOURCE_UPDATE_DEBOUNCE).await;
+            }
+        });
+        let previous = self
+            .resource_subscriptions
+            .lock()
+            .expect("subscription mutex poisoned")
+            .insert(uri, task.abort_handle());
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+        Ok(())
+    }
+}
+
+impl rmcp::ServerHandler for McpProxy {
+    fn get_info(&self) -> ServerInfo {
+        mcp_server_info()
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(
+            McpServer::tool_definitions(),
+        ))
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        self.identify(&context.peer);
+        if !McpServer::tool_definitions()
+            .iter()
+            .any(|tool| tool.name == request.name)
+        {
+            return Err(McpError::invalid_params(
+                format!("Unknown tool: {}", request.name),
+                None,
+            ));
+        }
+        let params = Value::Object(request.arguments.unwrap_or_default());
+        let result = self
+            .call(&request.name, params, &context.peer)
+            .await
+            .map_err(map_pty_error)?;
+        Ok(CallToolResult::structured(result))
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        self.identify(&context.peer);
+        let result = self
+            .request("resources/list", Value::Null)
+            .await
+            .map_err(map_pty_error)?;
+        serde_json::from_value(result).map_err(|err| map_pty_error(err.into()))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        Ok(session_resource_templates())
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        self.identify(&context.peer);
+        let result = self
+            .request("resources/read", json!({ "uri": request.uri }))
+            .await
+            .map_err(map_pty_error)?;
+        serde_json::from_value(result).map_err(|err| map_pty_error(err.into()))
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.identify(&context.peer);
+        self.subscribe_session_resource(request.uri, context.peer)
+            .await
+            .map_err(map_pty_error)
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let removed = self
+            .resource_subscriptions
+            .lock()
+            .expect("subscription mutex poisoned")
+            .remove(&request.uri);
+        if let Some(task) = removed {
+            task.abort();
+        }
+        Ok(())
+    }
+}
+
+/// Where an auto-started daemon logs: next to its control socket, e.g.
+/// `ptyctl.sock` logs to `ptyctl.log`.
+fn daemon_log_path(socket_path: &str) -> PathBuf {
+    Path::new(socket_path).with_extension("log")
+}
+
+/// Starts `ptyctl daemon` in its own process group, so it survives the MCP
+/// client (and a Ctrl-C in its terminal). Its stderr is appended to `log_path`.
+fn spawn_daemon(args: &[OsString], log_path: &Path) -> PtyResult<()> {
+    let exe = std::env::current_exe().map_err(PtyError::Io)?;
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .mode(0o600)
+        .open(log_path)
+        .map_err(|err| {
+            ApiError::new(ErrorCode::IoError, "Failed to open ptyctl daemon log")
+                .with_details(format!("{}: {err}", log_path.display()))
+        })?;
+    tokio::process::Command::new(exe)
+        .arg("daemon")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(log)
+        .process_group(0)
+        .spawn()
+        .map_err(|err| {
+            ApiError::new(ErrorCode::IoError, "Failed to start ptyctl daemon")
+                .with_details(err.to_string())
+        })?;
+    Ok(())
+}
+
+async fn read_reply(lines: &mut Lines<BufReader<OwnedReadHalf>>) -> PtyResult<Value> {
+    reply_value(&next_reply_line(lines).await?)
+}
+
+async fn next_reply_line(lines: &mut Lines<BufReader<OwnedReadHalf>>) -> PtyResult<String> {
+    Ok(lines
+        .next_line()
+        .await
+        .map_err(PtyError::Io)?
+        .ok_or_else(|| ApiError::new(ErrorCode::IoError, "No response from ptyctl daemon"))?)
+}
+
+fn reply_value(line: &str) -> PtyResult<Value> {
+    let reply: ControlReply = serde_json::from_str(line)?;
+    if let Some(error) = reply.error {
+        return Err(error
+            .data
+            .unwrap_or_else(|| ApiError::new(ErrorCode::IoError, error.message))
+            .into());
+    }
+    Ok(reply.result.unwrap_or(Value::Null))
+}
+
+/// Whether a subscription notification carries new output, and whether it
+/// reports the session closed.
+fn notification_kind(line: &str) -> (bool, bool) {
+    let method = serde_json::from_str::<Value>(line)
+        .ok()
+        .and_then(|message| message.get("method")?.as_str().map(str::to_string));
+    match method.as_deref() {
+        Some("ptyctl/output") => (true, false),
+        Some("ptyctl/closed") => (false, true),
+        _ => (false, false),
+    }
+}
+
+/// `<client name>@<hash>`, the hash covering the user and the working
+/// directory: two editors of the same kind in different workspaces stay apart,
+/// while a client restarted in the same place keeps its sessions.
+fn default_client_id(name: &str) -> String {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let uid = unsafe { libc::geteuid() };
+    let digest = hash_token(&format!("{uid}\0{}", cwd.display()));
+    format!("{name}@{}", &digest[..12])
+}
+
+fn connect_error(socket_path: &str, err: std::io::Error) -> PtyError {
+    ApiError::new(ErrorCode::IoError, "Failed to connect to ptyctl daemon")
+        .with_details(format!("{socket_path}: {err}"))
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_client_id_is_stable_and_qualified() {
+        let id = default_client_id("editor");
+        assert!(id.starts_with("editor@"), "{id}");
+        assert_eq!(id.len(), "editor@".len() + 12);
+        assert_eq!(id, default_client_id("editor"));
+        assert_ne!(id, default_client_id("other"));
+    }
+}
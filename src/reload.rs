@@ -0,0 +1,189 @@
+use crate::config::{Config, ServeArgs};
+use crate::error::{PtyError, PtyResult};
+use crate::mcp::McpServer;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::SystemTime;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{Duration, interval};
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Swaps the `logging.level` filter of the running subscriber.
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// A control `reload` call, answered with the outcome.
+pub type ReloadRequest = oneshot::Sender<PtyResult<ReloadReport>>;
+
+/// Changed settings, as dotted config paths (`session.limits.per_host`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReloadReport {
+    /// Now in effect.
+    pub applied: Vec<String>,
+    /// Changed in the file but kept at the running value until a restart.
+    pub restart_required: Vec<String>,
+}
+
+/// Re-reads the config when the file changes, on SIGHUP and on control
+/// `reload`, and applies what can change without dropping sessions.
+pub struct ConfigReloader {
+    args: ServeArgs,
+    running: Config,
+    server: McpServer,
+    log_filter: Option<LogFilterHandle>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        args: ServeArgs,
+        running: Config,
+        server: McpServer,
+        log_filter: Option<LogFilterHandle>,
+    ) -> Self {
+        Self {
+            args,
+            running,
+            server,
+            log_filter,
+        }
+    }
+
+    pub async fn run(mut self, mut requests: mpsc::Receiver<ReloadRequest>) -> PtyResult<()> {
+        let mut hangup = signal(SignalKind::hangup()).map_err(PtyError::Io)?;
+        let mut ticker = interval(WATCH_INTERVAL);
+        let mut modified = self.modified();
+        loop {
+            tokio::select! {
+                Some(reply) = requests.recv() => {
+                    let _ = reply.send(self.reload());
+                }
+                _ = hangup.recv() => self.reload_logged("SIGHUP"),
+                _ = ticker.tick() => {
+                    if self.modified() == modified {
+                        continue;
+                    }
+                    self.reload_logged("config file changed");
+                }
+            }
+            modified = self.modified();
+        }
+    }
+
+    /// Loads the config again and applies its live fields.
+    pub fn reload(&mut self) -> PtyResult<ReloadReport> {
+        let loaded = Config::load(&self.args)?;
+        let effective = live_config(&self.running, &loaded);
+        self.server.reconfigure(&effective)?;
+        if effective.logging.level != self.running.logging.level
+            && let Some(handle) = &self.log_filter
+            && let Err(err) = handle.reload(EnvFilter::new(&effective.logging.level))
+        {
+            tracing::warn!(error = %err, "Failed to apply logging.level");
+        }
+        let report = ReloadReport {
+            applied: changed_paths(&self.running, &effective)?,
+            restart_required: changed_paths(&effective, &loaded)?,
+        };
+        self.running = effective;
+        Ok(report)
+    }
+
+    fn reload_logged(&mut self, trigger: &str) {
+        match self.reload() {
+            Ok(report) => tracing::info!(
+                trigger,
+                applied = ?report.applied,
+                restart_required = ?report.restart_required,
+                "Reloaded config"
+            ),
+            Err(err) => tracing::error!(
+                trigger,
+                error = %err,
+                "Config reload failed; keeping the running config"
+            ),
+        }
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        let path = Config::path(&self.args)?;
+        std::fs::metadata(path).ok()?.modified().ok()
+    }
+}
+
+/// `running` with the fields that can change without a restart taken from `loaded`.
+fn live_config(running: &Config, loaded: &Config) -> Config {
+    let mut config = running.clone();
+    config.session = loaded.session.clone();
+    config.ssh = loaded.ssh.clone();
+    config.telnet = loaded.telnet.clone();
+    config.policy = loaded.policy.clone();
+    config.hosts = loaded.hosts.clone();
+    config.secrets = loaded.secrets.clone();
+    config.server.http.auth_token = loaded.server.http.auth_token.clone();
+    config.server.http.tokens = loaded.server.http.tokens.clone();
+    config.logging.level = loaded.logging.level.clone();
+    config
+}
+
+fn changed_paths(before: &Config, after: &Config) -> PtyResult<Vec<String>> {
+    let mut paths = Vec::new();
+    diff_values(
+        "",
+        &serde_json::to_value(before)?,
+        &serde_json::to_value(after)?,
+        &mut paths,
+    );
+    Ok(paths)
+}
+
+fn diff_values(path: &str, before: &Value, after: &Value, paths: &mut Vec<String>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            let added = after.keys().filter(|key| !before.contains_key(*key));
+            for key in before.keys().chain(added) {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                diff_values(
+                    &child,
+                    before.get(key).unwrap_or(&Value::Null),
+                    after.get(key).unwrap_or(&Value::Null),
+                    paths,
+                );
+            }
+        }
+        _ if before != after => paths.push(path.to_string()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_live_and_restart_fields() {
+        let running = Config::default();
+        let mut loaded = Config::default();
+        loaded.session.limits.per_host = 2;
+        loaded.logging.level = "debug".to_string();
+        loaded.server.http.listen = "127.0.0.1:9999".to_string();
+        loaded.logging.audit_path = "/tmp/audit.jsonl".to_string();
+
+        let effective = live_config(&running, &loaded);
+        assert_eq!(effective.session.limits.per_host, 2);
+        assert_eq!(effective.server.http.listen, running.server.http.listen);
+        assert_eq!(
+            changed_paths(&running, &effective).unwrap(),
+            ["logging.level", "session.limits.per_host"]
+        );
+        assert_eq!(
+            changed_paths(&effective, &loaded).unwrap(),
+            ["logging.audit_path", "server.http.listen"]
+        );
+    }
+}
@@ -0,0 +1,442 @@
+use crate::config::{SecretProviderKind, SecretsConfig};
+use crate::error::{ApiError, ErrorCode, PtyResult};
+use crate::policy::glob_regex;
+use crate::session::{KnownSecrets, SshAuth};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use regex::Regex;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tokio::time::{Duration, timeout};
+
+pub const SECRET_REF_PREFIX: &str = "secret://";
+const FILE_FORMAT_VERSION: u32 = 1;
+const PBKDF2_ITERATIONS: u32 = 210_000;
+
+/// Resolves `secret://name` references against the configured providers, in order.
+pub struct SecretStore {
+    config: SecretsConfig,
+    allowed_hosts: BTreeMap<String, Vec<Regex>>,
+    known: KnownSecrets,
+    file_cache: Mutex<Option<(SystemTime, BTreeMap<String, String>)>>,
+}
+
+impl SecretStore {
+    /// Resolved values are added to `known` so output redaction masks them.
+    pub fn new(config: SecretsConfig, known: KnownSecrets) -> PtyResult<Self> {
+        let mut allowed_hosts = BTreeMap::new();
+        for (name, globs) in &config.allowed_hosts {
+            let name = secret_name(name)?.to_string();
+            let globs = globs
+                .iter()
+                .map(|glob| glob_regex(glob))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| {
+                    ApiError::new(
+                        ErrorCode::InvalidArgument,
+                        format!("Invalid host pattern for secret://{name}"),
+                    )
+                    .with_details(err.to_string())
+                })?;
+            allowed_hosts.insert(name, globs);
+        }
+        Ok(Self {
+            config,
+            allowed_hosts,
+            known,
+            file_cache: Mutex::new(None),
+        })
+    }
+
+    /// Resolves `reference` for a connection to `host`, refusing secrets bound
+    /// to other hosts by `secrets.allowed_hosts`.
+    pub async fn resolve(&self, reference: &str, host: &str) -> PtyResult<String> {
+        let name = secret_name(reference)?;
+        if let Some(globs) = self.allowed_hosts.get(name)
+            && !globs.iter().any(|glob| glob.is_match(host))
+        {
+            return Err(ApiError::new(
+                ErrorCode::PermissionDenied,
+                format!("{SECRET_REF_PREFIX}{name} may not be used with host {host}"),
+            )
+            .with_details("see secrets.allowed_hosts")
+            .into());
+        }
+        for provider in &self.config.providers {
+            let value = match provider {
+                SecretProviderKind::Env => {
+                    std::env::var(env_var_name(&self.config.env_prefix, name)).ok()
+                }
+                SecretProviderKind::File => self.resolve_file(name)?,
+                SecretProviderKind::Command => self.resolve_command(name).await?,
+            };
+            if let Some(value) = value {
+                self.known.add(&value);
+                return Ok(value);
+            }
+        }
+        Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("Secret not found: {SECRET_REF_PREFIX}{name}"),
+        )
+        .into())
+    }
+
+    /// Replaces the `*_ref` fields of `auth` with the values they point at, for
+    /// a connection to `host`. `redirect` names a request option that may send
+    /// ssh elsewhere; secrets bound by `secrets.allowed_hosts` refuse it.
+    pub async fn resolve_auth(
+        &self,
+        auth: &mut SshAuth,
+        host: &str,
+        redirect: Option<&str>,
+    ) -> PtyResult<()> {
+        if let Some(option) = redirect {
+            let references = [
+                &auth.password_ref,
+                &auth.passphrase_ref,
+                &auth.private_key_ref,
+            ];
+            for reference in references.into_iter().flatten() {
+                let name = secret_name(reference)?;
+                if self.allowed_hosts.contains_key(name) {
+                    return Err(ApiError::new(
+                        ErrorCode::PermissionDenied,
+                        format!("{SECRET_REF_PREFIX}{name} may not be used with {option}"),
+                    )
+                    .with_details("it is bound to hosts by secrets.allowed_hosts")
+                    .into());
+                }
+            }
+        }
+        if let Some(reference) = auth.password_ref.take() {
+            auth.password = Some(self.resolve(&reference, host).await?);
+        }
+        if let Some(reference) = auth.passphrase_ref.take() {
+            auth.passphrase = Some(self.resolve(&reference, host).await?);
+        }
+        if let Some(reference) = auth.private_key_ref.take() {
+            auth.private_key_pem = Some(self.resolve(&reference, host).await?);
+        }
+        Ok(())
+    }
+
+    fn resolve_file(&self, name: &str) -> PtyResult<Option<String>> {
+        if self.config.file_path.is_empty() {
+            return Ok(None);
+        }
+        let path = Path::new(&self.config.file_path);
+        let modified = std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .map_err(|err| {
+                ApiError::new(ErrorCode::IoError, "Failed to read secrets file")
+                    .with_details(err.to_string())
+            })?;
+        let mut cache = self.file_cache.lock().expect("secrets cache poisoned");
+        if cache.as_ref().is_none_or(|(at, _)| *at != modified) {
+            let passphrase = std::env::var(&self.config.file_key_env).map_err(|_| {
+                ApiError::new(
+                    ErrorCode::InvalidArgument,
+                    format!(
+                        "Secrets file is configured but {} is not set",
+                        self.config.file_key_env
+                    ),
+                )
+            })?;
+            let secrets = load_secrets_file(path, &passphrase)?;
+            *cache = Some((modified, secrets));
+        }
+        Ok(cache
+            .as_ref()
+            .and_then(|(_, secrets)| secrets.get(name).cloned()))
+    }
+
+    async fn resolve_command(&self, name: &str) -> PtyResult<Option<String>> {
+        let Some((program, args)) = self.config.command.split_first() else {
+            return Ok(None);
+        };
+        let child = tokio::process::Command::new(program)
+            .args(args)
+            .arg(name)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .output();
+        let output = timeout(Duration::from_millis(self.config.command_timeout_ms), child)
+            .await
+            .map_err(|_| ApiError::new(ErrorCode::IoError, "Secret helper timed out"))?
+            .map_err(|err| {
+                ApiError::new(ErrorCode::IoError, "Failed to run secret helper")
+                    .with_details(err.to_string())
+            })?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let stdout = String::from_utf8(output.stdout).map_err(|_| {
+            ApiError::new(
+                ErrorCode::IoError,
+                "Secret helper returned non-UTF-8 output",
+            )
+        })?;
+        let value = stdout.strip_suffix('\n').unwrap_or(&stdout);
+        let value = value.strip_suffix('\r').unwrap_or(value);
+        Ok((!value.is_empty()).then(|| value.to_string()))
+    }
+}
+
+pub fn secret_name(reference: &str) -> PtyResult<&str> {
+    let name = reference
+        .strip_prefix(SECRET_REF_PREFIX)
+        .unwrap_or(reference);
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | '/'));
+    if !valid {
+        return Err(ApiError::new(
+            ErrorCode::InvalidArgument,
+            "Secret references look like secret://name (letters, digits, - _ . /)",
+        )
+        .into());
+    }
+    Ok(name)
+}
+
+fn env_var_name(prefix: &str, name: &str) -> String {
+    let suffix: String = name
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() {
+                ch.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{prefix}{suffix}")
+}
+
+#[derive(Serialize, Deserialize)]
+struct SecretsFile {
+    version: u32,
+    kdf: String,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+pub fn load_secrets_file(path: &Path, passphrase: &str) -> PtyResult<BTreeMap<String, String>> {
+    let content = std::fs::read_to_string(path).map_err(|err| {
+        ApiError::new(ErrorCode::IoError, "Failed to read secrets file")
+            .with_details(err.to_string())
+    })?;
+    decrypt_secrets(&content, passphrase)
+}
+
+pub fn save_secrets_file(
+    path: &Path,
+    passphrase: &str,
+    secrets: &BTreeMap<String, String>,
+) -> PtyResult<()> {
+    let content = encrypt_secrets(secrets, passphrase)?;
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let mut file = tempfile::NamedTempFile::new_in(dir.unwrap_or(Path::new(".")))?;
+    std::io::Write::write_all(&mut file, content.as_bytes())?;
+    file.persist(path).map_err(|err| {
+        ApiError::new(ErrorCode::IoError, "Failed to write secrets file")
+            .with_details(err.to_string())
+    })?;
+    Ok(())
+}
+
+fn encrypt_secrets(secrets: &BTreeMap<String, String>, passphrase: &str) -> PtyResult<String> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| ApiError::new(ErrorCode::IoError, "Random number generator failed"))?;
+    let key = derive_key(passphrase, &salt, PBKDF2_ITERATIONS)?;
+    let mut buffer = serde_json::to_vec(secrets)?;
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::empty(),
+        &mut buffer,
+    )
+    .map_err(|_| ApiError::new(ErrorCode::IoError, "Failed to encrypt secrets"))?;
+    let file = SecretsFile {
+        version: FILE_FORMAT_VERSION,
+        kdf: "pbkdf2-sha256".to_string(),
+        iterations: PBKDF2_ITERATIONS,
+        salt: BASE64.encode(salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(buffer),
+    };
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+fn decrypt_secrets(content: &str, passphrase: &str) -> PtyResult<BTreeMap<String, String>> {
+    let file: SecretsFile = serde_json::from_str(content).map_err(|err| {
+        ApiError::new(ErrorCode::InvalidArgument, "Invalid secrets file")
+            .with_details(err.to_string())
+    })?;
+    if file.version != FILE_FORMAT_VERSION || file.kdf != "pbkdf2-sha256" {
+        return Err(ApiError::new(
+            ErrorCode::InvalidArgument,
+            "Unsupported secrets file format",
+        )
+        .into());
+    }
+    let decode = |value: &str| {
+        BASE64
+            .decode(value)
+            .map_err(|_| ApiError::new(ErrorCode::InvalidArgument, "Invalid secrets file"))
+    };
+    let salt = decode(&file.salt)?;
+    let nonce: [u8; NONCE_LEN] = decode(&file.nonce)?
+        .try_into()
+        .map_err(|_| ApiError::new(ErrorCode::InvalidArgument, "Invalid secrets file"))?;
+    let mut buffer = decode(&file.ciphertext)?;
+    let key = derive_key(passphrase, &salt, file.iterations)?;
+    let plaintext = key
+        .open_in_place(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::empty(),
+            &mut buffer,
+        )
+        .map_err(|_| {
+            ApiError::new(
+                ErrorCode::AuthFailed,
+                "Failed to decrypt secrets file (wrong key?)",
+            )
+        })?;
+    Ok(serde_json::from_slice(plaintext)?)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> PtyResult<LessSafeKey> {
+    let iterations = NonZeroU32::new(iterations)
+        .ok_or_else(|| ApiError::new(ErrorCode::InvalidArgument, "Invalid secrets file"))?;
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&AES_256_GCM, &key)
+        .map_err(|_| ApiError::new(ErrorCode::IoError, "Failed to derive secrets key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_file_round_trips_and_rejects_wrong_key() {
+        let mut secrets = BTreeMap::new();
+        secrets.insert("router-admin".to_string(), "s3cret".to_string());
+        let content = encrypt_secrets(&secrets, "passphrase").unwrap();
+        assert!(!content.contains("s3cret"));
+        assert_eq!(decrypt_secrets(&content, "passphrase").unwrap(), secrets);
+        assert!(decrypt_secrets(&content, "wrong").is_err());
+    }
+
+    #[tokio::test]
+    async fn resolve_tries_env_then_command() {
+        let known = KnownSecrets::default();
+        let store = SecretStore::new(
+            SecretsConfig {
+                providers: vec![SecretProviderKind::Env, SecretProviderKind::Command],
+                env_prefix: "PTYCTL_TEST_SECRET_".to_string(),
+                command: vec!["echo".to_string(), "from-helper".to_string()],
+                ..SecretsConfig::default()
+            },
+            known.clone(),
+        )
+        .unwrap();
+        unsafe { std::env::set_var("PTYCTL_TEST_SECRET_ROUTER_ADMIN", "from-env") };
+        assert_eq!(
+            store
+                .resolve("secret://router-admin", "10.0.0.1")
+                .await
+                .unwrap(),
+            "from-env"
+        );
+        assert_eq!(
+            store
+                .resolve("secret://core/sw1", "10.0.0.1")
+                .await
+                .unwrap(),
+            "from-helper core/sw1"
+        );
+        assert!(
+            store
+                .resolve("secret://bad name", "10.0.0.1")
+                .await
+                .is_err()
+        );
+        let redactor = crate::session::Redactor::default().with_known_secrets(known);
+        assert_eq!(redactor.redact_text("echo from-env").0, "echo ");
+    }
+
+    #[tokio::test]
+    async fn resolve_keeps_multi_line_values_and_honours_host_bindings() {
+        let store = SecretStore::new(
+            SecretsConfig {
+                providers: vec![SecretProviderKind::Command],
+                command: vec![
+                    "sh".to_string(),
+                    "-c".to_string(),
+                    r"printf 'line one\nline two\n\n'".to_string(),
+                    "sh".to_string(),
+                ],
+                allowed_hosts: BTreeMap::from([(
+                    "core-key".to_string(),
+                    vec!["core-*".to_string()],
+                )]),
+                ..SecretsConfig::default()
+            },
+            KnownSecrets::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            store
+                .resolve("secret://core-key", "core-sw1")
+                .await
+                .unwrap(),
+            "line one\nline two\n"
+        );
+        let err = store
+            .resolve("secret://core-key", "edge-sw1")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("edge-sw1"), "{err}");
+
+        let mut auth = SshAuth {
+            password_ref: Some("secret://core-key".to_string()),
+            ..SshAuth::default()
+        };
+        let err = store
+            .resolve_auth(&mut auth, "core-sw1", Some("ssh_options.extra_args"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("ssh_options.extra_args"), "{err}");
+        store
+            .resolve_auth(&mut auth, "core-sw1", None)
+            .await
+            .unwrap();
+        assert_eq!(auth.password.as_deref(), Some("line one\nline two\n"));
+    }
+}
@@ -54,6 +54,10 @@ impl OutputBuffer {
         self.enforce_limits()
     }
 
+    pub fn dropped_bytes_total(&self) -> u64 {
+        self.dropped_bytes_total
+    }
+
     pub fn buffer_start(&self) -> u64 {
         self.start_cursor
     }
@@ -66,6 +70,10 @@ impl OutputBuffer {
         self.data.len()
     }
 
+    pub fn limit_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
     pub fn slice_from(&self, cursor: u64, max_bytes: usize) -> BufferSlice {
         let end_cursor = self.buffer_end();
         if self.data.is_empty() {
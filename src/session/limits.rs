@@ -0,0 +1,363 @@
+use crate::config::SessionLimits;
+use crate::error::{ApiError, ErrorCode, PtyResult};
+use crate::policy::glob_regex;
+use crate::session::Protocol;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::Notify;
+use tokio::time::{Duration, Instant, timeout_at};
+
+/// Counts open sessions per limit key and hands out [`Slot`]s within the
+/// configured caps. `open` may wait for a slot; waiters retry whenever one is
+/// released or the caps change.
+pub(super) struct SessionLimiter {
+    caps: RwLock<Caps>,
+    counts: Mutex<HashMap<LimitKey, usize>>,
+    released: Notify,
+}
+
+struct Caps {
+    max_sessions: usize,
+    limits: SessionLimits,
+    hosts: Vec<(Regex, usize)>,
+}
+
+impl Caps {
+    /// Host patterns were already checked by `Config::validate`.
+    fn new(max_sessions: usize, limits: SessionLimits) -> Self {
+        let hosts = limits
+            .hosts
+            .iter()
+            .filter_map(|limit| {
+                glob_regex(&limit.pattern)
+                    .ok()
+                    .map(|regex| (regex, limit.max_sessions))
+            })
+            .collect();
+        Self {
+            max_sessions,
+            limits,
+            hosts,
+        }
+    }
+
+    fn cap(&self, key: &LimitKey) -> usize {
+        match key {
+            LimitKey::Total => self.max_sessions,
+            LimitKey::Host(host) => self
+                .hosts
+                .iter()
+                .find(|(pattern, _)| pattern.is_match(host))
+                .map_or(self.limits.per_host, |(_, cap)| *cap),
+            LimitKey::Device(_) => self.limits.per_device,
+            LimitKey::Client(_) => self.limits.per_client,
+            // Token caps come with the request; see `SlotRequest::token`.
+            LimitKey::Token(_) => 0,
+            LimitKey::Protocol(protocol) => self
+                .limits
+                .per_protocol
+                .get(protocol)
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum LimitKey {
+    Total,
+    Host(String),
+    Device(String),
+    Client(String),
+    Protocol(Protocol),
+    Token(String),
+}
+
+/// What a new session would count against.
+pub(super) struct SlotRequest<'a> {
+    pub host: &'a str,
+    pub device_id: Option<&'a str>,
+    pub client: Option<&'a str>,
+    pub protocol: Protocol,
+    /// HTTP token the session is opened with and its `max_sessions`.
+    pub token: Option<(&'a str, usize)>,
+}
+
+/// One open session's share of the limits; released on drop.
+pub(super) struct Slot {
+    limiter: Arc<SessionLimiter>,
+    keys: Vec<LimitKey>,
+}
+
+impl SessionLimiter {
+    pub(super) fn new(max_sessions: usize, limits: SessionLimits) -> Self {
+        Self {
+            caps: RwLock::new(Caps::new(max_sessions, limits)),
+            counts: Mutex::new(HashMap::new()),
+            released: Notify::new(),
+        }
+    }
+
+    /// Replaces the caps. Open sessions keep their slots even above a lowered
+    /// cap; queued opens retry against the new caps.
+    pub(super) fn reconfigure(&self, max_sessions: usize, limits: SessionLimits) {
+        *self.caps.write().expect("limit caps poisoned") = Caps::new(max_sessions, limits);
+        self.released.notify_waiters();
+    }
+
+    /// Takes a slot, waiting up to `wait` for one to free up.
+    pub(super) async fn acquire(
+        self: &Arc<Self>,
+        request: &SlotRequest<'_>,
+        wait: Duration,
+    ) -> PtyResult<Slot> {
+        let keys = self.keys(request);
+        let token_cap = request.token.map_or(0, |(_, cap)| cap);
+        let deadline = Instant::now() + wait;
+        loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            let full = match self.try_acquire(&keys, token_cap) {
+                Ok(()) => {
+                    return Ok(Slot {
+                        limiter: self.clone(),
+                        keys,
+                    });
+                }
+                Err(full) => full,
+            };
+            if Instant::now() >= deadline || timeout_at(deadline, released).await.is_err() {
+                let mut error = ApiError::new(ErrorCode::LimitExceeded, full.to_string());
+                if !wait.is_zero() {
+                    error = error
+                        .with_details(format!("no slot freed up within {} ms", wait.as_millis()));
+                }
+                return Err(error.into());
+            }
+        }
+    }
+
+    fn keys(&self, request: &SlotRequest<'_>) -> Vec<LimitKey> {
+        let mut keys = vec![
+            LimitKey::Total,
+            LimitKey::Host(request.host.to_string()),
+            LimitKey::Protocol(request.protocol),
+        ];
+        if let Some(device_id) = request.device_id {
+            keys.push(LimitKey::Device(device_id.to_string()));
+        }
+        if let Some(client) = request.client {
+            keys.push(LimitKey::Client(client.to_string()));
+        }
+        if let Some((token, _)) = request.token {
+            keys.push(LimitKey::Token(token.to_string()));
+        }
+        keys
+    }
+
+    fn try_acquire(&self, keys: &[LimitKey], token_cap: usize) -> Result<(), Full> {
+        let caps = self.caps.read().expect("limit caps poisoned");
+        let mut counts = self.counts.lock().expect("limit counts poisoned");
+        for key in keys {
+            let cap = match key {
+                LimitKey::Token(_) => token_cap,
+                key => caps.cap(key),
+            };
+            let open = counts.get(key).copied().unwrap_or_default();
+            // The global cap always applies; 0 disables the other limits.
+            if (cap > 0 || *key == LimitKey::Total) && open >= cap {
+                return Err(Full {
+                    key: key.clone(),
+                    cap,
+                });
+            }
+        }
+        for key in keys {
+            *counts.entry(key.clone()).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    fn release(&self, keys: &[LimitKey]) {
+        let mut counts = self.counts.lock().expect("limit counts poisoned");
+        for key in keys {
+            if let Some(open) = counts.get_mut(key) {
+                *open = open.saturating_sub(1);
+                if *open == 0 {
+                    counts.remove(key);
+                }
+            }
+        }
+        drop(counts);
+        self.released.notify_waiters();
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.limiter.release(&self.keys);
+    }
+}
+
+struct Full {
+    key: LimitKey,
+    cap: usize,
+}
+
+impl fmt::Display for Full {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.key {
+            LimitKey::Total => write!(f, "Too many sessions (limit {})", self.cap),
+            LimitKey::Host(host) => {
+                write!(f, "Too many sessions to host {host} (limit {})", self.cap)
+            }
+            LimitKey::Device(device) => {
+                write!(
+                    f,
+                    "Too many sessions to device {device} (limit {})",
+                    self.cap
+                )
+            }
+            LimitKey::Client(client) => {
+                write!(
+                    f,
+                    "Too many sessions for client {client} (limit {})",
+                    self.cap
+                )
+            }
+            LimitKey::Token(token) => {
+                write!(
+                    f,
+                    "Too many sessions for token '{token}' (limit {})",
+                    self.cap
+                )
+            }
+            LimitKey::Protocol(protocol) => write!(
+                f,
+                "Too many {} sessions (limit {})",
+                match protocol {
+                    Protocol::Ssh => "ssh",
+                    Protocol::Telnet => "telnet",
+                },
+                self.cap
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HostLimit;
+
+    #[tokio::test]
+    async fn enforces_host_overrides_and_waits_for_release() {
+        let limits = SessionLimits {
+            per_host: 3,
+            hosts: vec![HostLimit {
+                pattern: "core-*".to_string(),
+                max_sessions: 1,
+            }],
+            ..SessionLimits::default()
+        };
+        let limiter = Arc::new(SessionLimiter::new(10, limits));
+        let request = |host| SlotRequest {
+            host,
+            device_id: None,
+            client: Some("mcp:a"),
+            protocol: Protocol::Ssh,
+            token: None,
+        };
+        let first = limiter
+            .acquire(&request("core-1"), Duration::ZERO)
+            .await
+            .unwrap();
+        let err = limiter
+            .acquire(&request("core-1"), Duration::ZERO)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("host core-1 (limit 1)"), "{err}");
+        let _other = limiter
+            .acquire(&request("edge-1"), Duration::ZERO)
+            .await
+            .unwrap();
+
+        let waiter = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move {
+                limiter
+                    .acquire(&request("core-1"), Duration::from_secs(5))
+                    .await
+                    .map(|_| ())
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop(first);
+        waiter.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn reconfigure_wakes_queued_opens() {
+        let limiter = Arc::new(SessionLimiter::new(1, SessionLimits::default()));
+        let request = SlotRequest {
+            host: "core-1",
+            device_id: None,
+            client: None,
+            protocol: Protocol::Telnet,
+            token: None,
+        };
+        let _first = limiter.acquire(&request, Duration::ZERO).await.unwrap();
+        let waiter = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move {
+                let request = SlotRequest {
+                    host: "core-1",
+                    device_id: None,
+                    client: None,
+                    protocol: Protocol::Telnet,
+                    token: None,
+                };
+                limiter
+                    .acquire(&request, Duration::from_secs(5))
+                    .await
+                    .map(|_| ())
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        limiter.reconfigure(2, SessionLimits::default());
+        waiter.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn enforces_token_caps_atomically() {
+        let limiter = Arc::new(SessionLimiter::new(10, SessionLimits::default()));
+        let request = |token| SlotRequest {
+            host: "core-1",
+            device_id: None,
+            client: None,
+            protocol: Protocol::Ssh,
+            token: Some((token, 1)),
+        };
+        let _first = limiter
+            .acquire(&request("ci-bot"), Duration::ZERO)
+            .await
+            .unwrap();
+        let err = limiter
+            .acquire(&request("ci-bot"), Duration::ZERO)
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string().contains("token 'ci-bot' (limit 1)"),
+            "{err}"
+        );
+        limiter
+            .acquire(&request("other"), Duration::ZERO)
+            .await
+            .unwrap();
+    }
+}
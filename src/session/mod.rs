@@ -1,11 +1,19 @@
 mod buffer;
+mod limits;
+mod profile;
+mod reconnect;
+mod redact;
+mod screen;
 mod ssh;
 mod telnet;
+mod watch;
 
-use crate::config::{SessionConfig, SshConfig, TelnetLineEnding};
+use crate::config::{SessionConfig, SshConfig, TelnetConfig, TelnetLineEnding};
 use crate::error::{ApiError, ErrorCode, PtyResult};
 use async_trait::async_trait;
 use buffer::{BufferSlice, OutputBuffer, TailSlice};
+use limits::{SessionLimiter, Slot, SlotRequest};
+use reconnect::ReconnectPlan;
 use regex::Regex;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -14,14 +22,18 @@ use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use telnet::TelnetBackend;
+use telnet::{TelnetBackend, TelnetConnectParams};
 use tokio::sync::{Notify, RwLock};
 use tokio::time::{Instant, sleep};
 use uuid::Uuid;
 
 pub use buffer::{BufferSlice as OutputBufferSlice, TailSlice as OutputTailSlice};
+pub use profile::ProfileSummary;
+pub use redact::{KnownSecrets, Redactor};
+pub use screen::render_screen;
+pub use watch::{SessionEvent, SessionWatcher};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Protocol {
     Ssh,
@@ -73,6 +85,10 @@ impl Default for PtyOptions {
 pub struct Timeouts {
     pub connect_timeout_ms: Option<u64>,
     pub idle_timeout_ms: Option<u64>,
+    #[schemars(
+        description = "How long open waits for a free slot when a session limit is reached (defaults to session.limits.queue_timeout_ms)."
+    )]
+    pub queue_timeout_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
@@ -85,7 +101,7 @@ pub struct ExpectConfig {
     pub error_regexes: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 pub struct SshAuth {
     #[schemars(description = "Authentication method hint (optional).")]
     pub method: Option<String>,
@@ -95,6 +111,16 @@ pub struct SshAuth {
     pub private_key_pem: Option<String>,
     #[schemars(description = "Passphrase for the private key, if needed.")]
     pub passphrase: Option<String>,
+    #[schemars(description = "Server-side secret reference (secret://name) used as the password.")]
+    pub password_ref: Option<String>,
+    #[schemars(
+        description = "Server-side secret reference (secret://name) used as the key passphrase."
+    )]
+    pub passphrase_ref: Option<String>,
+    #[schemars(
+        description = "Server-side secret reference (secret://name) holding the private key PEM."
+    )]
+    pub private_key_ref: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
@@ -107,6 +133,70 @@ pub struct SshOptions {
     pub extra_args: Option<Vec<String>>,
 }
 
+impl SshOptions {
+    /// The first option set here that lets ssh connect somewhere other than
+    /// the requested host (`-oHostName`, `ProxyCommand`, a config file).
+    pub fn redirecting_option(&self) -> Option<&'static str> {
+        if self.use_openssh_config == Some(true) {
+            return Some("ssh_options.use_openssh_config");
+        }
+        if self.config_path.is_some() {
+            return Some("ssh_options.config_path");
+        }
+        if self
+            .extra_args
+            .as_ref()
+            .is_some_and(|args| !args.is_empty())
+        {
+            return Some("ssh_options.extra_args");
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct JumpHost {
+    #[schemars(description = "Jump host (bastion) address.")]
+    pub host: String,
+    #[schemars(description = "Jump host SSH port (default 22).")]
+    pub port: Option<u16>,
+    #[schemars(description = "Username on the jump host.")]
+    pub username: Option<String>,
+    #[schemars(
+        description = "Credentials for this hop. Secrets are handed to ssh via temp files, never argv."
+    )]
+    pub auth: Option<SshAuth>,
+    #[schemars(description = "Host key policy for this hop: strict/accept-new/disabled.")]
+    pub host_key_policy: Option<String>,
+    #[schemars(description = "known_hosts file for this hop.")]
+    pub known_hosts_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+pub struct ReconnectPolicy {
+    #[schemars(description = "Reconnect attempts per outage (default 3).")]
+    pub max_attempts: Option<u32>,
+    #[schemars(
+        description = "Delay before the first attempt; doubles per attempt (default 1000)."
+    )]
+    pub backoff_ms: Option<u64>,
+    #[schemars(description = "Upper bound for the backoff delay (default 30000).")]
+    pub max_backoff_ms: Option<u64>,
+    #[schemars(description = "Steps replayed after each successful reconnect, e.g. a login.")]
+    pub login_script: Option<Vec<LoginStep>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LoginStep {
+    #[schemars(description = "Regex to wait for before sending (optional).")]
+    pub expect_regex: Option<String>,
+    #[schemars(description = "Data to send, e.g. \"admin\\n\".")]
+    pub send: String,
+    pub sensitive: Option<bool>,
+    #[schemars(description = "Wait timeout for expect_regex (default 10000).")]
+    pub timeout_ms: Option<u64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionOpenRequest {
     pub protocol: Protocol,
@@ -117,6 +207,8 @@ pub struct SessionOpenRequest {
     pub pty: Option<PtyOptions>,
     pub timeouts: Option<Timeouts>,
     pub ssh_options: Option<SshOptions>,
+    pub jump_hosts: Option<Vec<JumpHost>>,
+    pub reconnect: Option<ReconnectPolicy>,
     #[schemars(description = "Expect configuration object.")]
     pub expect: Option<ExpectConfig>,
     pub session_type: Option<SessionType>,
@@ -124,6 +216,18 @@ pub struct SessionOpenRequest {
     pub acquire_lock: Option<bool>,
     pub lock_ttl_ms: Option<u64>,
     pub task_id: Option<String>,
+    #[schemars(description = "Host profile the session is opened from, if any.")]
+    pub profile: Option<String>,
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub opened_by: Option<String>,
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub owner: Option<String>,
+    /// HTTP token name and its `max_sessions`, counted by the session limiter.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub token_limit: Option<(String, usize)>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -179,6 +283,33 @@ pub struct InputHints {
     pub wait_for_regexes: Option<Vec<String>>,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ElicitKind {
+    Secret,
+    Confirm,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ElicitInput {
+    #[schemars(
+        description = "secret: ask the human for a password/passphrase; confirm: ask yes/no and send y or n."
+    )]
+    pub kind: ElicitKind,
+    #[schemars(
+        description = "Optional note for the human, shown quoted and labelled as coming from the agent under ptyctl's own question."
+    )]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ElicitationOutcome {
+    Accepted,
+    Declined,
+    Cancelled,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionReadResponse {
     pub chunk: String,
@@ -195,6 +326,7 @@ pub struct SessionReadResponse {
     pub dropped_bytes: u64,
     pub buffered_bytes: usize,
     pub buffer_limit_bytes: usize,
+    pub redactions: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -233,6 +365,39 @@ pub struct SessionListEntry {
     pub state: SessionState,
     pub session_type: SessionType,
     pub device_id: Option<String>,
+    pub reconnects: u64,
+    pub error_reason: Option<String>,
+    pub lock_holder: Option<String>,
+    pub buffered_bytes: usize,
+    pub buffer_limit_bytes: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opened_by: Option<String>,
+    #[serde(flatten)]
+    pub access: SessionAccess,
+}
+
+/// Which MCP clients may use a session: its owner plus the clients it was
+/// shared with (`*` for every client). No owner means any client.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SessionAccess {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shared_with: Vec<String>,
+}
+
+impl SessionAccess {
+    pub fn is_owner(&self, client: &str) -> bool {
+        self.owner.as_deref().is_none_or(|owner| owner == client)
+    }
+
+    pub fn allows(&self, client: &str) -> bool {
+        self.is_owner(client)
+            || self
+                .shared_with
+                .iter()
+                .any(|shared| shared == client || shared == "*")
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -270,6 +435,7 @@ pub struct SessionTailResponse {
     pub truncated: bool,
     pub buffered_bytes: usize,
     pub buffer_limit_bytes: usize,
+    pub redactions: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -301,6 +467,7 @@ pub struct SessionExecResponse {
     pub error_hints: Option<Vec<String>>,
     pub timed_out: bool,
     pub duration_ms: u64,
+    pub redactions: usize,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
@@ -313,6 +480,9 @@ pub enum SessionAction {
     Unlock,
     Heartbeat,
     Status,
+    Profiles,
+    Share,
+    Transfer,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
@@ -346,13 +516,19 @@ pub struct Capabilities {
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SessionRequest {
-    #[schemars(description = "Session action: open/close/list/lock/unlock/heartbeat/status.")]
+    #[schemars(
+        description = "Session action: open/close/list/lock/unlock/heartbeat/status/profiles/share/transfer."
+    )]
     pub action: SessionAction,
     #[schemars(
-        description = "Connection protocol (required for action=open): \"ssh\" or \"telnet\"."
+        description = "Named host profile from the server config (see action=profiles). Explicit fields override the profile."
+    )]
+    pub profile: Option<String>,
+    #[schemars(
+        description = "Connection protocol (required for action=open unless a profile sets it): \"ssh\" or \"telnet\"."
     )]
     pub protocol: Option<Protocol>,
-    #[schemars(description = "Remote host (required for action=open).")]
+    #[schemars(description = "Remote host (required for action=open unless a profile sets it).")]
     pub host: Option<String>,
     #[schemars(description = "Remote port (optional; defaults to 22 for ssh, 23 for telnet).")]
     pub port: Option<u16>,
@@ -366,6 +542,14 @@ pub struct SessionRequest {
     pub pty: Option<PtyOptions>,
     pub timeouts: Option<Timeouts>,
     pub ssh_options: Option<SshOptions>,
+    #[schemars(
+        description = "SSH jump hosts (bastions) in connection order, first hop first. Each hop may carry its own auth and host key policy."
+    )]
+    pub jump_hosts: Option<Vec<JumpHost>>,
+    #[schemars(
+        description = "Reconnect automatically under the same session_id when the transport drops."
+    )]
+    pub reconnect: Option<ReconnectPolicy>,
     pub expect: Option<ExpectConfig>,
     pub session_type: Option<SessionType>,
     pub device_id: Option<String>,
@@ -373,8 +557,15 @@ pub struct SessionRequest {
     pub lock_ttl_ms: Option<u64>,
     #[schemars(description = "Existing session id (required for non-open actions).")]
     pub session_id: Option<String>,
+    #[schemars(
+        description = "For action=close, kill the transport; for action=lock, take the lock over from its current holder (control socket only; MCP clients are refused)."
+    )]
     pub force: Option<bool>,
     pub task_id: Option<String>,
+    #[schemars(
+        description = "For action=share: the client to let use the session (\"*\" for every client). For action=transfer: the new owner. Clients learn their own id from action=list."
+    )]
+    pub client_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -388,10 +579,13 @@ pub struct SessionResponse {
     pub lock_acquired: Option<bool>,
     pub existing_session_id: Option<String>,
     pub sessions: Option<Vec<SessionListEntry>>,
+    pub profiles: Option<Vec<ProfileSummary>>,
     pub capabilities: Option<Capabilities>,
     pub lock_holder: Option<String>,
     pub lock_expires_at: Option<u64>,
     pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -401,6 +595,9 @@ pub struct SessionIoRequest {
     pub data: Option<String>,
     pub key: Option<SessionKey>,
     pub encoding: Option<Encoding>,
+    #[schemars(
+        description = "For action=write: flag the data as sensitive. It is still checked by policy and audited (redacted, with sensitive=true); pass passwords via secret_ref or elicit instead."
+    )]
     pub sensitive: Option<bool>,
     pub mode: Option<ReadMode>,
     pub cursor: Option<String>,
@@ -411,6 +608,14 @@ pub struct SessionIoRequest {
     pub include_match: Option<bool>,
     pub until_idle_ms: Option<u64>,
     pub input_hints: Option<InputHints>,
+    #[schemars(
+        description = "For action=write without data/key: ask the human client via MCP elicitation and write the answer with sensitive=true. The value never appears in tool results."
+    )]
+    pub elicit: Option<ElicitInput>,
+    #[schemars(
+        description = "For action=write: server-side secret reference (secret://name) to type; written with sensitive=true and never echoed back."
+    )]
+    pub secret_ref: Option<String>,
     pub task_id: Option<String>,
 }
 
@@ -432,6 +637,16 @@ pub struct SessionIoResponse {
     pub dropped_bytes: Option<u64>,
     pub buffered_bytes: Option<usize>,
     pub buffer_limit_bytes: Option<usize>,
+    pub elicitation: Option<ElicitationOutcome>,
+    pub redactions: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSubscribeRequest {
+    pub session_id: String,
+    pub cursor: Option<String>,
+    pub encoding: Option<Encoding>,
+    pub max_bytes: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -505,6 +720,7 @@ pub enum SessionState {
     Closing,
     Closed,
     Error,
+    Reconnecting,
 }
 
 #[async_trait]
@@ -530,11 +746,18 @@ pub struct Session {
     pub port: u16,
     pub session_type: SessionType,
     pub device_id: Option<String>,
+    pub profile: Option<String>,
+    /// Name of the HTTP token that opened the session, if any.
+    pub opened_by: Option<String>,
+    access: std::sync::Mutex<SessionAccess>,
+    /// Counts against the session limits until the session is closed.
+    slot: std::sync::Mutex<Option<Slot>>,
     buffer: Arc<Mutex<OutputBuffer>>,
     notify: Arc<Notify>,
-    backend: Box<dyn SessionBackend>,
+    backend: std::sync::RwLock<Arc<dyn SessionBackend>>,
     expect: Arc<RwLock<ExpectConfig>>,
-    state: AtomicU64,
+    state: Arc<AtomicU64>,
+    failure: Arc<Mutex<Option<String>>>,
     created_at: u64,
     last_activity: Arc<AtomicU64>,
     bytes_in: Arc<AtomicU64>,
@@ -546,6 +769,19 @@ pub struct Session {
     pty_cols: AtomicU64,
     pty_rows: AtomicU64,
     lock_holder: RwLock<Option<LockInfo>>,
+    lock_conflicts: AtomicU64,
+    reconnects: AtomicU64,
+    /// What has been typed at the remote prompt since the last Enter, so
+    /// policy checks see whole lines however a write was split.
+    input_line: std::sync::Mutex<InputLine>,
+}
+
+#[derive(Default)]
+struct InputLine {
+    typed: Vec<u8>,
+    /// A secret was written since the last Enter; only ending the line may
+    /// follow it, so it cannot become an argument of another command.
+    after_secret: bool,
 }
 
 #[derive(Clone)]
@@ -555,6 +791,8 @@ pub struct OutputHandle {
     notify: Arc<Notify>,
     bytes_in: Arc<AtomicU64>,
     last_activity: Arc<AtomicU64>,
+    state: Arc<AtomicU64>,
+    failure: Arc<Mutex<Option<String>>>,
 }
 
 impl OutputHandle {
@@ -573,6 +811,26 @@ impl OutputHandle {
         self.last_activity.store(now_ms(), Ordering::SeqCst);
         self.notify.notify_waiters();
     }
+
+    pub fn mark_failed(&self, reason: impl Into<String>) {
+        let reason = reason.into();
+        tracing::warn!(session_id = %self.session_id, reason = %reason, "Session transport failed");
+        *self.failure.lock().expect("failure mutex poisoned") = Some(reason);
+        let _ = self.state.compare_exchange(
+            SessionState::Open as u64,
+            SessionState::Error as u64,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+        self.notify.notify_waiters();
+    }
+}
+
+struct ConnectTarget {
+    request: SessionOpenRequest,
+    port: u16,
+    pty: PtyOptions,
+    connect_timeout_ms: u64,
 }
 
 struct SessionInit {
@@ -582,12 +840,18 @@ struct SessionInit {
     port: u16,
     session_type: SessionType,
     device_id: Option<String>,
+    profile: Option<String>,
+    opened_by: Option<String>,
+    owner: Option<String>,
+    slot: Option<Slot>,
     backend: Box<dyn SessionBackend>,
     buffer: Arc<Mutex<OutputBuffer>>,
     notify: Arc<Notify>,
     last_activity: Arc<AtomicU64>,
     bytes_in: Arc<AtomicU64>,
     bytes_out: Arc<AtomicU64>,
+    state: Arc<AtomicU64>,
+    failure: Arc<Mutex<Option<String>>>,
     expect: ExpectConfig,
     pty: PtyOptions,
     idle_timeout_ms: u64,
@@ -605,11 +869,19 @@ impl Session {
             port: init.port,
             session_type: init.session_type,
             device_id: init.device_id,
+            profile: init.profile,
+            opened_by: init.opened_by,
+            access: std::sync::Mutex::new(SessionAccess {
+                owner: init.owner,
+                shared_with: Vec::new(),
+            }),
+            slot: std::sync::Mutex::new(init.slot),
             buffer: init.buffer,
             notify: init.notify,
-            backend: init.backend,
+            backend: std::sync::RwLock::new(Arc::from(init.backend)),
             expect: Arc::new(RwLock::new(init.expect)),
-            state: AtomicU64::new(SessionState::Open as u64),
+            state: init.state,
+            failure: init.failure,
             created_at: now,
             last_activity: init.last_activity,
             bytes_in: init.bytes_in,
@@ -621,6 +893,9 @@ impl Session {
             pty_cols: AtomicU64::new(init.pty.cols as u64),
             pty_rows: AtomicU64::new(init.pty.rows as u64),
             lock_holder: RwLock::new(None),
+            lock_conflicts: AtomicU64::new(0),
+            reconnects: AtomicU64::new(0),
+            input_line: std::sync::Mutex::new(InputLine::default()),
         }
     }
 
@@ -629,12 +904,25 @@ impl Session {
             x if x == SessionState::Open as u64 => SessionState::Open,
             x if x == SessionState::Closing as u64 => SessionState::Closing,
             x if x == SessionState::Closed as u64 => SessionState::Closed,
+            x if x == SessionState::Reconnecting as u64 => SessionState::Reconnecting,
             _ => SessionState::Error,
         }
     }
 
     pub fn set_state(&self, state: SessionState) {
         self.state.store(state as u64, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    fn transition_state(&self, from: SessionState, to: SessionState) -> bool {
+        let changed = self
+            .state
+            .compare_exchange(from as u64, to as u64, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok();
+        if changed {
+            self.notify.notify_waiters();
+        }
+        changed
     }
 
     pub fn metrics(&self) -> (u64, u64, u64, u64) {
@@ -646,11 +934,33 @@ impl Session {
         )
     }
 
+    pub fn dropped_bytes_total(&self) -> u64 {
+        self.lock_buffer().dropped_bytes_total()
+    }
+
+    /// Lock or write attempts refused because another task held the lock.
+    pub fn lock_conflicts(&self) -> u64 {
+        self.lock_conflicts.load(Ordering::SeqCst)
+    }
+
     pub fn buffer_snapshot(&self) -> BufferSlice {
         let buffer = self.lock_buffer();
         buffer.slice_from(buffer.buffer_start(), buffer.buffered_bytes())
     }
 
+    /// Up to `max_bytes` of buffered output just before `cursor`.
+    pub fn output_before(&self, cursor: u64, max_bytes: usize) -> Vec<u8> {
+        let buffer = self.lock_buffer();
+        let end = cursor.min(buffer.buffer_end());
+        let start = end
+            .saturating_sub(max_bytes as u64)
+            .max(buffer.buffer_start());
+        if start >= end {
+            return Vec::new();
+        }
+        buffer.slice_from(start, (end - start) as usize).bytes
+    }
+
     pub fn tail(&self, max_bytes: usize, max_lines: Option<usize>) -> TailSlice {
         let buffer = self.lock_buffer();
         buffer.tail(max_bytes, max_lines)
@@ -685,6 +995,12 @@ impl Session {
         self.touch();
     }
 
+    pub fn append_notice(&self, notice: &str) {
+        let text = format!("\r\n[ptyctl] {}\r\n", notice);
+        self.lock_buffer_mut().append(text.as_bytes());
+        self.notify.notify_waiters();
+    }
+
     pub fn touch(&self) {
         self.last_activity.store(now_ms(), Ordering::SeqCst);
     }
@@ -698,11 +1014,72 @@ impl Session {
     }
 
     pub async fn write(&self, data: &[u8], sensitive: bool) -> PtyResult<usize> {
+        if self.state() == SessionState::Reconnecting {
+            return Err(ApiError::new(ErrorCode::RemoteClosed, "Session is reconnecting").into());
+        }
+        let ends_line = |byte: &u8| matches!(byte, b'\r' | b'\n' | 0x03 | 0x15);
+        if self.lock_input_line().after_secret && !data.iter().all(ends_line) {
+            return Err(ApiError::new(
+                ErrorCode::PermissionDenied,
+                "Only enter, ctrl_c or ctrl_u may follow a secret on the same line",
+            )
+            .into());
+        }
+        let written = self.write_backend(data, sensitive).await?;
+        let mut line = self.lock_input_line();
+        edit_input_line(&mut line.typed, data);
+        if data.iter().any(ends_line) {
+            line.after_secret = false;
+        }
+        Ok(written)
+    }
+
+    /// Writes a resolved or elicited secret. It is left out of the tracked
+    /// input line, so it never reaches policy checks or approval requests,
+    /// and is refused unless that line is empty: otherwise the Enter after it
+    /// would submit unchecked text typed before it.
+    pub async fn write_secret(&self, data: &[u8]) -> PtyResult<usize> {
+        if self.state() == SessionState::Reconnecting {
+            return Err(ApiError::new(ErrorCode::RemoteClosed, "Session is reconnecting").into());
+        }
+        self.ensure_empty_input_line()?;
+        let written = self.write_backend(data, true).await?;
+        self.lock_input_line().after_secret = true;
+        Ok(written)
+    }
+
+    /// Fails while text typed since the last Enter is pending on the line.
+    pub fn ensure_empty_input_line(&self) -> PtyResult<()> {
+        let line = self.lock_input_line();
+        if line.typed.is_empty() && !line.after_secret {
+            return Ok(());
+        }
+        Err(ApiError::new(
+            ErrorCode::PermissionDenied,
+            "Secrets can only be typed on an empty input line",
+        )
+        .with_details("press enter or ctrl_u to finish the pending line first")
+        .into())
+    }
+
+    /// The lines `data` would submit at the remote prompt, each including what
+    /// was typed before it since the last Enter, joined with newlines. Empty
+    /// when `data` does not press Enter.
+    pub fn submitted_input(&self, data: &[u8]) -> String {
+        let mut line = self.lock_input_line().typed.clone();
+        edit_input_line(&mut line, data).join("\n")
+    }
+
+    fn lock_input_line(&self) -> std::sync::MutexGuard<'_, InputLine> {
+        self.input_line.lock().expect("input line lock poisoned")
+    }
+
+    async fn write_backend(&self, data: &[u8], sensitive: bool) -> PtyResult<usize> {
         let mut payload = data.to_vec();
         if self.protocol == Protocol::Telnet {
             payload = normalize_telnet_line_endings(&payload, self.telnet_line_ending.clone());
         }
-        let written = self.backend.write(&payload).await?;
+        let written = self.backend().write(&payload).await?;
         self.bytes_out.fetch_add(written as u64, Ordering::SeqCst);
         self.touch();
         if self.record_tx_events && sensitive {
@@ -712,26 +1089,82 @@ impl Session {
     }
 
     pub async fn send_key(&self, key: SessionKey) -> PtyResult<usize> {
-        let bytes = key_bytes(self.protocol, key)?;
-        self.write(&bytes, false).await
+        self.write(&self.key_bytes(key)?, false).await
+    }
+
+    /// The bytes `key` sends on this session's protocol.
+    pub fn key_bytes(&self, key: SessionKey) -> PtyResult<Vec<u8>> {
+        key_bytes(self.protocol, key)
     }
 
     pub async fn resize(&self, cols: u16, rows: u16) -> PtyResult<()> {
-        self.backend.resize(cols, rows).await?;
+        self.backend().resize(cols, rows).await?;
         self.pty_cols.store(cols as u64, Ordering::SeqCst);
         self.pty_rows.store(rows as u64, Ordering::SeqCst);
+        self.notify.notify_waiters();
         Ok(())
     }
 
     pub async fn close(&self, force: bool) -> PtyResult<()> {
         self.set_state(SessionState::Closing);
-        let result = self.backend.close(force).await;
+        let result = self.backend().close(force).await;
         self.set_state(SessionState::Closed);
         result
     }
 
     pub fn is_eof(&self) -> bool {
-        self.backend.is_eof()
+        self.state() != SessionState::Reconnecting && self.backend().is_eof()
+    }
+
+    pub fn access(&self) -> SessionAccess {
+        self.access.lock().expect("session access poisoned").clone()
+    }
+
+    /// Lets `client` use the session alongside its owner.
+    pub fn share(&self, client: &str) {
+        let mut access = self.access.lock().expect("session access poisoned");
+        if !access.shared_with.iter().any(|shared| shared == client) {
+            access.shared_with.push(client.to_string());
+        }
+    }
+
+    /// Makes `client` the owner; the previous owner keeps no access unless shared.
+    pub fn transfer(&self, client: &str) {
+        let mut access = self.access.lock().expect("session access poisoned");
+        access.shared_with.retain(|shared| shared != client);
+        access.owner = Some(client.to_string());
+    }
+
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::SeqCst)
+    }
+
+    fn backend(&self) -> Arc<dyn SessionBackend> {
+        self.backend.read().expect("backend lock poisoned").clone()
+    }
+
+    fn replace_backend(&self, backend: Box<dyn SessionBackend>) {
+        *self.backend.write().expect("backend lock poisoned") = Arc::from(backend);
+    }
+
+    fn output_handle(&self) -> OutputHandle {
+        OutputHandle {
+            session_id: self.id.clone(),
+            buffer: self.buffer.clone(),
+            notify: self.notify.clone(),
+            bytes_in: self.bytes_in.clone(),
+            last_activity: self.last_activity.clone(),
+            state: self.state.clone(),
+            failure: self.failure.clone(),
+        }
+    }
+
+    pub fn failure(&self) -> Option<String> {
+        self.failure.lock().expect("failure mutex poisoned").clone()
+    }
+
+    fn clear_failure(&self) {
+        *self.failure.lock().expect("failure mutex poisoned") = None;
     }
 
     pub async fn set_expect(&self, expect: ExpectConfig) {
@@ -767,6 +1200,7 @@ impl Session {
                 )
             })?;
             if lock.task_id != task_id {
+                self.lock_conflicts.fetch_add(1, Ordering::SeqCst);
                 return Err(ApiError::new(
                     ErrorCode::InvalidArgument,
                     format!("Session locked by task {}", lock.task_id),
@@ -801,11 +1235,14 @@ impl Session {
                 info.heartbeat_interval_ms = ttl_ms;
                 Ok(info.clone())
             }
-            Some(info) => Err(ApiError::new(
-                ErrorCode::InvalidArgument,
-                format!("Session locked by task {}", info.task_id),
-            )
-            .into()),
+            Some(info) => {
+                self.lock_conflicts.fetch_add(1, Ordering::SeqCst);
+                Err(ApiError::new(
+                    ErrorCode::InvalidArgument,
+                    format!("Session locked by task {}", info.task_id),
+                )
+                .into())
+            }
             None => {
                 let info = LockInfo {
                     task_id: task_id.to_string(),
@@ -814,11 +1251,35 @@ impl Session {
                     heartbeat_interval_ms: ttl_ms,
                 };
                 *guard = Some(info.clone());
+                self.notify.notify_waiters();
                 Ok(info)
             }
         }
     }
 
+    pub async fn take_over_lock(&self, task_id: &str, ttl_ms: u64) -> (LockInfo, Option<String>) {
+        let now = now_ms();
+        let ttl_ms = ttl_ms.max(1);
+        let mut guard = self.lock_holder.write().await;
+        Self::prune_expired_lock(&mut guard, now);
+        let previous = guard
+            .as_ref()
+            .map(|info| info.task_id.clone())
+            .filter(|holder| holder != task_id);
+        let info = LockInfo {
+            task_id: task_id.to_string(),
+            acquired_at: now,
+            expires_at: now.saturating_add(ttl_ms),
+            heartbeat_interval_ms: ttl_ms,
+        };
+        *guard = Some(info.clone());
+        self.notify.notify_waiters();
+        if let Some(previous) = &previous {
+            tracing::warn!(session_id = %self.id, from = %previous, to = %task_id, "Session lock taken over");
+        }
+        (info, previous)
+    }
+
     pub async fn heartbeat(&self, task_id: &str, ttl_ms: Option<u64>) -> PtyResult<LockInfo> {
         let now = now_ms();
         let mut guard = self.lock_holder.write().await;
@@ -846,6 +1307,7 @@ impl Session {
         match guard.as_ref() {
             Some(info) if info.task_id == task_id => {
                 *guard = None;
+                self.notify.notify_waiters();
                 Ok(())
             }
             Some(info) => Err(ApiError::new(
@@ -858,43 +1320,151 @@ impl Session {
     }
 
     fn prune_expired_lock(lock: &mut Option<LockInfo>, now: u64) {
-        if let Some(info) = lock.as_ref() {
-            if info.expires_at <= now {
-                *lock = None;
-            }
+        if let Some(info) = lock.as_ref()
+            && info.expires_at <= now
+        {
+            *lock = None;
+        }
+    }
+}
+
+/// Counters summed over live sessions and sessions that have already closed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionTotals {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub dropped_bytes: u64,
+    pub lock_conflicts: u64,
+}
+
+impl SessionTotals {
+    fn add(&mut self, session: &Session) {
+        let (_, _, bytes_in, bytes_out) = session.metrics();
+        self.bytes_in += bytes_in;
+        self.bytes_out += bytes_out;
+        self.dropped_bytes += session.dropped_bytes_total();
+        self.lock_conflicts += session.lock_conflicts();
+    }
+}
+
+/// How a session ended during [`SessionManager::shutdown`].
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ShutdownOutcome {
+    /// The remote side closed the connection after the logout commands.
+    LoggedOut,
+    /// Still open when the grace period ended, so ptyctl closed it.
+    Closed,
+    Failed,
+}
+
+impl ShutdownOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ShutdownOutcome::LoggedOut => "logged_out",
+            ShutdownOutcome::Closed => "closed",
+            ShutdownOutcome::Failed => "failed",
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct ShutdownEntry {
+    pub session_id: String,
+    pub protocol: Protocol,
+    pub host: String,
+    pub outcome: ShutdownOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 pub struct SessionManager {
     sessions: RwLock<HashMap<String, Arc<Session>>>,
+    closed_totals: Mutex<SessionTotals>,
     console_sessions: RwLock<HashMap<String, String>>,
-    session_config: SessionConfig,
-    ssh_config: SshConfig,
-    telnet_line_ending: TelnetLineEnding,
+    limiter: Arc<SessionLimiter>,
+    session_config: std::sync::RwLock<Arc<SessionConfig>>,
+    ssh_config: std::sync::RwLock<Arc<SshConfig>>,
+    telnet_config: std::sync::RwLock<Arc<TelnetConfig>>,
     cleanup_running: AtomicBool,
+    shutting_down: AtomicBool,
 }
 
 impl SessionManager {
     pub fn new(
         session_config: SessionConfig,
         ssh_config: SshConfig,
-        telnet_line_ending: TelnetLineEnding,
+        telnet_config: TelnetConfig,
     ) -> Arc<Self> {
         Arc::new(Self {
             sessions: RwLock::new(HashMap::new()),
+            closed_totals: Mutex::new(SessionTotals::default()),
             console_sessions: RwLock::new(HashMap::new()),
-            session_config,
-            ssh_config,
-            telnet_line_ending,
+            limiter: Arc::new(SessionLimiter::new(
+                session_config.max_sessions,
+                session_config.limits.clone(),
+            )),
+            session_config: std::sync::RwLock::new(Arc::new(session_config)),
+            ssh_config: std::sync::RwLock::new(Arc::new(ssh_config)),
+            telnet_config: std::sync::RwLock::new(Arc::new(telnet_config)),
             cleanup_running: AtomicBool::new(false),
+            shutting_down: AtomicBool::new(false),
         })
     }
 
+    /// Applies reloaded settings to sessions opened from now on. Open sessions
+    /// keep their buffers, timeouts and transport options.
+    pub fn reconfigure(
+        &self,
+        session_config: SessionConfig,
+        ssh_config: SshConfig,
+        telnet_config: TelnetConfig,
+    ) {
+        self.limiter
+            .reconfigure(session_config.max_sessions, session_config.limits.clone());
+        *self
+            .session_config
+            .write()
+            .expect("session config lock poisoned") = Arc::new(session_config);
+        *self.ssh_config.write().expect("ssh config lock poisoned") = Arc::new(ssh_config);
+        *self
+            .telnet_config
+            .write()
+            .expect("telnet config lock poisoned") = Arc::new(telnet_config);
+    }
+
+    fn session_config(&self) -> Arc<SessionConfig> {
+        self.session_config
+            .read()
+            .expect("session config lock poisoned")
+            .clone()
+    }
+
+    fn ssh_config(&self) -> Arc<SshConfig> {
+        self.ssh_config
+            .read()
+            .expect("ssh config lock poisoned")
+            .clone()
+    }
+
+    fn telnet_config(&self) -> Arc<TelnetConfig> {
+        self.telnet_config
+            .read()
+            .expect("telnet config lock poisoned")
+            .clone()
+    }
+
     pub async fn open_session(
         self: &Arc<Self>,
         request: SessionOpenRequest,
     ) -> PtyResult<SessionOpenResponse> {
+        if self.is_shutting_down() {
+            return Err(ApiError::new(
+                ErrorCode::ShuttingDown,
+                "Server is shutting down; no new sessions are opened",
+            )
+            .into());
+        }
         let session_type = request.session_type.unwrap_or_default();
         let device_id = request.device_id.clone();
         if session_type == SessionType::Console {
@@ -929,30 +1499,53 @@ impl SessionManager {
             }
         }
 
-        if self.sessions.read().await.len() >= self.session_config.max_sessions {
-            return Err(ApiError::new(ErrorCode::InvalidArgument, "Too many sessions").into());
-        }
+        let session_config = self.session_config();
+        let queue_timeout_ms = request
+            .timeouts
+            .as_ref()
+            .and_then(|timeouts| timeouts.queue_timeout_ms)
+            .unwrap_or(session_config.limits.queue_timeout_ms);
+        let slot = self
+            .limiter
+            .acquire(
+                &SlotRequest {
+                    host: &request.host,
+                    device_id: device_id.as_deref(),
+                    client: request.owner.as_deref(),
+                    protocol: request.protocol,
+                    token: request
+                        .token_limit
+                        .as_ref()
+                        .map(|(name, cap)| (name.as_str(), *cap)),
+                },
+                Duration::from_millis(queue_timeout_ms),
+            )
+            .await?;
 
-        let pty = request.pty.unwrap_or_default();
+        let pty = request.pty.clone().unwrap_or_default();
         let port = match request.protocol {
             Protocol::Ssh => request.port.unwrap_or(22),
             Protocol::Telnet => request.port.unwrap_or(23),
         };
         let id = Uuid::new_v4().to_string();
         let buffer = Arc::new(Mutex::new(OutputBuffer::new(
-            self.session_config.output_buffer_max_bytes,
-            self.session_config.output_buffer_max_lines,
+            session_config.output_buffer_max_bytes,
+            session_config.output_buffer_max_lines,
         )));
         let notify = Arc::new(Notify::new());
         let last_activity = Arc::new(AtomicU64::new(now_ms()));
         let bytes_in = Arc::new(AtomicU64::new(0));
         let bytes_out = Arc::new(AtomicU64::new(0));
+        let state = Arc::new(AtomicU64::new(SessionState::Open as u64));
+        let failure = Arc::new(Mutex::new(None));
         let output = OutputHandle {
             session_id: id.clone(),
             buffer: buffer.clone(),
             notify: notify.clone(),
             bytes_in: bytes_in.clone(),
             last_activity: last_activity.clone(),
+            state: state.clone(),
+            failure: failure.clone(),
         };
         let expect = request.expect.clone().unwrap_or_default();
 
@@ -960,42 +1553,25 @@ impl SessionManager {
             .timeouts
             .as_ref()
             .and_then(|timeouts| timeouts.idle_timeout_ms)
-            .unwrap_or(self.session_config.idle_timeout_ms);
+            .unwrap_or(session_config.idle_timeout_ms);
         let connect_timeout_ms = request
             .timeouts
             .as_ref()
             .and_then(|timeouts| timeouts.connect_timeout_ms)
             .unwrap_or(15_000);
 
-        let backend: Box<dyn SessionBackend> = match request.protocol {
-            Protocol::Ssh => {
-                let backend = SshBackend::connect(SshConnectParams {
-                    session_id: &id,
-                    host: &request.host,
-                    port,
-                    username: request.username.clone(),
-                    auth: request.auth.clone(),
-                    options: request.ssh_options.clone(),
-                    ssh_config: &self.ssh_config,
-                    connect_timeout_ms,
-                    pty: pty.clone(),
-                    output: output.clone(),
-                })
-                .await?;
-                Box::new(backend)
-            }
-            Protocol::Telnet => {
-                let backend = TelnetBackend::connect(
-                    &request.host,
-                    port,
-                    pty.clone(),
-                    connect_timeout_ms,
-                    output.clone(),
-                )
-                .await?;
-                Box::new(backend)
-            }
+        let reconnect_plan = request
+            .reconnect
+            .as_ref()
+            .map(ReconnectPlan::compile)
+            .transpose()?;
+        let target = ConnectTarget {
+            request: request.clone(),
+            port,
+            pty: pty.clone(),
+            connect_timeout_ms,
         };
+        let backend = self.connect_backend(&id, &target, output).await?;
 
         let session = Arc::new(Session::new(SessionInit {
             id: id.clone(),
@@ -1004,27 +1580,38 @@ impl SessionManager {
             port,
             session_type,
             device_id: device_id.clone(),
+            profile: request.profile.clone(),
+            opened_by: request.opened_by.clone(),
+            owner: request.owner.clone(),
+            slot: Some(slot),
             backend,
             buffer,
             notify,
             last_activity,
             bytes_in,
             bytes_out,
+            state,
+            failure,
             expect,
             pty: pty.clone(),
             idle_timeout_ms: idle_timeout,
-            telnet_line_ending: self.telnet_line_ending.clone(),
-            record_tx_events: self.session_config.record_tx_events,
+            telnet_line_ending: self.telnet_config().line_ending.clone(),
+            record_tx_events: session_config.record_tx_events,
         }));
 
+        if let Some(plan) = reconnect_plan
+            && plan.is_enabled()
+        {
+            self.start_reconnect_task(&session, target, plan);
+        }
         self.sessions.write().await.insert(id.clone(), session);
-        if session_type == SessionType::Console {
-            if let Some(device_id) = device_id {
-                self.console_sessions
-                    .write()
-                    .await
-                    .insert(device_id, id.clone());
-            }
+        if session_type == SessionType::Console
+            && let Some(device_id) = device_id
+        {
+            self.console_sessions
+                .write()
+                .await
+                .insert(device_id, id.clone());
         }
 
         if !self.cleanup_running.load(Ordering::SeqCst) {
@@ -1046,6 +1633,49 @@ impl SessionManager {
         })
     }
 
+    async fn connect_backend(
+        &self,
+        session_id: &str,
+        target: &ConnectTarget,
+        output: OutputHandle,
+    ) -> PtyResult<Box<dyn SessionBackend>> {
+        let request = &target.request;
+        let backend: Box<dyn SessionBackend> = match request.protocol {
+            Protocol::Ssh => {
+                let backend = SshBackend::connect(SshConnectParams {
+                    session_id,
+                    host: &request.host,
+                    port: target.port,
+                    username: request.username.clone(),
+                    auth: request.auth.clone(),
+                    options: request.ssh_options.clone(),
+                    jump_hosts: request.jump_hosts.clone().unwrap_or_default(),
+                    ssh_config: &self.ssh_config(),
+                    connect_timeout_ms: target.connect_timeout_ms,
+                    pty: target.pty.clone(),
+                    output,
+                })
+                .await?;
+                Box::new(backend)
+            }
+            Protocol::Telnet => {
+                let telnet_config = self.telnet_config();
+                let backend = TelnetBackend::connect(TelnetConnectParams {
+                    host: &request.host,
+                    port: target.port,
+                    pty: target.pty.clone(),
+                    connect_timeout_ms: target.connect_timeout_ms,
+                    keepalive_interval_ms: telnet_config.keepalive_interval_ms,
+                    keepalive_count_max: telnet_config.keepalive_count_max,
+                    output,
+                })
+                .await?;
+                Box::new(backend)
+            }
+        };
+        Ok(backend)
+    }
+
     pub async fn get_session(&self, session_id: &str) -> PtyResult<Arc<Session>> {
         let sessions = self.sessions.read().await;
         sessions
@@ -1062,20 +1692,39 @@ impl SessionManager {
         let session_type = session.session_type;
         let device_id = session.device_id.clone();
         session.close(force).await?;
-        self.sessions.write().await.remove(session_id);
-        if session_type == SessionType::Console {
-            if let Some(device_id) = device_id {
-                self.console_sessions.write().await.remove(&device_id);
-            }
+        session.slot.lock().expect("session slot poisoned").take();
+        if self.sessions.write().await.remove(session_id).is_some() {
+            self.closed_totals
+                .lock()
+                .expect("totals mutex poisoned")
+                .add(&session);
+        }
+        if session_type == SessionType::Console
+            && let Some(device_id) = device_id
+        {
+            self.console_sessions.write().await.remove(&device_id);
         }
         Ok(())
     }
 
+    pub async fn totals(&self) -> SessionTotals {
+        let mut totals = *self.closed_totals.lock().expect("totals mutex poisoned");
+        for session in self.sessions.read().await.values() {
+            totals.add(session);
+        }
+        totals
+    }
+
     pub async fn list_sessions(&self) -> SessionListResponse {
         let sessions = self.sessions.read().await;
         let mut entries = Vec::with_capacity(sessions.len());
         for session in sessions.values() {
             let (created_at, last_activity, bytes_in, bytes_out) = session.metrics();
+            let lock_holder = session.lock_status().await.map(|info| info.task_id);
+            let (buffered_bytes, buffer_limit_bytes) = {
+                let buffer = session.lock_buffer();
+                (buffer.buffered_bytes(), buffer.limit_bytes())
+            };
             entries.push(SessionListEntry {
                 session_id: session.id.clone(),
                 protocol: session.protocol,
@@ -1088,11 +1737,76 @@ impl SessionManager {
                 state: session.state(),
                 session_type: session.session_type,
                 device_id: session.device_id.clone(),
+                reconnects: session.reconnects(),
+                error_reason: session.failure(),
+                lock_holder,
+                buffered_bytes,
+                buffer_limit_bytes,
+                opened_by: session.opened_by.clone(),
+                access: session.access(),
             });
         }
         SessionListResponse { sessions: entries }
     }
 
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Refuses further opens, then closes every session: each one is sent
+    /// `logout_commands` and given up to `grace` to exit on its own before
+    /// it is closed.
+    pub async fn shutdown(
+        &self,
+        logout_commands: &[String],
+        grace: Duration,
+    ) -> Vec<ShutdownEntry> {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        let sessions: Vec<Arc<Session>> = self.sessions.read().await.values().cloned().collect();
+        for session in &sessions {
+            // Closing also stops the reconnect task from reviving the session.
+            session.set_state(SessionState::Closing);
+            // Telnet devices expect CR, so use the session's own Enter.
+            let enter = session
+                .key_bytes(SessionKey::Enter)
+                .unwrap_or_else(|_| vec![b'\n']);
+            for command in logout_commands {
+                let line = [command.as_bytes(), &enter].concat();
+                if let Err(err) = session.write_backend(&line, false).await {
+                    tracing::debug!(session_id = %session.id, error = %err, "Logout command failed");
+                    break;
+                }
+            }
+        }
+        if !logout_commands.is_empty() {
+            let deadline = Instant::now() + grace;
+            while Instant::now() < deadline
+                && sessions.iter().any(|session| !session.backend().is_eof())
+            {
+                sleep(Duration::from_millis(100)).await;
+            }
+        }
+
+        let mut report = Vec::with_capacity(sessions.len());
+        for session in sessions {
+            let exited = session.backend().is_eof();
+            let result = self.close_session(&session.id, !exited).await;
+            let (outcome, error) = match result {
+                Ok(()) if exited => (ShutdownOutcome::LoggedOut, None),
+                Ok(()) => (ShutdownOutcome::Closed, None),
+                Err(err) => (ShutdownOutcome::Failed, Some(err.to_string())),
+            };
+            report.push(ShutdownEntry {
+                session_id: session.id.clone(),
+                protocol: session.protocol,
+                host: session.host.clone(),
+                outcome,
+                error,
+            });
+        }
+        report
+    }
+
     pub async fn cleanup_idle_sessions(&self) {
         let now = now_ms();
         let mut to_close = Vec::new();
@@ -1181,6 +1895,42 @@ fn key_bytes(protocol: Protocol, key: SessionKey) -> PtyResult<Vec<u8>> {
     Ok(bytes)
 }
 
+/// Applies `data` to the partially typed `line` roughly like a shell's line
+/// editor: Backspace deletes, Ctrl-C and Ctrl-U discard the line, escape
+/// sequences and other control bytes are ignored. Returns the non-empty lines
+/// submitted with CR or LF. This is best effort: completion or history recall
+/// on the remote side is not visible here.
+fn edit_input_line(line: &mut Vec<u8>, data: &[u8]) -> Vec<String> {
+    let mut submitted = Vec::new();
+    let mut bytes = data.iter().copied().peekable();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'\r' | b'\n' => {
+                let text = String::from_utf8_lossy(line).trim().to_string();
+                if !text.is_empty() {
+                    submitted.push(text);
+                }
+                line.clear();
+            }
+            0x7f | 0x08 => while line.pop().is_some_and(|byte| byte & 0xc0 == 0x80) {},
+            0x03 | 0x15 => line.clear(),
+            0x1b => {
+                if bytes.next_if(|byte| matches!(byte, b'[' | b'O')).is_some() {
+                    while bytes
+                        .next_if(|byte| !(0x40..=0x7e).contains(byte))
+                        .is_some()
+                    {}
+                    bytes.next();
+                }
+            }
+            b'\t' => line.push(byte),
+            byte if byte < 0x20 => {}
+            byte => line.push(byte),
+        }
+    }
+    submitted
+}
+
 fn now_ms() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -1232,18 +1982,17 @@ pub async fn read_from_session(
         if !slice.bytes.is_empty() {
             let mut matched = false;
             let mut bytes = slice.bytes.clone();
-            if let Some(regex) = &params.until_regex {
-                if let Ok(text) = std::str::from_utf8(&bytes) {
-                    if let Some(mat) = regex.find(text) {
-                        matched = true;
-                        let end = if params.include_match {
-                            mat.end()
-                        } else {
-                            mat.start()
-                        };
-                        bytes.truncate(end);
-                    }
-                }
+            if let Some(regex) = &params.until_regex
+                && let Ok(text) = std::str::from_utf8(&bytes)
+                && let Some(mat) = regex.find(text)
+            {
+                matched = true;
+                let end = if params.include_match {
+                    mat.end()
+                } else {
+                    mat.start()
+                };
+                bytes.truncate(end);
             }
 
             let waiting_for_input = params.input_hints.as_ref().map(|hints| {
@@ -1283,18 +2032,18 @@ pub async fn read_from_session(
             });
         }
 
-        if let Some(idle_deadline_at) = idle_deadline {
-            if Instant::now() >= idle_deadline_at {
-                return Ok(ReadResult {
-                    slice,
-                    matched: false,
-                    idle_reached: true,
-                    timed_out: false,
-                    eof: false,
-                    waiting_for_input: None,
-                    next_cursor: current_cursor,
-                });
-            }
+        if let Some(idle_deadline_at) = idle_deadline
+            && Instant::now() >= idle_deadline_at
+        {
+            return Ok(ReadResult {
+                slice,
+                matched: false,
+                idle_reached: true,
+                timed_out: false,
+                eof: false,
+                waiting_for_input: None,
+                next_cursor: current_cursor,
+            });
         }
 
         if Instant::now() >= deadline {
@@ -1312,10 +2061,10 @@ pub async fn read_from_session(
         let notify = session.notify.notified();
         let next_wait = {
             let mut next = deadline;
-            if let Some(idle) = idle_deadline {
-                if idle < next {
-                    next = idle;
-                }
+            if let Some(idle) = idle_deadline
+                && idle < next
+            {
+                next = idle;
             }
             next
         };
@@ -1369,6 +2118,21 @@ mod tests {
         assert_eq!(out, b"show\rrun\r");
     }
 
+    #[test]
+    fn edit_input_line_tracks_split_and_edited_input() {
+        let mut line = Vec::new();
+        assert!(edit_input_line(&mut line, b"rel").is_empty());
+        assert!(edit_input_line(&mut line, b"oax\x7fd").is_empty());
+        assert_eq!(edit_input_line(&mut line, b"\r"), ["reload"]);
+        assert!(line.is_empty());
+        assert_eq!(
+            edit_input_line(&mut line, b"junk\x15show \x1b[Dclock\nexit\n"),
+            ["show clock", "exit"]
+        );
+        edit_input_line(&mut line, b"erase\x03");
+        assert!(line.is_empty());
+    }
+
     #[test]
     fn key_bytes_enter_telnet() {
         let bytes = key_bytes(Protocol::Telnet, SessionKey::Enter).expect("key bytes");
@@ -1519,12 +2283,18 @@ mod tests {
             port: 22,
             session_type,
             device_id,
+            profile: None,
+            opened_by: None,
+            owner: None,
+            slot: None,
             backend: Box::new(backend),
             buffer,
             notify,
             last_activity,
             bytes_in,
             bytes_out,
+            state: Arc::new(AtomicU64::new(SessionState::Open as u64)),
+            failure: Arc::new(Mutex::new(None)),
             expect: ExpectConfig::default(),
             pty,
             idle_timeout_ms: 0,
@@ -1563,6 +2333,17 @@ mod tests {
         assert!(session.ensure_write_access(None).await.is_err());
     }
 
+    #[tokio::test]
+    async fn take_over_lock_replaces_holder() {
+        let session = build_session(SessionType::Normal);
+        session.lock("agent", 1000).await.expect("lock");
+        let (info, previous) = session.take_over_lock("human:ops", 1000).await;
+        assert_eq!(info.task_id, "human:ops");
+        assert_eq!(previous.as_deref(), Some("agent"));
+        assert!(session.ensure_write_access(Some("agent")).await.is_err());
+        assert!(session.ensure_write_access(Some("human:ops")).await.is_ok());
+    }
+
     #[tokio::test]
     async fn console_requires_lock_for_write() {
         let session = build_session(SessionType::Console);
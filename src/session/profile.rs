@@ -0,0 +1,372 @@
+use crate::config::{HostProfile, ProfileAuth};
+use crate::error::{ApiError, ErrorCode, PtyResult};
+use crate::session::{
+    ExpectConfig, JumpHost, Protocol, SessionRequest, SessionType, SshAuth, SshOptions, Timeouts,
+};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub protocol: Option<Protocol>,
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub auth_method: Option<String>,
+    pub jump_hosts: Vec<String>,
+    pub session_type: Option<SessionType>,
+    pub device_id: Option<String>,
+}
+
+impl ProfileSummary {
+    pub fn from_profile(profile: &HostProfile) -> Self {
+        Self {
+            name: profile.name.clone(),
+            description: profile.description.clone(),
+            tags: profile.tags.clone(),
+            protocol: profile.protocol,
+            host: profile.host.clone(),
+            port: profile.port,
+            username: profile.username.clone(),
+            auth_method: profile.auth.as_ref().and_then(auth_method),
+            jump_hosts: profile
+                .jump_hosts
+                .iter()
+                .flatten()
+                .map(|hop| hop.host.clone())
+                .collect(),
+            session_type: profile.session_type,
+            device_id: profile.device_id.clone(),
+        }
+    }
+}
+
+impl SessionRequest {
+    pub fn apply_profile(&mut self, profile: &HostProfile) -> PtyResult<()> {
+        // The profile's credentials only ever go to the profile's destination.
+        if self.auth.is_none()
+            && profile.auth.is_some()
+            && let Some(field) = self.overridden_destination(profile)
+        {
+            return Err(ApiError::new(
+                ErrorCode::InvalidArgument,
+                format!(
+                    "{field} cannot be overridden: profile {} supplies the credentials",
+                    profile.name
+                ),
+            )
+            .with_details("pass auth explicitly to connect elsewhere")
+            .into());
+        }
+        self.protocol = self.protocol.or(profile.protocol);
+        if self.host.is_none() {
+            self.host = Some(profile.host.clone());
+        }
+        self.port = self.port.or(profile.port);
+        if self.username.is_none() {
+            self.username = profile.username.clone();
+        }
+        if self.auth.is_none()
+            && let Some(auth) = &profile.auth
+        {
+            self.auth = Some(resolve_auth(&profile.name, auth)?);
+        }
+        if self.pty.is_none() {
+            self.pty = profile.pty.clone();
+        }
+        self.timeouts = merge_timeouts(self.timeouts.take(), profile.timeouts.as_ref());
+        self.ssh_options = merge_ssh_options(self.ssh_options.take(), profile.ssh_options.as_ref());
+        if self.jump_hosts.is_none() {
+            self.jump_hosts = profile.jump_hosts.clone();
+        }
+        if self.reconnect.is_none() {
+            self.reconnect = profile.reconnect.clone();
+        }
+        self.expect = merge_expect(self.expect.take(), profile.expect.as_ref());
+        self.session_type = self.session_type.or(profile.session_type);
+        if self.device_id.is_none() {
+            self.device_id = profile.device_id.clone();
+        }
+        Ok(())
+    }
+
+    /// The first destination field the request sets to something other than the profile.
+    fn overridden_destination(&self, profile: &HostProfile) -> Option<&'static str> {
+        if self
+            .protocol
+            .is_some_and(|protocol| Some(protocol) != profile.protocol)
+        {
+            return Some("protocol");
+        }
+        if self.host.as_ref().is_some_and(|host| *host != profile.host) {
+            return Some("host");
+        }
+        if self.port.is_some_and(|port| Some(port) != profile.port) {
+            return Some("port");
+        }
+        let jump_hosts = |hops: Option<&Vec<JumpHost>>| serde_json::to_value(hops).ok();
+        if self.jump_hosts.is_some()
+            && jump_hosts(self.jump_hosts.as_ref()) != jump_hosts(profile.jump_hosts.as_ref())
+        {
+            return Some("jump_hosts");
+        }
+        // ssh reads the real destination from its config and `-o` options too.
+        let requested = self.ssh_options.as_ref()?;
+        let profile_ssh = profile.ssh_options.clone().unwrap_or_default();
+        if requested
+            .use_openssh_config
+            .is_some_and(|value| Some(value) != profile_ssh.use_openssh_config)
+        {
+            return Some("ssh_options.use_openssh_config");
+        }
+        if requested.config_path.is_some() && requested.config_path != profile_ssh.config_path {
+            return Some("ssh_options.config_path");
+        }
+        if requested.extra_args.is_some() && requested.extra_args != profile_ssh.extra_args {
+            return Some("ssh_options.extra_args");
+        }
+        None
+    }
+}
+
+fn auth_method(auth: &ProfileAuth) -> Option<String> {
+    if auth.method.is_some() {
+        return auth.method.clone();
+    }
+    if auth.private_key_path.is_some() {
+        return Some("publickey".to_string());
+    }
+    if auth.password.is_some() || auth.password_env.is_some() || auth.password_ref.is_some() {
+        return Some("password".to_string());
+    }
+    None
+}
+
+fn resolve_auth(profile: &str, auth: &ProfileAuth) -> PtyResult<SshAuth> {
+    let password = resolve_secret(profile, auth.password.as_ref(), auth.password_env.as_ref())?;
+    let passphrase = resolve_secret(
+        profile,
+        auth.passphrase.as_ref(),
+        auth.passphrase_env.as_ref(),
+    )?;
+    let private_key_pem = auth
+        .private_key_path
+        .as_ref()
+        .map(|path| {
+            fs::read_to_string(path).map_err(|err| {
+                ApiError::new(
+                    ErrorCode::InvalidArgument,
+                    format!("Failed to read private key for profile {}", profile),
+                )
+                .with_details(err.to_string())
+            })
+        })
+        .transpose()?;
+    Ok(SshAuth {
+        method: auth.method.clone(),
+        password,
+        private_key_pem,
+        passphrase,
+        password_ref: auth.password_ref.clone(),
+        passphrase_ref: auth.passphrase_ref.clone(),
+        private_key_ref: None,
+    })
+}
+
+fn resolve_secret(
+    profile: &str,
+    value: Option<&String>,
+    env_name: Option<&String>,
+) -> PtyResult<Option<String>> {
+    if let Some(value) = value {
+        return Ok(Some(value.clone()));
+    }
+    let Some(name) = env_name else {
+        return Ok(None);
+    };
+    env::var(name).map(Some).map_err(|_| {
+        ApiError::new(
+            ErrorCode::InvalidArgument,
+            format!(
+                "Environment variable {} for profile {} is not set",
+                name, profile
+            ),
+        )
+        .into()
+    })
+}
+
+fn merge_timeouts(request: Option<Timeouts>, profile: Option<&Timeouts>) -> Option<Timeouts> {
+    let Some(profile) = profile else {
+        return request;
+    };
+    let request = request.unwrap_or_default();
+    Some(Timeouts {
+        connect_timeout_ms: request.connect_timeout_ms.or(profile.connect_timeout_ms),
+        idle_timeout_ms: request.idle_timeout_ms.or(profile.idle_timeout_ms),
+        queue_timeout_ms: request.queue_timeout_ms.or(profile.queue_timeout_ms),
+    })
+}
+
+fn merge_ssh_options(
+    request: Option<SshOptions>,
+    profile: Option<&SshOptions>,
+) -> Option<SshOptions> {
+    let Some(profile) = profile else {
+        return request;
+    };
+    let request = request.unwrap_or_default();
+    Some(SshOptions {
+        host_key_policy: request
+            .host_key_policy
+            .or_else(|| profile.host_key_policy.clone()),
+        known_hosts_path: request
+            .known_hosts_path
+            .or_else(|| profile.known_hosts_path.clone()),
+        host_key_fingerprint: request
+            .host_key_fingerprint
+            .or_else(|| profile.host_key_fingerprint.clone()),
+        use_openssh_config: request.use_openssh_config.or(profile.use_openssh_config),
+        config_path: request.config_path.or_else(|| profile.config_path.clone()),
+        extra_args: request.extra_args.or_else(|| profile.extra_args.clone()),
+    })
+}
+
+fn merge_expect(
+    request: Option<ExpectConfig>,
+    profile: Option<&ExpectConfig>,
+) -> Option<ExpectConfig> {
+    let Some(profile) = profile else {
+        return request;
+    };
+    let request = request.unwrap_or_default();
+    Some(ExpectConfig {
+        prompt_regex: request
+            .prompt_regex
+            .or_else(|| profile.prompt_regex.clone()),
+        pager_regexes: request
+            .pager_regexes
+            .or_else(|| profile.pager_regexes.clone()),
+        error_regexes: request
+            .error_regexes
+            .or_else(|| profile.error_regexes.clone()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionAction;
+
+    fn open_request() -> SessionRequest {
+        serde_json::from_value(serde_json::json!({
+            "action": "open",
+            "profile": "core-sw-1",
+            "port": 2222,
+            "expect": {"pager_regexes": ["--More--"]}
+        }))
+        .expect("request")
+    }
+
+    #[test]
+    fn request_fields_override_profile() {
+        let profile = HostProfile {
+            name: "core-sw-1".to_string(),
+            protocol: Some(Protocol::Ssh),
+            host: "10.0.0.1".to_string(),
+            port: Some(22),
+            username: Some("admin".to_string()),
+            expect: Some(ExpectConfig {
+                prompt_regex: Some("[#>]\\s*$".to_string()),
+                pager_regexes: None,
+                error_regexes: None,
+            }),
+            ..HostProfile::default()
+        };
+        let mut req = open_request();
+        req.apply_profile(&profile).expect("apply");
+        assert!(matches!(req.action, SessionAction::Open));
+        assert_eq!(req.protocol, Some(Protocol::Ssh));
+        assert_eq!(req.host.as_deref(), Some("10.0.0.1"));
+        assert_eq!(req.port, Some(2222));
+        assert_eq!(req.username.as_deref(), Some("admin"));
+        let expect = req.expect.expect("expect");
+        assert_eq!(expect.prompt_regex.as_deref(), Some("[#>]\\s*$"));
+        assert_eq!(expect.pager_regexes, Some(vec!["--More--".to_string()]));
+    }
+
+    #[test]
+    fn profile_credentials_stay_with_its_destination() {
+        let profile = HostProfile {
+            name: "core-sw-1".to_string(),
+            protocol: Some(Protocol::Ssh),
+            host: "10.0.0.1".to_string(),
+            auth: Some(ProfileAuth {
+                password: Some("hunter2".to_string()),
+                ..ProfileAuth::default()
+            }),
+            ..HostProfile::default()
+        };
+        let request = |overrides: serde_json::Value| -> SessionRequest {
+            let mut value = serde_json::json!({"action": "open", "profile": "core-sw-1"});
+            value
+                .as_object_mut()
+                .unwrap()
+                .extend(overrides.as_object().unwrap().clone());
+            serde_json::from_value(value).expect("request")
+        };
+        for overrides in [
+            serde_json::json!({"host": "203.0.113.9"}),
+            serde_json::json!({"protocol": "telnet"}),
+            serde_json::json!({"port": 2323}),
+            serde_json::json!({"jump_hosts": [{"host": "203.0.113.9"}]}),
+            serde_json::json!({"ssh_options": {"extra_args": ["-oHostName=203.0.113.9"]}}),
+            serde_json::json!({"ssh_options": {"config_path": "/tmp/evil_config"}}),
+            serde_json::json!({"ssh_options": {"use_openssh_config": true}}),
+        ] {
+            let err = request(overrides.clone())
+                .apply_profile(&profile)
+                .expect_err("override must be rejected");
+            assert!(
+                err.to_string().contains("supplies the credentials"),
+                "{overrides}: {err}"
+            );
+        }
+
+        let mut same = request(serde_json::json!({"host": "10.0.0.1", "protocol": "ssh"}));
+        same.apply_profile(&profile).expect("same destination");
+        assert_eq!(
+            same.auth.and_then(|auth| auth.password).as_deref(),
+            Some("hunter2")
+        );
+
+        let mut own =
+            request(serde_json::json!({"host": "203.0.113.9", "auth": {"password": "x"}}));
+        own.apply_profile(&profile).expect("own credentials");
+        assert_eq!(
+            own.auth.and_then(|auth| auth.password).as_deref(),
+            Some("x")
+        );
+    }
+
+    #[test]
+    fn summary_omits_secrets() {
+        let profile = HostProfile {
+            name: "core-sw-1".to_string(),
+            host: "10.0.0.1".to_string(),
+            auth: Some(ProfileAuth {
+                password: Some("hunter2".to_string()),
+                ..ProfileAuth::default()
+            }),
+            ..HostProfile::default()
+        };
+        let summary = ProfileSummary::from_profile(&profile);
+        assert_eq!(summary.auth_method.as_deref(), Some("password"));
+        let json = serde_json::to_string(&summary).expect("json");
+        assert!(!json.contains("hunter2"));
+    }
+}
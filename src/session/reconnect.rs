@@ -0,0 +1,227 @@
+use crate::error::{ApiError, ErrorCode, PtyResult};
+use crate::session::{
+    ConnectTarget, ReadParams, ReconnectPolicy, Session, SessionManager, SessionState,
+    read_from_session,
+};
+use regex::Regex;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use tokio::time::{Duration, Instant, sleep};
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+const DEFAULT_BACKOFF_MS: u64 = 1_000;
+const DEFAULT_MAX_BACKOFF_MS: u64 = 30_000;
+const DEFAULT_STEP_TIMEOUT_MS: u64 = 10_000;
+
+pub(super) struct ReconnectPlan {
+    max_attempts: u32,
+    backoff_ms: u64,
+    max_backoff_ms: u64,
+    steps: Vec<LoginStepPlan>,
+}
+
+struct LoginStepPlan {
+    expect_regex: Option<Regex>,
+    send: Vec<u8>,
+    sensitive: bool,
+    timeout_ms: u64,
+}
+
+impl ReconnectPlan {
+    pub(super) fn compile(policy: &ReconnectPolicy) -> PtyResult<Self> {
+        let mut steps = Vec::new();
+        for step in policy.login_script.iter().flatten() {
+            steps.push(LoginStepPlan {
+                expect_regex: step.expect_regex.as_deref().map(Regex::new).transpose()?,
+                send: step.send.as_bytes().to_vec(),
+                sensitive: step.sensitive.unwrap_or(false),
+                timeout_ms: step.timeout_ms.unwrap_or(DEFAULT_STEP_TIMEOUT_MS),
+            });
+        }
+        Ok(Self {
+            max_attempts: policy.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS),
+            backoff_ms: policy.backoff_ms.unwrap_or(DEFAULT_BACKOFF_MS),
+            max_backoff_ms: policy.max_backoff_ms.unwrap_or(DEFAULT_MAX_BACKOFF_MS),
+            steps,
+        })
+    }
+
+    pub(super) fn is_enabled(&self) -> bool {
+        self.max_attempts > 0
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        let delay = self
+            .backoff_ms
+            .saturating_mul(factor)
+            .min(self.max_backoff_ms.max(self.backoff_ms));
+        Duration::from_millis(delay)
+    }
+}
+
+impl SessionManager {
+    pub(super) fn start_reconnect_task(
+        self: &Arc<Self>,
+        session: &Arc<Session>,
+        target: ConnectTarget,
+        plan: ReconnectPlan,
+    ) {
+        let manager = Arc::clone(self);
+        let session = Arc::downgrade(session);
+        tokio::spawn(async move {
+            loop {
+                let Some(current) = session.upgrade() else {
+                    return;
+                };
+                if !matches!(current.state(), SessionState::Open | SessionState::Error) {
+                    return;
+                }
+                if current.backend().is_eof() {
+                    if !manager.reconnect(&current, &target, &plan).await {
+                        return;
+                    }
+                    continue;
+                }
+                let notify = current.notify.clone();
+                drop(current);
+                tokio::select! {
+                    _ = notify.notified() => {}
+                    _ = sleep(Duration::from_secs(1)) => {}
+                }
+            }
+        });
+    }
+
+    async fn reconnect(
+        &self,
+        session: &Arc<Session>,
+        target: &ConnectTarget,
+        plan: &ReconnectPlan,
+    ) -> bool {
+        if !session.transition_state(SessionState::Open, SessionState::Reconnecting)
+            && !session.transition_state(SessionState::Error, SessionState::Reconnecting)
+        {
+            return false;
+        }
+        tracing::warn!(session_id = %session.id, "Session transport lost; reconnecting");
+        for attempt in 1..=plan.max_attempts {
+            session.append_notice(&format!(
+                "connection lost; reconnecting (attempt {}/{})",
+                attempt, plan.max_attempts
+            ));
+            sleep(plan.backoff(attempt)).await;
+            if session.state() != SessionState::Reconnecting {
+                return false;
+            }
+
+            let cursor = session.buffer_end_cursor();
+            let backend = match self
+                .connect_backend(&session.id, target, session.output_handle())
+                .await
+            {
+                Ok(backend) => backend,
+                Err(err) => {
+                    tracing::warn!(session_id = %session.id, attempt, error = %err, "Reconnect failed");
+                    session
+                        .append_notice(&format!("reconnect attempt {} failed: {}", attempt, err));
+                    continue;
+                }
+            };
+            session.replace_backend(backend);
+            if session.state() != SessionState::Reconnecting {
+                let _ = session.backend().close(true).await;
+                return false;
+            }
+            if let Err(err) = run_login_script(session, cursor, &plan.steps).await {
+                tracing::warn!(session_id = %session.id, attempt, error = %err, "Login script failed");
+                session.append_notice(&format!("login script failed: {}", err));
+                let _ = session.backend().close(true).await;
+                continue;
+            }
+            if !session.transition_state(SessionState::Reconnecting, SessionState::Open) {
+                let _ = session.backend().close(true).await;
+                return false;
+            }
+            session.clear_failure();
+            session.reconnects.fetch_add(1, Ordering::SeqCst);
+            session.append_notice("reconnected");
+            tracing::info!(session_id = %session.id, attempt, "Session reconnected");
+            return true;
+        }
+        if session.transition_state(SessionState::Reconnecting, SessionState::Error) {
+            session.append_notice("reconnect failed; giving up");
+            tracing::warn!(session_id = %session.id, "Reconnect attempts exhausted");
+        }
+        false
+    }
+}
+
+async fn run_login_script(
+    session: &Arc<Session>,
+    mut cursor: u64,
+    steps: &[LoginStepPlan],
+) -> PtyResult<()> {
+    for step in steps {
+        if let Some(regex) = &step.expect_regex {
+            let deadline = Instant::now() + Duration::from_millis(step.timeout_ms);
+            let mut collected = Vec::new();
+            loop {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(ApiError::new(
+                        ErrorCode::ExecTimeout,
+                        format!("Timed out waiting for {}", regex.as_str()),
+                    )
+                    .into());
+                }
+                let read = read_from_session(
+                    session,
+                    ReadParams {
+                        cursor: Some(cursor),
+                        timeout_ms: (deadline - now).as_millis() as u64,
+                        max_bytes: 65536,
+                        until_regex: None,
+                        include_match: true,
+                        until_idle_ms: None,
+                        input_hints: None,
+                    },
+                )
+                .await?;
+                collected.extend_from_slice(&read.slice.bytes);
+                cursor = read.next_cursor;
+                if regex.is_match(&String::from_utf8_lossy(&collected)) {
+                    break;
+                }
+                if session.backend().is_eof() {
+                    return Err(ApiError::new(
+                        ErrorCode::RemoteClosed,
+                        "Remote closed during login script",
+                    )
+                    .into());
+                }
+            }
+        }
+        session.write_backend(&step.send, step.sensitive).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_and_caps() {
+        let plan = ReconnectPlan::compile(&ReconnectPolicy {
+            max_attempts: Some(5),
+            backoff_ms: Some(100),
+            max_backoff_ms: Some(350),
+            login_script: None,
+        })
+        .expect("plan");
+        assert_eq!(plan.backoff(1), Duration::from_millis(100));
+        assert_eq!(plan.backoff(2), Duration::from_millis(200));
+        assert_eq!(plan.backoff(3), Duration::from_millis(350));
+    }
+}
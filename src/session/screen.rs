@@ -0,0 +1,173 @@
+/// Replays terminal output onto a `cols` x `rows` grid and returns the visible rows.
+///
+/// This is a deliberately small interpreter: it applies carriage returns, backspaces,
+/// line wrapping, cursor movement and erase sequences, and drops every other escape
+/// sequence (colors, modes, OSC titles). It is meant to give a readable "what is on
+/// the screen" view, not a faithful VT100 emulation.
+pub fn render_screen(bytes: &[u8], cols: u16, rows: u16) -> String {
+    let cols = usize::from(cols.max(1));
+    let rows = usize::from(rows.max(1));
+    let text = String::from_utf8_lossy(bytes);
+    let mut screen = Grid::new(cols, rows);
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\n' => screen.line_feed(),
+            '\r' => screen.col = 0,
+            '\x08' => screen.col = screen.col.saturating_sub(1),
+            '\t' => {
+                let next = ((screen.col / 8) + 1) * 8;
+                screen.col = next.min(cols - 1);
+            }
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    let mut params = String::new();
+                    let mut final_byte = None;
+                    for next in chars.by_ref() {
+                        if ('@'..='~').contains(&next) {
+                            final_byte = Some(next);
+                            break;
+                        }
+                        params.push(next);
+                    }
+                    if let Some(final_byte) = final_byte {
+                        screen.apply_csi(&params, final_byte);
+                    }
+                }
+                Some(']') => {
+                    while let Some(next) = chars.next() {
+                        if next == '\x07' {
+                            break;
+                        }
+                        if next == '\x1b' {
+                            chars.next_if_eq(&'\\');
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            ch if ch.is_control() => {}
+            ch => screen.put(ch),
+        }
+    }
+    screen.visible()
+}
+
+struct Grid {
+    lines: Vec<Vec<char>>,
+    row: usize,
+    col: usize,
+    cols: usize,
+    rows: usize,
+}
+
+impl Grid {
+    fn new(cols: usize, rows: usize) -> Self {
+        Self {
+            lines: vec![Vec::new()],
+            row: 0,
+            col: 0,
+            cols,
+            rows,
+        }
+    }
+
+    fn origin(&self) -> usize {
+        self.lines.len().saturating_sub(self.rows)
+    }
+
+    fn line_feed(&mut self) {
+        self.row += 1;
+        self.col = 0;
+        self.ensure_row();
+    }
+
+    fn ensure_row(&mut self) {
+        while self.lines.len() <= self.row {
+            self.lines.push(Vec::new());
+        }
+    }
+
+    fn put(&mut self, ch: char) {
+        if self.col >= self.cols {
+            self.line_feed();
+        }
+        let line = &mut self.lines[self.row];
+        if line.len() <= self.col {
+            line.resize(self.col + 1, ' ');
+        }
+        line[self.col] = ch;
+        self.col += 1;
+    }
+
+    fn apply_csi(&mut self, params: &str, final_byte: char) {
+        let mut args = params
+            .trim_start_matches('?')
+            .split(';')
+            .map(|value| value.parse::<usize>().ok());
+        let first = args.next().flatten();
+        let count = first.unwrap_or(1).max(1);
+        match final_byte {
+            'A' => self.row = self.row.saturating_sub(count).max(self.origin()),
+            'B' => {
+                self.row += count;
+                self.ensure_row();
+            }
+            'C' => self.col = (self.col + count).min(self.cols - 1),
+            'D' => self.col = self.col.saturating_sub(count),
+            'G' => self.col = (count - 1).min(self.cols - 1),
+            'H' | 'f' => {
+                let column = args.next().flatten().unwrap_or(1).max(1);
+                self.row = self.origin() + (count - 1).min(self.rows - 1);
+                self.col = (column - 1).min(self.cols - 1);
+                self.ensure_row();
+            }
+            'K' => {
+                let line = &mut self.lines[self.row];
+                match first.unwrap_or(0) {
+                    0 => line.truncate(self.col),
+                    1 => line
+                        .iter_mut()
+                        .take(self.col + 1)
+                        .for_each(|cell| *cell = ' '),
+                    _ => line.clear(),
+                }
+            }
+            'J' => match first.unwrap_or(0) {
+                0 => {
+                    self.lines.truncate(self.row + 1);
+                    self.lines[self.row].truncate(self.col);
+                }
+                _ => {
+                    self.lines = vec![Vec::new()];
+                    self.row = 0;
+                    self.col = 0;
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn visible(&self) -> String {
+        let start = self.origin();
+        self.lines[start..]
+            .iter()
+            .map(|line| line.iter().collect::<String>().trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_screen_applies_overwrites_and_erases() {
+        let output = b"\x1b[1;32mrouter#\x1b[0m show\r\nline one\rLINE\r\n\x1b]0;title\x07abcdef";
+        assert_eq!(render_screen(output, 10, 3), "ow\nLINE one\nabcdef");
+        assert_eq!(render_screen(b"old\x1b[2Jnew\x1b[1Dx", 80, 24), "nex");
+        assert_eq!(render_screen(b"keep\x1b[3Gxy\x1b[K", 80, 24), "kexy");
+    }
+}
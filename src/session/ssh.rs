@@ -1,15 +1,16 @@
 use crate::config::SshConfig;
 use crate::error::{ApiError, ErrorCode, PtyResult};
-use crate::session::{OutputHandle, PtyOptions, SessionBackend, SshAuth, SshOptions};
+use crate::session::{JumpHost, OutputHandle, PtyOptions, SessionBackend, SshAuth, SshOptions};
 use async_trait::async_trait;
 use portable_pty::{CommandBuilder, MasterPty, PtySize, native_pty_system};
 use std::io::{Read, Write};
 use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use tempfile::NamedTempFile;
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempPath};
 
 pub struct SshBackend {
     writer: Arc<Mutex<Box<dyn Write + Send>>>,
@@ -17,6 +18,7 @@ pub struct SshBackend {
     child: Arc<Mutex<Box<dyn portable_pty::Child + Send>>>,
     eof: Arc<AtomicBool>,
     _key_file: Arc<Mutex<Option<NamedTempFile>>>,
+    _jump_files: Arc<Mutex<Vec<TempPath>>>,
 }
 
 pub(super) struct SshConnectParams<'a> {
@@ -26,6 +28,7 @@ pub(super) struct SshConnectParams<'a> {
     pub username: Option<String>,
     pub auth: Option<SshAuth>,
     pub options: Option<SshOptions>,
+    pub jump_hosts: Vec<JumpHost>,
     pub ssh_config: &'a SshConfig,
     pub connect_timeout_ms: u64,
     pub pty: PtyOptions,
@@ -38,11 +41,19 @@ struct SshArgsConfig<'a> {
     username: Option<String>,
     auth: Option<&'a SshAuth>,
     options: Option<&'a SshOptions>,
+    jump_hosts: &'a [JumpHost],
+    jump_files: &'a [JumpHopFiles],
     ssh_config: &'a SshConfig,
     key_path: Option<PathBuf>,
     connect_timeout_ms: u64,
 }
 
+#[derive(Debug, Default)]
+struct JumpHopFiles {
+    key_path: Option<PathBuf>,
+    askpass_path: Option<PathBuf>,
+}
+
 impl SshBackend {
     pub async fn connect(params: SshConnectParams<'_>) -> PtyResult<Self> {
         let pty_system = native_pty_system();
@@ -64,6 +75,7 @@ impl SshBackend {
             .and_then(|auth| auth.private_key_pem.as_ref())
             .map(|pem| write_temp_key(pem))
             .transpose()?;
+        let (jump_files, jump_temp_files) = prepare_jump_hosts(&params.jump_hosts)?;
 
         let mut cmd = CommandBuilder::new(&params.ssh_config.openssh_path);
         cmd.env("TERM", &params.pty.term);
@@ -74,6 +86,8 @@ impl SshBackend {
             username: params.username,
             auth: params.auth.as_ref(),
             options: params.options.as_ref(),
+            jump_hosts: &params.jump_hosts,
+            jump_files: &jump_files,
             ssh_config: params.ssh_config,
             key_path: key_file.as_ref().map(|file| file.path().to_path_buf()),
             connect_timeout_ms: params.connect_timeout_ms,
@@ -89,6 +103,8 @@ impl SshBackend {
             ApiError::new(ErrorCode::ConnectFailed, "Failed to spawn ssh")
                 .with_details(err.to_string())
         })?;
+        let child: Arc<Mutex<Box<dyn portable_pty::Child + Send>>> = Arc::new(Mutex::new(child));
+        let child_clone = child.clone();
 
         let mut reader = pair.master.try_clone_reader().map_err(|err| {
             ApiError::new(ErrorCode::IoError, "Failed to clone PTY reader")
@@ -105,11 +121,16 @@ impl SshBackend {
         let session_id = params.session_id.to_string();
         thread::spawn(move || {
             let mut buffer = [0u8; 4096];
+            let mut tail = Vec::new();
             loop {
                 match reader.read(&mut buffer) {
                     Ok(0) => break,
                     Ok(n) => {
                         output_clone.append_output(&buffer[..n]);
+                        tail.extend_from_slice(&buffer[..n]);
+                        if tail.len() > FAILURE_TAIL_BYTES {
+                            tail.drain(..tail.len() - FAILURE_TAIL_BYTES);
+                        }
                     }
                     Err(err) => {
                         tracing::warn!(
@@ -121,6 +142,9 @@ impl SshBackend {
                     }
                 }
             }
+            if let Some(reason) = transport_failure_reason(wait_for_exit(&child_clone), &tail) {
+                output_clone.mark_failed(reason);
+            }
             eof_flag.store(true, Ordering::SeqCst);
             output_clone.append_output(b"");
         });
@@ -128,9 +152,10 @@ impl SshBackend {
         Ok(Self {
             writer: Arc::new(Mutex::new(writer)),
             master: Arc::new(Mutex::new(pair.master)),
-            child: Arc::new(Mutex::new(child)),
+            child,
             eof,
             _key_file: Arc::new(Mutex::new(key_file)),
+            _jump_files: Arc::new(Mutex::new(jump_temp_files)),
         })
     }
 }
@@ -201,6 +226,45 @@ impl SessionBackend for SshBackend {
     }
 }
 
+const FAILURE_TAIL_BYTES: usize = 1024;
+/// ssh's exit status when the connection itself failed.
+const SSH_CONNECTION_ERROR: u32 = 255;
+const EXIT_WAIT: Duration = Duration::from_secs(2);
+
+/// Best-effort heuristic: ssh exits 255 when the connection fails, which a
+/// remote command exiting 255 cannot be told apart from. The wording of
+/// ssh's last message only refines the reason.
+fn transport_failure_reason(exit_code: Option<u32>, tail: &[u8]) -> Option<String> {
+    if exit_code != Some(SSH_CONNECTION_ERROR) {
+        return None;
+    }
+    let text = String::from_utf8_lossy(tail);
+    if text.contains("not responding") {
+        return Some("Peer stopped responding (SSH keepalive timeout)".to_string());
+    }
+    let reason = ["Broken pipe", "Connection reset", "Connection timed out"]
+        .iter()
+        .find(|marker| text.contains(*marker))
+        .map_or_else(
+            || format!("SSH transport failed (exit status {SSH_CONNECTION_ERROR})"),
+            |marker| format!("SSH transport failed: {marker}"),
+        );
+    Some(reason)
+}
+
+/// The ssh exit code, once the PTY has hit EOF and ssh is exiting.
+fn wait_for_exit(child: &Mutex<Box<dyn portable_pty::Child + Send>>) -> Option<u32> {
+    let deadline = std::time::Instant::now() + EXIT_WAIT;
+    while std::time::Instant::now() < deadline {
+        match child.lock().expect("child mutex poisoned").try_wait() {
+            Ok(Some(status)) => return Some(status.exit_code()),
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(_) => return None,
+        }
+    }
+    None
+}
+
 fn build_ssh_args(config: SshArgsConfig<'_>) -> PtyResult<Vec<String>> {
     let mut args = Vec::new();
     args.push("-p".to_string());
@@ -215,18 +279,8 @@ fn build_ssh_args(config: SshArgsConfig<'_>) -> PtyResult<Vec<String>> {
         .options
         .and_then(|opts| opts.host_key_policy.clone())
         .unwrap_or_else(|| format!("{:?}", config.ssh_config.host_key_policy));
-    match host_key_policy.to_ascii_lowercase().as_str() {
-        "strict" => args.push("-o".to_string()),
-        "acceptnew" | "accept_new" | "accept-new" => args.push("-o".to_string()),
-        "disabled" => args.push("-o".to_string()),
-        _ => args.push("-o".to_string()),
-    }
-    let policy_value = match host_key_policy.to_ascii_lowercase().as_str() {
-        "acceptnew" | "accept_new" | "accept-new" => "StrictHostKeyChecking=accept-new",
-        "disabled" => "StrictHostKeyChecking=no",
-        _ => "StrictHostKeyChecking=yes",
-    };
-    args.push(policy_value.to_string());
+    args.push("-o".to_string());
+    args.push(host_key_policy_option(&host_key_policy).to_string());
 
     let known_hosts_path = config
         .options
@@ -238,13 +292,14 @@ fn build_ssh_args(config: SshArgsConfig<'_>) -> PtyResult<Vec<String>> {
         args.push(format!("UserKnownHostsFile={}", known_hosts_path));
     }
 
+    let mut config_file_args = Vec::new();
     let use_config = config
         .options
         .and_then(|opts| opts.use_openssh_config)
         .unwrap_or(config.ssh_config.use_openssh_config);
     if !use_config {
-        args.push("-F".to_string());
-        args.push("/dev/null".to_string());
+        config_file_args.push("-F".to_string());
+        config_file_args.push("/dev/null".to_string());
     } else {
         let config_path = config
             .options
@@ -252,12 +307,74 @@ fn build_ssh_args(config: SshArgsConfig<'_>) -> PtyResult<Vec<String>> {
             .filter(|path| !path.is_empty())
             .unwrap_or_else(|| config.ssh_config.config_path.clone());
         if !config_path.is_empty() {
-            args.push("-F".to_string());
-            args.push(config_path);
+            config_file_args.push("-F".to_string());
+            config_file_args.push(config_path);
+        }
+    }
+    args.extend_from_slice(&config_file_args);
+
+    push_auth_preferences(&mut args, config.auth);
+
+    if let Some(opts) = config.options
+        && let Some(extra) = &opts.extra_args
+    {
+        for arg in extra {
+            args.push(arg.clone());
         }
     }
 
-    if let Some(auth) = config.auth {
+    let mut connection_args = Vec::new();
+    if config.connect_timeout_ms > 0 {
+        connection_args.push("-o".to_string());
+        let seconds = (config.connect_timeout_ms as f64 / 1000.0).ceil() as u64;
+        connection_args.push(format!("ConnectTimeout={}", seconds.max(1)));
+    }
+    if config.ssh_config.server_alive_interval_ms > 0 {
+        connection_args.push("-o".to_string());
+        connection_args.push(format!(
+            "ServerAliveInterval={}",
+            config.ssh_config.server_alive_interval_ms.div_ceil(1000)
+        ));
+        connection_args.push("-o".to_string());
+        connection_args.push(format!(
+            "ServerAliveCountMax={}",
+            config.ssh_config.server_alive_count_max.max(1)
+        ));
+    }
+    args.extend_from_slice(&connection_args);
+
+    if let Some(path) = config.key_path {
+        args.push("-i".to_string());
+        args.push(path.to_string_lossy().to_string());
+    }
+
+    if !config.jump_hosts.is_empty() {
+        let proxy_command = build_jump_proxy_command(&JumpChain {
+            openssh_path: &config.ssh_config.openssh_path,
+            hops: config.jump_hosts,
+            files: config.jump_files,
+            default_host_key_policy: &host_key_policy,
+            default_known_hosts_path: &known_hosts_path,
+            shared_args: &[config_file_args, connection_args].concat(),
+        })?;
+        args.push("-o".to_string());
+        args.push(format!("ProxyCommand={}", proxy_command));
+    }
+
+    args.push(config.host.to_string());
+    Ok(args)
+}
+
+fn host_key_policy_option(policy: &str) -> &'static str {
+    match policy.to_ascii_lowercase().as_str() {
+        "acceptnew" | "accept_new" | "accept-new" => "StrictHostKeyChecking=accept-new",
+        "disabled" => "StrictHostKeyChecking=no",
+        _ => "StrictHostKeyChecking=yes",
+    }
+}
+
+fn push_auth_preferences(args: &mut Vec<String>, auth: Option<&SshAuth>) {
+    if let Some(auth) = auth {
         if auth.method.as_deref() == Some("agent") {
             args.push("-o".to_string());
             args.push("PreferredAuthentications=publickey".to_string());
@@ -267,50 +384,179 @@ fn build_ssh_args(config: SshArgsConfig<'_>) -> PtyResult<Vec<String>> {
             args.push("PreferredAuthentications=password,keyboard-interactive".to_string());
         }
     }
+}
 
-    if let Some(opts) = config.options {
-        if let Some(extra) = &opts.extra_args {
-            for arg in extra {
-                args.push(arg.clone());
-            }
-        }
+struct JumpChain<'a> {
+    openssh_path: &'a str,
+    hops: &'a [JumpHost],
+    files: &'a [JumpHopFiles],
+    default_host_key_policy: &'a str,
+    default_known_hosts_path: &'a str,
+    shared_args: &'a [String],
+}
+
+// Earlier hops nest as ProxyCommands of later ones; `%` is escaped per level.
+fn build_jump_proxy_command(chain: &JumpChain<'_>) -> PtyResult<String> {
+    let Some((hop, earlier_hops)) = chain.hops.split_last() else {
+        return Err(ApiError::new(ErrorCode::InvalidArgument, "jump_hosts is empty").into());
+    };
+    validate_ssh_word(&hop.host, "jump host")?;
+    let files = chain.files.get(earlier_hops.len());
+
+    let mut words = Vec::new();
+    if let Some(askpass) = files.and_then(|files| files.askpass_path.as_deref()) {
+        words.push("env".to_string());
+        words.push(shell_quote(&format!("SSH_ASKPASS={}", askpass.display())));
+        words.push("SSH_ASKPASS_REQUIRE=force".to_string());
     }
+    words.push(shell_quote(chain.openssh_path));
 
-    if config.connect_timeout_ms > 0 {
-        args.push("-o".to_string());
-        let seconds = (config.connect_timeout_ms as f64 / 1000.0).ceil() as u64;
-        args.push(format!("ConnectTimeout={}", seconds.max(1)));
+    let mut hop_args = vec!["-p".to_string(), hop.port.unwrap_or(22).to_string()];
+    if let Some(user) = &hop.username {
+        validate_ssh_word(user, "jump host username")?;
+        hop_args.push("-l".to_string());
+        hop_args.push(user.clone());
+    }
+    let policy = hop
+        .host_key_policy
+        .as_deref()
+        .unwrap_or(chain.default_host_key_policy);
+    hop_args.push("-o".to_string());
+    hop_args.push(host_key_policy_option(policy).to_string());
+    let known_hosts_path = hop
+        .known_hosts_path
+        .as_deref()
+        .filter(|path| !path.is_empty())
+        .unwrap_or(chain.default_known_hosts_path);
+    if !known_hosts_path.is_empty() {
+        hop_args.push("-o".to_string());
+        hop_args.push(format!("UserKnownHostsFile={}", known_hosts_path));
     }
+    hop_args.extend_from_slice(chain.shared_args);
+    push_auth_preferences(&mut hop_args, hop.auth.as_ref());
+    if let Some(key_path) = files.and_then(|files| files.key_path.as_deref()) {
+        hop_args.push("-i".to_string());
+        hop_args.push(key_path.to_string_lossy().to_string());
+    }
+    if !earlier_hops.is_empty() {
+        let inner = build_jump_proxy_command(&JumpChain {
+            hops: earlier_hops,
+            ..*chain
+        })?;
+        hop_args.push("-o".to_string());
+        hop_args.push(format!("ProxyCommand={}", inner.replace('%', "%%")));
+    }
+    hop_args.push("-W".to_string());
+    hop_args.push("[%h]:%p".to_string());
+    hop_args.push(hop.host.clone());
 
-    if let Some(path) = config.key_path {
-        args.push("-i".to_string());
-        args.push(path.to_string_lossy().to_string());
+    words.extend(hop_args.iter().map(|arg| shell_quote(arg)));
+    Ok(words.join(" "))
+}
+
+fn validate_ssh_word(value: &str, what: &str) -> PtyResult<()> {
+    if value.is_empty() || value.starts_with('-') || value.chars().any(char::is_whitespace) {
+        return Err(ApiError::new(
+            ErrorCode::InvalidArgument,
+            format!("Invalid {}: {:?}", what, value),
+        )
+        .into());
     }
+    Ok(())
+}
 
-    args.push(config.host.to_string());
-    Ok(args)
+fn shell_quote(value: &str) -> String {
+    let is_plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@+".contains(c));
+    if is_plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+fn prepare_jump_hosts(hops: &[JumpHost]) -> PtyResult<(Vec<JumpHopFiles>, Vec<TempPath>)> {
+    let mut files = Vec::with_capacity(hops.len());
+    let mut temp_paths = Vec::new();
+    for hop in hops {
+        validate_ssh_word(&hop.host, "jump host")?;
+        let mut hop_files = JumpHopFiles::default();
+        let Some(auth) = &hop.auth else {
+            files.push(hop_files);
+            continue;
+        };
+        if let Some(pem) = &auth.private_key_pem {
+            let key = write_temp_key(pem)?.into_temp_path();
+            hop_files.key_path = Some(key.to_path_buf());
+            temp_paths.push(key);
+        }
+        let password = auth
+            .password
+            .as_deref()
+            .map(|password| write_secret_file(password.as_bytes(), 0o600))
+            .transpose()?
+            .map(NamedTempFile::into_temp_path);
+        let passphrase = auth
+            .passphrase
+            .as_deref()
+            .map(|passphrase| write_secret_file(passphrase.as_bytes(), 0o600))
+            .transpose()?
+            .map(NamedTempFile::into_temp_path);
+        let script = match (&password, &passphrase) {
+            (Some(password), Some(passphrase)) => Some(format!(
+                "#!/bin/sh\ncase \"$1\" in\n*assphrase*) exec cat {} ;;\n*) exec cat {} ;;\nesac\n",
+                shell_quote_path(passphrase),
+                shell_quote_path(password)
+            )),
+            (Some(secret), None) | (None, Some(secret)) => Some(format!(
+                "#!/bin/sh\nexec cat {}\n",
+                shell_quote_path(secret)
+            )),
+            (None, None) => None,
+        };
+        if let Some(script) = script {
+            // The script must be closed before ssh can exec it.
+            let askpass = write_secret_file(script.as_bytes(), 0o700)?.into_temp_path();
+            hop_files.askpass_path = Some(askpass.to_path_buf());
+            temp_paths.push(askpass);
+        }
+        temp_paths.extend(password);
+        temp_paths.extend(passphrase);
+        files.push(hop_files);
+    }
+    Ok((files, temp_paths))
+}
+
+fn shell_quote_path(path: &Path) -> String {
+    shell_quote(&path.to_string_lossy())
 }
 
 fn write_temp_key(pem: &str) -> PtyResult<NamedTempFile> {
+    write_secret_file(pem.as_bytes(), 0o600)
+}
+
+fn write_secret_file(contents: &[u8], mode: u32) -> PtyResult<NamedTempFile> {
     let mut file = NamedTempFile::new().map_err(|err| {
-        ApiError::new(ErrorCode::IoError, "Failed to create temp key file")
-            .with_details(err.to_string())
-    })?;
-    file.write_all(pem.as_bytes()).map_err(|err| {
-        ApiError::new(ErrorCode::IoError, "Failed to write private key")
+        ApiError::new(ErrorCode::IoError, "Failed to create temp secret file")
             .with_details(err.to_string())
     })?;
     let mut perms = file
         .as_file()
         .metadata()
         .map_err(|err| {
-            ApiError::new(ErrorCode::IoError, "Failed to read key file metadata")
+            ApiError::new(ErrorCode::IoError, "Failed to read secret file metadata")
                 .with_details(err.to_string())
         })?
         .permissions();
-    perms.set_mode(0o600);
+    perms.set_mode(mode);
     file.as_file().set_permissions(perms).map_err(|err| {
-        ApiError::new(ErrorCode::IoError, "Failed to set key permissions")
+        ApiError::new(ErrorCode::IoError, "Failed to set secret file permissions")
+            .with_details(err.to_string())
+    })?;
+    file.write_all(contents).map_err(|err| {
+        ApiError::new(ErrorCode::IoError, "Failed to write secret file")
             .with_details(err.to_string())
     })?;
     Ok(file)
@@ -329,6 +575,8 @@ mod tests {
             username: Some("root".to_string()),
             auth: None,
             options: None,
+            jump_hosts: &[],
+            jump_files: &[],
             ssh_config: &ssh_config,
             key_path: None,
             connect_timeout_ms: 15000,
@@ -338,4 +586,148 @@ mod tests {
         assert!(args.contains(&"-p".to_string()));
         assert!(args.contains(&"22".to_string()));
     }
+
+    #[test]
+    fn build_ssh_args_sets_server_alive() {
+        let ssh_config = SshConfig {
+            server_alive_interval_ms: 15_000,
+            server_alive_count_max: 4,
+            ..SshConfig::default()
+        };
+        let hops = vec![jump_host("bastion", None)];
+        let files = vec![JumpHopFiles::default()];
+        let args = build_ssh_args(SshArgsConfig {
+            host: "example.com",
+            port: 22,
+            username: None,
+            auth: None,
+            options: None,
+            jump_hosts: &hops,
+            jump_files: &files,
+            ssh_config: &ssh_config,
+            key_path: None,
+            connect_timeout_ms: 0,
+        })
+        .expect("args");
+        assert!(args.contains(&"ServerAliveInterval=15".to_string()));
+        assert!(args.contains(&"ServerAliveCountMax=4".to_string()));
+        let proxy = args
+            .iter()
+            .find(|arg| arg.starts_with("ProxyCommand="))
+            .expect("proxy command");
+        assert!(proxy.contains("ServerAliveInterval=15"));
+    }
+
+    #[test]
+    fn transport_failure_reason_detects_keepalive_timeout() {
+        let reason = transport_failure_reason(
+            Some(255),
+            b"Timeout, server example.com not responding.\r\n",
+        );
+        assert_eq!(
+            reason.as_deref(),
+            Some("Peer stopped responding (SSH keepalive timeout)")
+        );
+        assert!(transport_failure_reason(Some(255), b"logout\r\n").is_some());
+        assert!(transport_failure_reason(Some(0), b"server not responding\r\n").is_none());
+        assert!(transport_failure_reason(Some(1), b"logout\r\n").is_none());
+    }
+
+    fn jump_host(host: &str, username: Option<&str>) -> JumpHost {
+        JumpHost {
+            host: host.to_string(),
+            port: None,
+            username: username.map(str::to_string),
+            auth: None,
+            host_key_policy: None,
+            known_hosts_path: None,
+        }
+    }
+
+    #[test]
+    fn build_ssh_args_nests_jump_hosts() {
+        let ssh_config = SshConfig {
+            server_alive_interval_ms: 0,
+            ..SshConfig::default()
+        };
+        let mut second = jump_host("bastion-2", Some("ops"));
+        second.port = Some(2222);
+        second.host_key_policy = Some("accept-new".to_string());
+        let hops = vec![jump_host("bastion-1", None), second];
+        let files = vec![JumpHopFiles::default(), JumpHopFiles::default()];
+        let args = build_ssh_args(SshArgsConfig {
+            host: "core-sw-1",
+            port: 22,
+            username: None,
+            auth: None,
+            options: None,
+            jump_hosts: &hops,
+            jump_files: &files,
+            ssh_config: &ssh_config,
+            key_path: None,
+            connect_timeout_ms: 0,
+        })
+        .expect("args");
+        assert_eq!(args.last().map(String::as_str), Some("core-sw-1"));
+        let proxy = args
+            .iter()
+            .find_map(|arg| arg.strip_prefix("ProxyCommand="))
+            .expect("proxy command");
+        assert!(proxy.starts_with(
+            "ssh -p 2222 -l ops -o StrictHostKeyChecking=accept-new -o 'ProxyCommand=ssh -p 22"
+        ));
+        assert!(proxy.ends_with(r"-W '\''[%%h]:%%p'\'' bastion-1' -W '[%h]:%p' bastion-2"));
+    }
+
+    #[test]
+    fn jump_host_password_stays_out_of_argv() {
+        let mut hop = jump_host("bastion-1", Some("ops"));
+        hop.auth = Some(SshAuth {
+            method: Some("password".to_string()),
+            password: Some("hunter2".to_string()),
+            private_key_pem: None,
+            passphrase: None,
+            ..SshAuth::default()
+        });
+        let hops = vec![hop];
+        let (files, temp_paths) = prepare_jump_hosts(&hops).expect("prepare");
+        let askpass = files[0].askpass_path.clone().expect("askpass");
+        assert_eq!(temp_paths.len(), 2);
+
+        let ssh_config = SshConfig::default();
+        let args = build_ssh_args(SshArgsConfig {
+            host: "core-sw-1",
+            port: 22,
+            username: None,
+            auth: None,
+            options: None,
+            jump_hosts: &hops,
+            jump_files: &files,
+            ssh_config: &ssh_config,
+            key_path: None,
+            connect_timeout_ms: 0,
+        })
+        .expect("args");
+        assert!(args.iter().all(|arg| !arg.contains("hunter2")));
+        let proxy = args
+            .iter()
+            .find_map(|arg| arg.strip_prefix("ProxyCommand="))
+            .expect("proxy command");
+        assert!(proxy.starts_with(&format!(
+            "env SSH_ASKPASS={} SSH_ASKPASS_REQUIRE=force ssh",
+            askpass.display()
+        )));
+
+        let output = std::process::Command::new(&askpass)
+            .arg("ops@bastion-1's password: ")
+            .output()
+            .expect("run askpass");
+        assert_eq!(output.stdout, b"hunter2");
+    }
+
+    #[test]
+    fn jump_host_rejects_option_like_host() {
+        let hops = vec![jump_host("-oProxyCommand=evil", None)];
+        assert!(prepare_jump_hosts(&hops).is_err());
+    }
 }
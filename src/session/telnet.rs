@@ -1,6 +1,7 @@
 use crate::error::{ApiError, ErrorCode, PtyResult};
 use crate::session::{OutputHandle, PtyOptions, SessionBackend};
 use async_trait::async_trait;
+use socket2::{SockRef, TcpKeepalive};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -15,6 +16,7 @@ const DO: u8 = 0xfd;
 const WONT: u8 = 0xfc;
 const WILL: u8 = 0xfb;
 const SB: u8 = 0xfa;
+const NOP: u8 = 0xf1;
 const SE: u8 = 0xf0;
 
 const OPT_BINARY: u8 = 0;
@@ -32,6 +34,16 @@ enum WriteItem {
     Close,
 }
 
+pub struct TelnetConnectParams<'a> {
+    pub host: &'a str,
+    pub port: u16,
+    pub pty: PtyOptions,
+    pub connect_timeout_ms: u64,
+    pub keepalive_interval_ms: u64,
+    pub keepalive_count_max: u32,
+    pub output: OutputHandle,
+}
+
 pub struct TelnetBackend {
     sender: mpsc::Sender<WriteItem>,
     eof: Arc<AtomicBool>,
@@ -39,13 +51,16 @@ pub struct TelnetBackend {
 }
 
 impl TelnetBackend {
-    pub async fn connect(
-        host: &str,
-        port: u16,
-        pty: PtyOptions,
-        connect_timeout_ms: u64,
-        output: OutputHandle,
-    ) -> PtyResult<Self> {
+    pub async fn connect(params: TelnetConnectParams<'_>) -> PtyResult<Self> {
+        let TelnetConnectParams {
+            host,
+            port,
+            pty,
+            connect_timeout_ms,
+            keepalive_interval_ms,
+            keepalive_count_max,
+            output,
+        } = params;
         let addr = format!("{}:{}", host, port);
         let stream = timeout(
             Duration::from_millis(connect_timeout_ms),
@@ -58,10 +73,23 @@ impl TelnetBackend {
                 .with_details(err.to_string())
         })?;
 
+        if keepalive_interval_ms > 0 {
+            let interval = Duration::from_millis(keepalive_interval_ms);
+            let keepalive = TcpKeepalive::new()
+                .with_time(interval)
+                .with_interval(interval)
+                .with_retries(keepalive_count_max.max(1));
+            if let Err(err) = SockRef::from(&stream).set_tcp_keepalive(&keepalive) {
+                tracing::warn!(error = %err, "Failed to enable TCP keepalive");
+            }
+        }
+
         let (reader, writer) = stream.into_split();
         let (tx, mut rx) = mpsc::channel::<WriteItem>(128);
         let eof = Arc::new(AtomicBool::new(false));
         let eof_flag = eof.clone();
+        let writer_eof = eof.clone();
+        let writer_output = output.clone();
 
         tokio::spawn(async move {
             let mut writer = writer;
@@ -75,6 +103,7 @@ impl TelnetBackend {
                         let payload = escape_iac(&data);
                         if let Err(err) = writer.write_all(&payload).await {
                             tracing::warn!(error = %err, "Telnet write failed");
+                            writer_output.mark_failed(format!("Telnet write failed: {}", err));
                             break;
                         }
                         let _ = writer.flush().await;
@@ -82,14 +111,40 @@ impl TelnetBackend {
                     WriteItem::Raw(data) => {
                         if let Err(err) = writer.write_all(&data).await {
                             tracing::warn!(error = %err, "Telnet write failed");
+                            writer_output.mark_failed(format!("Telnet write failed: {}", err));
                             break;
                         }
                         let _ = writer.flush().await;
                     }
                 }
             }
+            writer_eof.store(true, Ordering::SeqCst);
+            writer_output.append_output(b"");
         });
 
+        if keepalive_interval_ms > 0 {
+            let keepalive_tx = tx.clone();
+            let keepalive_eof = eof.clone();
+            tokio::spawn(async move {
+                let mut ticker =
+                    tokio::time::interval(Duration::from_millis(keepalive_interval_ms));
+                ticker.tick().await;
+                loop {
+                    ticker.tick().await;
+                    if keepalive_eof.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if keepalive_tx
+                        .send(WriteItem::Raw(vec![IAC, NOP]))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+
         let negotiator = Arc::new(Mutex::new(Negotiator::new(
             pty.term.clone(),
             pty.cols,
@@ -124,6 +179,12 @@ impl TelnetBackend {
                     }
                     Err(err) => {
                         tracing::warn!(error = %err, "Telnet read failed");
+                        if err.kind() == std::io::ErrorKind::TimedOut {
+                            output_clone
+                                .mark_failed("Peer stopped responding (TCP keepalive timeout)");
+                        } else {
+                            output_clone.mark_failed(format!("Telnet read failed: {}", err));
+                        }
                         break;
                     }
                 }
@@ -320,11 +381,9 @@ impl Negotiator {
                             responses.push(iac_command(WONT, option));
                         }
                     }
-                    DONT => {
-                        if self.is_local_enabled(option) {
-                            self.set_local(option, false);
-                            responses.push(iac_command(WONT, option));
-                        }
+                    DONT if self.is_local_enabled(option) => {
+                        self.set_local(option, false);
+                        responses.push(iac_command(WONT, option));
                     }
                     WILL => {
                         if self.allow_remote(option) {
@@ -339,11 +398,9 @@ impl Negotiator {
                             responses.push(iac_command(DONT, option));
                         }
                     }
-                    WONT => {
-                        if self.is_remote_enabled(option) {
-                            self.set_remote(option, false);
-                            responses.push(iac_command(DONT, option));
-                        }
+                    WONT if self.is_remote_enabled(option) => {
+                        self.set_remote(option, false);
+                        responses.push(iac_command(DONT, option));
                     }
                     _ => {}
                 }
@@ -0,0 +1,147 @@
+use crate::session::{Session, SessionState};
+use std::sync::Arc;
+use tokio::time::{Duration, sleep};
+
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    Output {
+        bytes: Vec<u8>,
+        cursor: u64,
+        next_cursor: u64,
+        dropped_bytes: u64,
+    },
+    State {
+        state: SessionState,
+        eof: bool,
+        error_reason: Option<String>,
+    },
+    Lock {
+        holder: Option<String>,
+        expires_at: Option<u64>,
+    },
+    Resize {
+        cols: u16,
+        rows: u16,
+    },
+}
+
+#[derive(PartialEq)]
+struct StateSnapshot {
+    state: SessionState,
+    eof: bool,
+    error_reason: Option<String>,
+}
+
+pub struct SessionWatcher {
+    session: Arc<Session>,
+    cursor: u64,
+    max_bytes: usize,
+    last_state: Option<StateSnapshot>,
+    last_lock: Option<Option<String>>,
+    last_size: Option<(u16, u16)>,
+}
+
+impl SessionWatcher {
+    pub fn new(session: Arc<Session>, cursor: Option<u64>, max_bytes: usize) -> Self {
+        let cursor = cursor.unwrap_or_else(|| session.buffer_end_cursor());
+        Self {
+            session,
+            cursor,
+            max_bytes: max_bytes.max(1),
+            last_state: None,
+            last_lock: None,
+            last_size: None,
+        }
+    }
+
+    pub fn cursor(&self) -> u64 {
+        self.cursor
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.session.state() == SessionState::Closed
+    }
+
+    pub async fn next_events(&mut self) -> Vec<SessionEvent> {
+        loop {
+            let notify = self.session.notify.clone();
+            let notified = notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let events = self.poll().await;
+            if !events.is_empty() {
+                return events;
+            }
+            tokio::select! {
+                _ = &mut notified => {}
+                _ = sleep(WATCH_POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    async fn poll(&mut self) -> Vec<SessionEvent> {
+        let mut events = Vec::new();
+        loop {
+            let slice = self
+                .session
+                .lock_buffer()
+                .slice_from(self.cursor, self.max_bytes);
+            let cursor = if slice.truncated {
+                slice.start_cursor
+            } else {
+                self.cursor
+            };
+            if slice.bytes.is_empty() {
+                self.cursor = cursor;
+                break;
+            }
+            let next_cursor = cursor + slice.bytes.len() as u64;
+            events.push(SessionEvent::Output {
+                bytes: slice.bytes,
+                cursor,
+                next_cursor,
+                dropped_bytes: slice.dropped_bytes,
+            });
+            self.cursor = next_cursor;
+        }
+
+        let state = StateSnapshot {
+            state: self.session.state(),
+            eof: self.session.is_eof(),
+            error_reason: self.session.failure(),
+        };
+        if self.last_state.as_ref() != Some(&state) {
+            events.push(SessionEvent::State {
+                state: state.state,
+                eof: state.eof,
+                error_reason: state.error_reason.clone(),
+            });
+            self.last_state = Some(state);
+        }
+
+        let lock = self.session.lock_status().await;
+        let holder = lock.as_ref().map(|info| info.task_id.clone());
+        if self.last_lock.as_ref() != Some(&holder) {
+            events.push(SessionEvent::Lock {
+                holder: holder.clone(),
+                expires_at: lock.map(|info| info.expires_at),
+            });
+            self.last_lock = Some(holder);
+        }
+
+        if self.session.pty_enabled() {
+            let size = self.session.pty_size();
+            if self.last_size != Some(size) {
+                events.push(SessionEvent::Resize {
+                    cols: size.0,
+                    rows: size.1,
+                });
+                self.last_size = Some(size);
+            }
+        }
+        events
+    }
+}
@@ -0,0 +1,125 @@
+use std::io;
+use std::os::fd::RawFd;
+
+pub struct RawMode {
+    fd: RawFd,
+    original: libc::termios,
+}
+
+impl RawMode {
+    pub fn enable(fd: RawFd) -> io::Result<Self> {
+        let mut original = unsafe { std::mem::zeroed::<libc::termios>() };
+        if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = original;
+        unsafe { libc::cfmakeraw(&mut raw) };
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { fd, original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// Disables echo (but keeps line editing) until dropped, for typing secrets.
+pub struct EchoOff {
+    fd: RawFd,
+    original: libc::termios,
+}
+
+impl EchoOff {
+    pub fn enable(fd: RawFd) -> io::Result<Self> {
+        let mut original = unsafe { std::mem::zeroed::<libc::termios>() };
+        if unsafe { libc::tcgetattr(fd, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut quiet = original;
+        quiet.c_lflag &= !libc::ECHO;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &quiet) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { fd, original })
+    }
+}
+
+impl Drop for EchoOff {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+pub fn is_terminal(fd: RawFd) -> bool {
+    unsafe { libc::isatty(fd) == 1 }
+}
+
+pub fn window_size(fd: RawFd) -> Option<(u16, u16)> {
+    let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+    if unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } != 0 {
+        return None;
+    }
+    if size.ws_col == 0 || size.ws_row == 0 {
+        return None;
+    }
+    Some((size.ws_col, size.ws_row))
+}
+
+/// Waits up to `timeout_ms` for input; `None` means nothing arrived, `Some(0)` is end of input.
+pub fn read_timeout(fd: RawFd, buf: &mut [u8], timeout_ms: i32) -> io::Result<Option<usize>> {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let ready = unsafe { libc::poll(&mut pollfd, 1, timeout_ms) };
+    if ready < 0 {
+        let err = io::Error::last_os_error();
+        if err.kind() == io::ErrorKind::Interrupted {
+            return Ok(None);
+        }
+        return Err(err);
+    }
+    if ready == 0 {
+        return Ok(None);
+    }
+    let read = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+    if read < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Some(read as usize))
+}
+
+pub fn parse_detach_key(value: &str) -> Option<u8> {
+    let lower = value.trim().to_ascii_lowercase();
+    let key = lower
+        .strip_prefix("ctrl-")
+        .or_else(|| lower.strip_prefix("ctrl+"))
+        .or_else(|| lower.strip_prefix('^'))?;
+    match key.as_bytes() {
+        [byte @ (b'a'..=b'z' | b'[' | b'\\' | b']' | b'^' | b'_')] => Some(byte & 0x1f),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_detach_key_accepts_ctrl_forms() {
+        assert_eq!(parse_detach_key("ctrl-]"), Some(0x1d));
+        assert_eq!(parse_detach_key("Ctrl+A"), Some(0x01));
+        assert_eq!(parse_detach_key("^q"), Some(0x11));
+        assert_eq!(parse_detach_key("q"), None);
+        assert_eq!(parse_detach_key("ctrl-1"), None);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::config::HttpConfig;
+use crate::error::{ApiError, ErrorCode, PtyResult};
+use axum::{Extension, Router};
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use rustls::RootCertStore;
+use rustls::crypto::ring::default_provider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ServerConfig, WebPkiClientVerifier};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::time::{Duration, sleep, timeout};
+use tokio_rustls::TlsAcceptor;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Pauses after a failed accept (for example EMFILE), doubling up to the max.
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(5);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+
+/// Who presented a verified client certificate: its subject CN, or the whole
+/// subject when there is no CN. Inserted into every request on the connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity(pub String);
+
+/// Builds the HTTPS acceptor from `tls_cert`/`tls_key`, requiring client
+/// certificates when `client_ca` is set. `None` means plain HTTP.
+pub fn acceptor(http: &HttpConfig) -> PtyResult<Option<TlsAcceptor>> {
+    if http.tls_cert.is_empty() {
+        return Ok(None);
+    }
+    let certs = CertificateDer::pem_file_iter(&http.tls_cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| pem_error("tls_cert", &http.tls_cert, err))?;
+    let key = PrivateKeyDer::from_pem_file(&http.tls_key)
+        .map_err(|err| pem_error("tls_key", &http.tls_key, err))?;
+    let provider = Arc::new(default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(tls_error)?;
+    let builder = if http.client_ca.is_empty() {
+        builder.with_no_client_auth()
+    } else {
+        let mut roots = RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(&http.client_ca)
+            .map_err(|err| pem_error("client_ca", &http.client_ca, err))?
+        {
+            let cert = cert.map_err(|err| pem_error("client_ca", &http.client_ca, err))?;
+            roots.add(cert).map_err(tls_error)?;
+        }
+        let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+            .build()
+            .map_err(tls_error)?;
+        builder.with_client_cert_verifier(verifier)
+    };
+    let mut config = builder.with_single_cert(certs, key).map_err(tls_error)?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Some(TlsAcceptor::from(Arc::new(config))))
+}
+
+/// Serves `router` over TLS, tagging requests with the client's [`ClientIdentity`].
+/// Accept errors are logged and retried after a backoff; they never stop the
+/// server.
+pub async fn serve(listener: TcpListener, acceptor: TlsAcceptor, router: Router) -> PtyResult<()> {
+    let mut backoff = ACCEPT_BACKOFF_MIN;
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => {
+                backoff = ACCEPT_BACKOFF_MIN;
+                accepted
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, retry_ms = backoff.as_millis() as u64, "HTTPS accept failed");
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let router = router.clone();
+        tokio::spawn(async move {
+            let stream = match timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(err)) => {
+                    tracing::debug!(%peer, error = %err, "TLS handshake failed");
+                    return;
+                }
+                Err(_) => {
+                    tracing::debug!(%peer, "TLS handshake timed out");
+                    return;
+                }
+            };
+            let identity = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(client_identity);
+            let router = match identity {
+                Some(identity) => router.layer(Extension(identity)),
+                None => router,
+            };
+            let service = TowerToHyperService::new(router);
+            if let Err(err) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::debug!(%peer, error = %err, "HTTPS connection ended with error");
+            }
+        });
+    }
+}
+
+pub fn client_identity(cert: &CertificateDer<'_>) -> Option<ClientIdentity> {
+    let (_, cert) = X509Certificate::from_der(cert.as_ref()).ok()?;
+    let subject = cert.subject();
+    let identity = subject
+        .iter_common_name()
+        .next()
+        .and_then(|cn| cn.as_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| subject.to_string());
+    (!identity.is_empty()).then_some(ClientIdentity(identity))
+}
+
+fn pem_error(field: &str, path: &str, err: impl std::fmt::Display) -> ApiError {
+    ApiError::new(
+        ErrorCode::InvalidArgument,
+        format!("Failed to load server.http.{field} from {path}"),
+    )
+    .with_details(err.to_string())
+}
+
+fn tls_error(err: impl std::fmt::Display) -> ApiError {
+    ApiError::new(ErrorCode::InvalidArgument, "Invalid TLS configuration")
+        .with_details(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_prefers_common_name() {
+        let mut params = rcgen::CertificateParams::new(Vec::<String>::new()).unwrap();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::OrganizationName, "netops");
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "alice");
+        let key = rcgen::KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key).unwrap();
+        assert_eq!(
+            client_identity(cert.der()),
+            Some(ClientIdentity("alice".to_string()))
+        );
+
+        let mut params = rcgen::CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::OrganizationName, "netops");
+        let cert = params.self_signed(&key).unwrap();
+        assert_eq!(
+            client_identity(cert.der()),
+            Some(ClientIdentity("O=netops".to_string()))
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use crate::config::{ControlMode, HttpConfig, HttpToken};
+use crate::error::{ApiError, ErrorCode, PtyResult};
+use crate::policy::glob_regex;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use regex::Regex;
+use ring::digest::{SHA256, digest};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Bearer tokens accepted by the HTTP transport.
+///
+/// `server.http.auth_token` grants full access; each `[[server.http.tokens]]`
+/// entry is matched by the SHA-256 of the presented token and grants a
+/// [`TokenScope`].
+#[derive(Debug, Default)]
+pub struct TokenTable {
+    full_access: Option<[u8; 32]>,
+    scoped: Vec<([u8; 32], Arc<TokenScope>)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TokenGrant {
+    Full,
+    Scoped(Arc<TokenScope>),
+}
+
+/// What a named HTTP token may do.
+#[derive(Debug)]
+pub struct TokenScope {
+    pub name: String,
+    readonly: bool,
+    tools: Vec<String>,
+    hosts: Vec<Regex>,
+    pub max_sessions: usize,
+}
+
+impl TokenTable {
+    pub fn new(config: &HttpConfig) -> PtyResult<Self> {
+        let mut names = HashSet::new();
+        let mut scoped = Vec::with_capacity(config.tokens.len());
+        for token in &config.tokens {
+            if token.name.trim().is_empty() {
+                return Err(invalid("HTTP tokens require a name").into());
+            }
+            if !names.insert(token.name.as_str()) {
+                return Err(invalid(format!("Duplicate HTTP token: {}", token.name)).into());
+            }
+            scoped.push((parse_digest(token)?, Arc::new(TokenScope::compile(token)?)));
+        }
+        Ok(Self {
+            full_access: (!config.auth_token.is_empty()).then(|| sha256(&config.auth_token)),
+            scoped,
+        })
+    }
+
+    /// True when requests must present a bearer token.
+    pub fn requires_auth(&self) -> bool {
+        self.full_access.is_some() || !self.scoped.is_empty()
+    }
+
+    pub fn authenticate(&self, token: &str) -> Option<TokenGrant> {
+        let presented = sha256(token);
+        if self
+            .full_access
+            .is_some_and(|expected| digests_equal(&expected, &presented))
+        {
+            return Some(TokenGrant::Full);
+        }
+        self.scoped
+            .iter()
+            .find(|(expected, _)| digests_equal(expected, &presented))
+            .map(|(_, scope)| TokenGrant::Scoped(scope.clone()))
+    }
+}
+
+impl TokenScope {
+    fn compile(token: &HttpToken) -> PtyResult<Self> {
+        let hosts = token
+            .hosts
+            .iter()
+            .map(|glob| glob_regex(glob))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| {
+                invalid(format!(
+                    "Invalid host pattern in HTTP token '{}'",
+                    token.name
+                ))
+                .with_details(err.to_string())
+            })?;
+        Ok(Self {
+            name: token.name.clone(),
+            readonly: !matches!(token.mode, ControlMode::Readwrite),
+            tools: token.tools.clone(),
+            hosts,
+            max_sessions: token.max_sessions,
+        })
+    }
+
+    pub fn is_readonly(&self) -> bool {
+        self.readonly
+    }
+
+    pub fn allows_tool(&self, tool: &str, action: Option<&str>) -> bool {
+        self.tools.is_empty()
+            || self
+                .tools
+                .iter()
+                .any(|allowed| match allowed.split_once(':') {
+                    Some((name, allowed_action)) => name == tool && action == Some(allowed_action),
+                    None => allowed == tool,
+                })
+    }
+
+    pub fn allows_host(&self, host: &str) -> bool {
+        self.hosts.is_empty() || self.hosts.iter().any(|glob| glob.is_match(host))
+    }
+}
+
+/// Hex SHA-256 of `token`, as stored in `token_sha256`.
+pub fn hash_token(token: &str) -> String {
+    sha256(token)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// A fresh random bearer token (256 bits, base64url).
+pub fn generate_token() -> PtyResult<String> {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| ApiError::new(ErrorCode::IoError, "Failed to generate random token"))?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn sha256(token: &str) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out.copy_from_slice(digest(&SHA256, token.as_bytes()).as_ref());
+    out
+}
+
+fn parse_digest(token: &HttpToken) -> PtyResult<[u8; 32]> {
+    let hex = token.token_sha256.trim();
+    let bad = || {
+        invalid(format!(
+            "HTTP token '{}' needs token_sha256 as 64 hex characters",
+            token.name
+        ))
+        .with_details("generate one with `ptyctl token new`")
+    };
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(bad().into());
+    }
+    let mut out = [0u8; 32];
+    for (index, byte) in out.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).map_err(|_| bad())?;
+    }
+    Ok(out)
+}
+
+fn digests_equal(left: &[u8; 32], right: &[u8; 32]) -> bool {
+    left.iter()
+        .zip(right)
+        .fold(0u8, |diff, (left, right)| diff | (left ^ right))
+        == 0
+}
+
+fn invalid(message: impl Into<String>) -> ApiError {
+    ApiError::new(ErrorCode::InvalidArgument, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_hashed_tokens_and_scopes() {
+        let config = HttpConfig {
+            auth_token: "admin".to_string(),
+            tokens: vec![HttpToken {
+                name: "ci".to_string(),
+                token_sha256: hash_token("ci-secret"),
+                tools: vec![
+                    "ptyctl_session:list".to_string(),
+                    "ptyctl_session_io".to_string(),
+                ],
+                hosts: vec!["lab-*".to_string()],
+                max_sessions: 2,
+                ..HttpToken::default()
+            }],
+            ..HttpConfig::default()
+        };
+        let table = TokenTable::new(&config).unwrap();
+        assert!(table.requires_auth());
+        assert!(matches!(
+            table.authenticate("admin"),
+            Some(TokenGrant::Full)
+        ));
+        assert!(table.authenticate("nope").is_none());
+        let Some(TokenGrant::Scoped(scope)) = table.authenticate("ci-secret") else {
+            panic!("expected scoped grant");
+        };
+        assert_eq!(scope.name, "ci");
+        assert!(scope.is_readonly());
+        assert!(scope.allows_tool("ptyctl_session", Some("list")));
+        assert!(!scope.allows_tool("ptyctl_session", Some("open")));
+        assert!(scope.allows_tool("ptyctl_session_io", Some("write")));
+        assert!(!scope.allows_tool("ptyctl_session_exec", None));
+        assert!(scope.allows_host("lab-r1"));
+        assert!(!scope.allows_host("core-r1"));
+
+        let mut broken = config.clone();
+        broken.tokens[0].token_sha256 = "ci-secret".to_string();
+        assert!(TokenTable::new(&broken).is_err());
+        assert!(
+            !TokenTable::new(&HttpConfig::default())
+                .unwrap()
+                .requires_auth()
+        );
+    }
+}
@@ -0,0 +1,417 @@
+use crate::{
+    ControlEndpoint, control_request, control_request_or_exit, run_interactive_attach, terminal,
+};
+use ptyctl::config::{ControlAttachArgs, ControlTopArgs};
+use ptyctl::error::{ApiError, ErrorCode, PtyResult};
+use ptyctl::session::{SessionListEntry, SessionListResponse};
+use serde::Serialize;
+use serde_json::json;
+use std::io::Write;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::time::Duration;
+
+const HELP: &str = "q quit  j/k select  t tail  a attach  A force attach";
+
+struct Screen {
+    _raw_mode: terminal::RawMode,
+}
+
+impl Screen {
+    fn enter() -> PtyResult<Self> {
+        let raw_mode = terminal::RawMode::enable(libc::STDIN_FILENO).map_err(|err| {
+            ApiError::new(ErrorCode::IoError, "Failed to enable raw mode")
+                .with_details(err.to_string())
+        })?;
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(b"\x1b[?1049h\x1b[?25l")?;
+        stdout.flush()?;
+        Ok(Self {
+            _raw_mode: raw_mode,
+        })
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(b"\x1b[?25h\x1b[?1049l");
+        let _ = stdout.flush();
+    }
+}
+
+enum Key {
+    Quit,
+    Up,
+    Down,
+    ToggleTail,
+    Attach { force: bool },
+}
+
+struct TopState {
+    sessions: Vec<SessionListEntry>,
+    selected: usize,
+    tail_open: bool,
+    tail: String,
+    status: Option<String>,
+}
+
+impl TopState {
+    fn selected_id(&self) -> Option<&str> {
+        self.sessions
+            .get(self.selected)
+            .map(|entry| entry.session_id.as_str())
+    }
+}
+
+pub async fn run_top(args: ControlTopArgs) -> PtyResult<()> {
+    let endpoint = ControlEndpoint::new(args.control_socket, args.control_token);
+    if !terminal::is_terminal(libc::STDIN_FILENO) || !terminal::is_terminal(libc::STDOUT_FILENO) {
+        return Err(ApiError::new(
+            ErrorCode::InvalidArgument,
+            "ptyctl top requires a terminal; use `ptyctl sessions` for scripts",
+        )
+        .into());
+    }
+    control_request_or_exit(&endpoint, "ptyctl_session", json!({ "action": "list" })).await?;
+
+    let interval = Duration::from_millis(args.interval_ms.max(100));
+    let mut state = TopState {
+        sessions: Vec::new(),
+        selected: 0,
+        tail_open: false,
+        tail: String::new(),
+        status: None,
+    };
+    let mut screen = Some(Screen::enter()?);
+    let mut last_refresh: Option<Instant> = None;
+    loop {
+        if last_refresh.is_none_or(|at| at.elapsed() >= interval) {
+            refresh(&endpoint, &mut state).await;
+            last_refresh = Some(Instant::now());
+        }
+        render(&state)?;
+        let wait = interval.saturating_sub(last_refresh.map_or(interval, |at| at.elapsed()));
+        let Some(keys) = read_keys(wait).await? else {
+            continue;
+        };
+        for key in keys {
+            match key {
+                Key::Quit => return Ok(()),
+                Key::Up => state.selected = state.selected.saturating_sub(1),
+                Key::Down => {
+                    state.selected =
+                        (state.selected + 1).min(state.sessions.len().saturating_sub(1));
+                }
+                Key::ToggleTail => state.tail_open = !state.tail_open,
+                Key::Attach { force } => {
+                    let Some(session_id) = state.selected_id().map(str::to_string) else {
+                        continue;
+                    };
+                    screen.take();
+                    attach(&endpoint, session_id, force).await?;
+                    screen = Some(Screen::enter()?);
+                }
+            }
+            last_refresh = None;
+        }
+    }
+}
+
+async fn refresh(endpoint: &ControlEndpoint, state: &mut TopState) {
+    let previous = state.selected_id().map(str::to_string);
+    match control_request(endpoint, "ptyctl_session", json!({ "action": "list" })).await {
+        Ok(response) => match serde_json::from_value::<SessionListResponse>(response) {
+            Ok(list) => {
+                state.sessions = list.sessions;
+                state.sessions.sort_by_key(|entry| entry.created_at);
+                state.status = None;
+            }
+            Err(err) => state.status = Some(format!("Invalid session list: {err}")),
+        },
+        Err(err) => state.status = Some(format!("Refresh failed: {err}")),
+    }
+    if let Some(previous) = previous
+        && let Some(index) = state
+            .sessions
+            .iter()
+            .position(|entry| entry.session_id == previous)
+    {
+        state.selected = index;
+    }
+    state.selected = state.selected.min(state.sessions.len().saturating_sub(1));
+
+    state.tail.clear();
+    if !state.tail_open {
+        return;
+    }
+    let Some(session_id) = state.selected_id().map(str::to_string) else {
+        return;
+    };
+    let (_, rows) = screen_size();
+    let params = json!({
+        "action": "read",
+        "mode": "tail",
+        "session_id": session_id,
+        "max_lines": rows as usize / 2,
+        "encoding": "utf-8",
+    });
+    match control_request(endpoint, "ptyctl_session_io", params).await {
+        Ok(response) => {
+            if let Some(chunk) = response.get("chunk").and_then(|v| v.as_str()) {
+                state.tail = chunk.to_string();
+            }
+        }
+        Err(err) => state.status = Some(format!("Tail failed: {err}")),
+    }
+}
+
+async fn attach(endpoint: &ControlEndpoint, session_id: String, force: bool) -> PtyResult<()> {
+    let args = ControlAttachArgs {
+        session_id: Some(session_id.clone()),
+        max_bytes: 65536,
+        interactive: true,
+        force,
+        task_id: None,
+        detach_key: "ctrl-]".to_string(),
+        lock_ttl_ms: 30_000,
+        control_socket: None,
+        control_token: None,
+    };
+    if let Err(err) = run_interactive_attach(endpoint.clone(), session_id, &args).await {
+        eprintln!("Attach failed: {err}");
+        eprintln!("Press any key to return to ptyctl top.");
+        let _raw_mode = terminal::RawMode::enable(libc::STDIN_FILENO)?;
+        let mut buf = [0u8; 16];
+        while terminal::read_timeout(libc::STDIN_FILENO, &mut buf, 1_000)?.is_none() {}
+    }
+    Ok(())
+}
+
+async fn read_keys(wait: Duration) -> PtyResult<Option<Vec<Key>>> {
+    let timeout_ms = wait.as_millis().min(i32::MAX as u128) as i32;
+    let read = tokio::task::spawn_blocking(move || {
+        let mut buf = [0u8; 64];
+        terminal::read_timeout(libc::STDIN_FILENO, &mut buf, timeout_ms)
+            .map(|read| read.map(|n| buf[..n].to_vec()))
+    })
+    .await
+    .map_err(|err| {
+        ApiError::new(ErrorCode::IoError, "Input task failed").with_details(err.to_string())
+    })??;
+    Ok(read.map(|bytes| {
+        if bytes.is_empty() {
+            vec![Key::Quit]
+        } else {
+            parse_keys(&bytes)
+        }
+    }))
+}
+
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        let rest = &bytes[index..];
+        if rest.starts_with(b"\x1b[A") || rest.starts_with(b"\x1bOA") {
+            keys.push(Key::Up);
+            index += 3;
+            continue;
+        }
+        if rest.starts_with(b"\x1b[B") || rest.starts_with(b"\x1bOB") {
+            keys.push(Key::Down);
+            index += 3;
+            continue;
+        }
+        match rest[0] {
+            b'q' | 0x03 => keys.push(Key::Quit),
+            b'k' => keys.push(Key::Up),
+            b'j' => keys.push(Key::Down),
+            b't' | b'\r' => keys.push(Key::ToggleTail),
+            b'a' => keys.push(Key::Attach { force: false }),
+            b'A' => keys.push(Key::Attach { force: true }),
+            _ => {}
+        }
+        index += 1;
+    }
+    keys
+}
+
+fn render(state: &TopState) -> PtyResult<()> {
+    let (cols, rows) = screen_size();
+    let cols = cols as usize;
+    let rows = rows as usize;
+    let now = now_ms();
+    let mut lines = Vec::with_capacity(rows);
+    lines.push(format!(
+        "ptyctl top - {} session(s)   {HELP}",
+        state.sessions.len()
+    ));
+    lines.push(format!(
+        "\x1b[7m{}\x1b[0m",
+        pad(
+            &format!(
+                "{:<10} {:<7} {:<24} {:<12} {:<16} {:>8} {:>8} {:>6} {:>5}",
+                "SESSION", "PROTO", "HOST", "STATE", "LOCK", "IN", "OUT", "IDLE", "BUF"
+            ),
+            cols
+        )
+    ));
+    let table_rows = if state.tail_open {
+        rows.saturating_sub(3) / 2
+    } else {
+        rows.saturating_sub(3)
+    };
+    let first = state.selected.saturating_sub(table_rows.saturating_sub(1));
+    for (index, entry) in state
+        .sessions
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(table_rows)
+    {
+        let row = pad(&format_entry(entry, now), cols);
+        if index == state.selected {
+            lines.push(format!("\x1b[7m{row}\x1b[0m"));
+        } else {
+            lines.push(row);
+        }
+    }
+    if state.sessions.is_empty() {
+        lines.push("No sessions.".to_string());
+    }
+    if state.tail_open {
+        lines.resize(table_rows + 2, String::new());
+        let title = format!("-- tail {} ", state.selected_id().unwrap_or("-"));
+        lines.push(pad(&format!("{title:-<cols$}"), cols));
+        let available = rows.saturating_sub(lines.len() + 1);
+        let tail: Vec<&str> = state.tail.lines().collect();
+        for line in &tail[tail.len().saturating_sub(available)..] {
+            lines.push(truncate(&sanitize_line(line), cols));
+        }
+    }
+    lines.resize(rows.saturating_sub(1), String::new());
+    lines.push(truncate(state.status.as_deref().unwrap_or_default(), cols));
+
+    let mut frame = String::from("\x1b[H");
+    for (index, line) in lines.iter().enumerate() {
+        frame.push_str(line);
+        frame.push_str("\x1b[K");
+        if index + 1 < lines.len() {
+            frame.push_str("\r\n");
+        }
+    }
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(frame.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn format_entry(entry: &SessionListEntry, now: u64) -> String {
+    let short_id: String = entry.session_id.chars().take(10).collect();
+    let host = format!("{}:{}", entry.host, entry.port);
+    let buffer_fill = (entry.buffered_bytes * 100)
+        .checked_div(entry.buffer_limit_bytes)
+        .unwrap_or_default();
+    format!(
+        "{:<10} {:<7} {:<24} {:<12} {:<16} {:>8} {:>8} {:>6} {:>4}%",
+        short_id,
+        label(&entry.protocol),
+        truncate(&host, 24),
+        label(&entry.state),
+        truncate(entry.lock_holder.as_deref().unwrap_or("-"), 16),
+        format_bytes(entry.bytes_in),
+        format_bytes(entry.bytes_out),
+        format_age(now.saturating_sub(entry.last_activity_at)),
+        buffer_fill,
+    )
+}
+
+fn label<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if bytes < 1024 {
+        return format!("{bytes}B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1}{}", UNITS[unit])
+}
+
+fn format_age(ms: u64) -> String {
+    let secs = ms / 1000;
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+/// Drops escape sequences and control characters so remote output cannot move the cursor.
+fn sanitize_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            if chars.peek() == Some(&'[') {
+                chars.next();
+                for next in chars.by_ref() {
+                    if ('@'..='~').contains(&next) {
+                        break;
+                    }
+                }
+            } else {
+                chars.next();
+            }
+        } else if ch == '\t' {
+            out.push_str("    ");
+        } else if !ch.is_control() {
+            out.push(ch);
+        }
+    }
+    out
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!("{:<width$}", truncate(text, width))
+}
+
+fn screen_size() -> (u16, u16) {
+    terminal::window_size(libc::STDOUT_FILENO).unwrap_or((80, 24))
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_and_sanitizes_cells() {
+        assert_eq!(format_bytes(512), "512B");
+        assert_eq!(format_bytes(1536), "1.5K");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0M");
+        assert_eq!(format_age(59_999), "59s");
+        assert_eq!(format_age(7_200_000), "2h");
+        assert_eq!(sanitize_line("\x1b[1;31mERR\x1b[0m\tok\x07"), "ERR    ok");
+    }
+}
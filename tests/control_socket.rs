@@ -0,0 +1,774 @@
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, UnixStream};
+use tokio::process::Command;
+use tokio::time::{Duration, sleep};
+
+async fn control_call(socket_path: &Path, request: serde_json::Value) -> serde_json::Value {
+    let stream = UnixStream::connect(socket_path).await.expect("connect");
+    let (reader, mut writer) = stream.into_split();
+    let mut payload = serde_json::to_vec(&request).expect("encode");
+    payload.push(b'\n');
+    writer.write_all(&payload).await.expect("write");
+    let mut lines = BufReader::new(reader).lines();
+    let line = lines.next_line().await.expect("read").expect("line");
+    serde_json::from_str(&line).expect("decode")
+}
+
+async fn spawn_server(socket_path: &Path, extra: &[&str]) -> tokio::process::Child {
+    let child = Command::new(env!("CARGO_BIN_EXE_ptyctl"))
+        .arg("serve")
+        .arg("--transport")
+        .arg("stdio")
+        .arg("--control-socket")
+        .arg(socket_path)
+        .args(extra)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("spawn ptyctl");
+    for _ in 0..50 {
+        if socket_path.exists() {
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    child
+}
+
+#[tokio::test]
+async fn control_socket_requires_token() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = env!("CARGO_BIN_EXE_ptyctl");
+    let dir = tempfile::tempdir()?;
+    let socket_path = dir.path().join("ptyctl.sock");
+    let mut child = spawn_server(&socket_path, &["--control-auth-token", "s3cret"]).await;
+    let mode = std::fs::metadata(&socket_path)?.permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    let list = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "ptyctl_session",
+        "params": {"action": "list"}
+    });
+    let denied = control_call(&socket_path, list.clone()).await;
+    assert_eq!(denied["error"]["data"]["error_code"], "AUTH_FAILED");
+
+    let mut authed = list;
+    authed["auth_token"] = "s3cret".into();
+    let allowed = control_call(&socket_path, authed).await;
+    assert!(allowed["result"]["sessions"].is_array());
+
+    let status = Command::new(bin)
+        .arg("sessions")
+        .arg("--control-socket")
+        .arg(&socket_path)
+        .env_remove("PTYCTL_CONTROL_AUTH_TOKEN")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await?;
+    assert!(!status.success());
+    let status = Command::new(bin)
+        .arg("sessions")
+        .arg("--control-socket")
+        .arg(&socket_path)
+        .arg("--control-token")
+        .arg("s3cret")
+        .stdout(Stdio::null())
+        .status()
+        .await?;
+    assert!(status.success());
+
+    child.kill().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribe_pushes_output_lock_and_close() -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(async move {
+        if let Ok((mut socket, _)) = listener.accept().await {
+            let _ = socket.write_all(b"login: ").await;
+            sleep(Duration::from_millis(300)).await;
+            let _ = socket.write_all(b"welcome password=hunter2\r\n").await;
+            sleep(Duration::from_secs(5)).await;
+        }
+    });
+
+    let dir = tempfile::tempdir()?;
+    let socket_path = dir.path().join("ptyctl.sock");
+    let mut child = spawn_server(&socket_path, &["--control-mode", "readwrite"]).await;
+
+    let opened = control_call(
+        &socket_path,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "ptyctl_session",
+            "params": {"action": "open", "protocol": "telnet", "host": "127.0.0.1", "port": port}
+        }),
+    )
+    .await;
+    let session_id = opened["result"]["session_id"]
+        .as_str()
+        .expect("session_id")
+        .to_string();
+
+    let stream = UnixStream::connect(&socket_path).await?;
+    let (reader, mut writer) = stream.into_split();
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "subscribe",
+        "params": {"session_id": session_id, "cursor": "0"}
+    });
+    writer
+        .write_all(format!("{}\n", request).as_bytes())
+        .await?;
+    let mut lines = BufReader::new(reader).lines();
+    let ack: serde_json::Value = serde_json::from_str(&lines.next_line().await?.expect("ack"))?;
+    assert_eq!(ack["result"]["subscribed"], true);
+
+    let mut output = String::new();
+    let mut saw_lock = false;
+    let mut saw_closed = false;
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    while tokio::time::Instant::now() < deadline {
+        let Ok(Some(line)) =
+            tokio::time::timeout(Duration::from_secs(5), lines.next_line()).await?
+        else {
+            break;
+        };
+        let message: serde_json::Value = serde_json::from_str(&line)?;
+        match message["method"].as_str() {
+            Some("ptyctl/output") => {
+                output.push_str(message["params"]["chunk"].as_str().unwrap_or_default());
+                if output.contains("welcome") && !saw_lock {
+                    control_call(
+                        &socket_path,
+                        serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": 3,
+                            "method": "ptyctl_session",
+                            "params": {"action": "lock", "session_id": session_id, "task_id": "ops"}
+                        }),
+                    )
+                    .await;
+                }
+            }
+            Some("ptyctl/lock") if message["params"]["lock_holder"] == "ops" => {
+                saw_lock = true;
+                control_call(
+                    &socket_path,
+                    serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": 4,
+                        "method": "ptyctl_session",
+                        "params": {"action": "close", "session_id": session_id, "force": true}
+                    }),
+                )
+                .await;
+            }
+            Some("ptyctl/closed") => {
+                saw_closed = true;
+                break;
+            }
+            _ => {}
+        }
+    }
+    assert!(output.contains("login: "), "output: {output:?}");
+    assert!(output.contains("welcome"), "output: {output:?}");
+    assert!(output.contains("password=[REDACTED]"), "output: {output:?}");
+    assert!(saw_lock, "lock notification not received");
+    assert!(saw_closed, "closed notification not received");
+
+    child.kill().await?;
+    Ok(())
+}
+
+async fn cli(socket_path: &Path, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_ptyctl"))
+        .args(args)
+        .arg("--control-socket")
+        .arg(socket_path)
+        .env_remove("PTYCTL_CONTROL_AUTH_TOKEN")
+        .output()
+        .await
+        .expect("run ptyctl")
+}
+
+#[tokio::test]
+async fn cli_client_drives_session_lifecycle() -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port().to_string();
+    tokio::spawn(async move {
+        if let Ok((socket, _)) = listener.accept().await {
+            let (mut reader, mut writer) = socket.into_split();
+            let _ = writer.write_all(b"ready> ").await;
+            let _ = tokio::io::copy(&mut reader, &mut writer).await;
+        }
+    });
+
+    let dir = tempfile::tempdir()?;
+    let socket_path = dir.path().join("ptyctl.sock");
+    let mut child = spawn_server(&socket_path, &["--control-mode", "readwrite"]).await;
+
+    let opened = cli(
+        &socket_path,
+        &["open", "127.0.0.1", "--protocol", "telnet", "--port", &port],
+    )
+    .await;
+    assert!(opened.status.success(), "open: {opened:?}");
+    let session_id = String::from_utf8(opened.stdout)?.trim().to_string();
+    let id = session_id.as_str();
+
+    let sent = cli(&socket_path, &["send", id, "hello", "--enter"]).await;
+    assert!(sent.status.success(), "send: {sent:?}");
+    let read = cli(
+        &socket_path,
+        &[
+            "read",
+            id,
+            "--cursor",
+            "0",
+            "--until",
+            "hello",
+            "--timeout-ms",
+            "3000",
+        ],
+    )
+    .await;
+    assert!(read.status.success(), "read: {read:?}");
+    assert!(String::from_utf8_lossy(&read.stdout).contains("hello"));
+    let missed = cli(
+        &socket_path,
+        &[
+            "read",
+            id,
+            "--until",
+            "never-printed",
+            "--timeout-ms",
+            "200",
+        ],
+    )
+    .await;
+    assert_eq!(missed.status.code(), Some(3));
+
+    let locked = cli(&socket_path, &["lock", id, "--task-id", "ops", "--json"]).await;
+    let locked: serde_json::Value = serde_json::from_slice(&locked.stdout)?;
+    assert_eq!(locked["lock_holder"], "ops");
+    let blocked = cli(&socket_path, &["send", id, "x", "--json"]).await;
+    assert_eq!(blocked.status.code(), Some(1));
+    let blocked: serde_json::Value = serde_json::from_slice(&blocked.stdout)?;
+    assert!(blocked["error"]["message"].is_string());
+    let unlocked = cli(&socket_path, &["unlock", id, "--task-id", "ops"]).await;
+    assert!(unlocked.status.success(), "unlock: {unlocked:?}");
+
+    let closed = cli(&socket_path, &["close", id, "--force"]).await;
+    assert!(closed.status.success(), "close: {closed:?}");
+    let gone = cli(&socket_path, &["read", id, "--tail"]).await;
+    assert_eq!(gone.status.code(), Some(2));
+
+    child.kill().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn secret_ref_writes_resolve_on_server() -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(async move {
+        if let Ok((socket, _)) = listener.accept().await {
+            let (mut reader, mut writer) = socket.into_split();
+            let _ = tokio::io::copy(&mut reader, &mut writer).await;
+        }
+    });
+
+    unsafe { std::env::set_var("PTYCTL_SECRET_ECHO_TEST", "t0ps3cret") };
+    let dir = tempfile::tempdir()?;
+    let socket_path = dir.path().join("ptyctl.sock");
+    let mut child = spawn_server(&socket_path, &["--control-mode", "readwrite"]).await;
+
+    let opened = control_call(
+        &socket_path,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "ptyctl_session",
+            "params": {"action": "open", "protocol": "telnet", "host": "127.0.0.1", "port": port}
+        }),
+    )
+    .await;
+    let session_id = opened["result"]["session_id"].as_str().expect("session_id");
+
+    let write = |secret_ref: &str| {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "ptyctl_session_io",
+            "params": {"action": "write", "session_id": session_id, "secret_ref": secret_ref}
+        })
+    };
+    let written = control_call(&socket_path, write("secret://echo-test")).await;
+    assert_eq!(written["result"]["bytes_written"], 9);
+    assert!(!written.to_string().contains("t0ps3cret"));
+    let missing = control_call(&socket_path, write("secret://missing")).await;
+    assert_eq!(missing["error"]["data"]["error_code"], "NOT_FOUND");
+
+    // A secret must be a line of its own: nothing but Enter may follow it,
+    // and it cannot complete a line typed before it, which Enter would then
+    // submit without a policy check.
+    let io = |params: serde_json::Value| {
+        let mut params = params;
+        params["action"] = "write".into();
+        params["session_id"] = session_id.into();
+        serde_json::json!({"jsonrpc": "2.0", "id": 4, "method": "ptyctl_session_io", "params": params})
+    };
+    let piped = control_call(&socket_path, io(serde_json::json!({"data": " | base64\n"}))).await;
+    assert_eq!(piped["error"]["data"]["error_code"], "PERMISSION_DENIED");
+    let enter = control_call(&socket_path, io(serde_json::json!({"key": "enter"}))).await;
+    assert_eq!(enter["result"]["bytes_written"], 1);
+    let typed = control_call(&socket_path, io(serde_json::json!({"data": "write "}))).await;
+    assert_eq!(typed["result"]["bytes_written"], 6);
+    let appended = control_call(&socket_path, write("secret://echo-test")).await;
+    assert_eq!(appended["error"]["data"]["error_code"], "PERMISSION_DENIED");
+    let cleared = control_call(&socket_path, io(serde_json::json!({"key": "ctrl-u"}))).await;
+    assert_eq!(cleared["result"]["bytes_written"], 1);
+
+    let read = control_call(
+        &socket_path,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "ptyctl_session_io",
+            "params": {"action": "read", "session_id": session_id, "cursor": "0", "until_regex": "t0ps3cret", "timeout_ms": 3000}
+        }),
+    )
+    .await;
+    assert_eq!(read["result"]["matched"], true);
+
+    child.kill().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn policy_denies_and_queues_commands_for_approval() -> Result<(), Box<dyn std::error::Error>>
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(async move {
+        if let Ok((socket, _)) = listener.accept().await {
+            let (mut reader, mut writer) = socket.into_split();
+            let _ = tokio::io::copy(&mut reader, &mut writer).await;
+        }
+    });
+
+    let dir = tempfile::tempdir()?;
+    let config_path = dir.path().join("ptyctl.toml");
+    std::fs::write(
+        &config_path,
+        r#"
+[[policy.rules]]
+name = "destructive"
+deny = ["^write erase", "rm\\s+-rf\\s+/"]
+require_confirmation = ["^reload"]
+require_approval = ["^commit"]
+"#,
+    )?;
+    let socket_path = dir.path().join("ptyctl.sock");
+    let config_arg = config_path.to_string_lossy().to_string();
+    let mut child = spawn_server(
+        &socket_path,
+        &["--control-mode", "readwrite", "--config", &config_arg],
+    )
+    .await;
+
+    let opened = control_call(
+        &socket_path,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "ptyctl_session",
+            "params": {"action": "open", "protocol": "telnet", "host": "127.0.0.1", "port": port}
+        }),
+    )
+    .await;
+    let session_id = opened["result"]["session_id"].as_str().expect("session_id");
+
+    let exec = control_call(
+        &socket_path,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "ptyctl_session_exec",
+            "params": {"session_id": session_id, "cmd": "write erase"}
+        }),
+    )
+    .await;
+    assert_eq!(exec["error"]["data"]["error_code"], "POLICY_DENIED");
+    assert!(
+        exec["error"]["message"]
+            .as_str()
+            .unwrap_or_default()
+            .contains("destructive")
+    );
+
+    let write = |data: &str| {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "ptyctl_session_io",
+            "params": {"action": "write", "session_id": session_id, "data": data}
+        })
+    };
+    let allowed = control_call(&socket_path, write("show clock\n")).await;
+    assert_eq!(allowed["result"]["bytes_written"], 11);
+
+    // Neither `sensitive` nor splitting a command over several writes skips the policy.
+    let io = |params: serde_json::Value| {
+        let mut params = params;
+        params["action"] = "write".into();
+        params["session_id"] = session_id.into();
+        serde_json::json!({"jsonrpc": "2.0", "id": 4, "method": "ptyctl_session_io", "params": params})
+    };
+    let sensitive = control_call(
+        &socket_path,
+        io(serde_json::json!({"data": "write erase\n", "sensitive": true})),
+    )
+    .await;
+    assert_eq!(sensitive["error"]["data"]["error_code"], "POLICY_DENIED");
+    for part in ["wri", "te er", "ase"] {
+        let partial = control_call(&socket_path, io(serde_json::json!({"data": part}))).await;
+        assert_eq!(partial["result"]["bytes_written"], part.len());
+    }
+    let enter = control_call(&socket_path, io(serde_json::json!({"key": "enter"}))).await;
+    assert_eq!(enter["error"]["data"]["error_code"], "POLICY_DENIED");
+    let cleared = control_call(&socket_path, io(serde_json::json!({"key": "ctrl-u"}))).await;
+    assert_eq!(cleared["result"]["bytes_written"], 1);
+
+    // Without an elicitation-capable client, confirmations wait for an operator.
+    for (command, decision) in [("reload\n", "approve"), ("commit\n", "reject")] {
+        let parked = tokio::spawn({
+            let socket_path = socket_path.clone();
+            let request = write(command);
+            async move { control_call(&socket_path, request).await }
+        });
+        let approval_id = loop {
+            let listed = cli(&socket_path, &["approve", "--json"]).await;
+            let listed: serde_json::Value = serde_json::from_slice(&listed.stdout)?;
+            if let Some(id) = listed["pending"][0]["approval_id"].as_str() {
+                assert_eq!(listed["pending"][0]["command"], command.trim_end());
+                break id.to_string();
+            }
+            sleep(Duration::from_millis(50)).await;
+        };
+        let args = if decision == "approve" {
+            vec!["approve", approval_id.as_str()]
+        } else {
+            vec![
+                "approve",
+                approval_id.as_str(),
+                "--reject",
+                "--reason",
+                "freeze",
+            ]
+        };
+        let decided = cli(&socket_path, &args).await;
+        assert!(decided.status.success(), "{decision}: {decided:?}");
+        let result = parked.await?;
+        if decision == "approve" {
+            assert_eq!(result["result"]["bytes_written"], 7);
+        } else {
+            assert_eq!(result["error"]["data"]["error_code"], "POLICY_DENIED");
+        }
+    }
+
+    child.kill().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn audit_log_records_session_actions() -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(async move {
+        if let Ok((socket, _)) = listener.accept().await {
+            let (mut reader, mut writer) = socket.into_split();
+            let _ = tokio::io::copy(&mut reader, &mut writer).await;
+        }
+    });
+
+    unsafe { std::env::set_var("PTYCTL_SECRET_AUDIT_TEST", "hunter2") };
+    let dir = tempfile::tempdir()?;
+    let socket_path = dir.path().join("ptyctl.sock");
+    let audit_path = dir.path().join("audit.jsonl");
+    let audit_arg = audit_path.to_string_lossy().to_string();
+    let mut child = spawn_server(
+        &socket_path,
+        &["--control-mode", "readwrite", "--audit-log", &audit_arg],
+    )
+    .await;
+
+    let call = |id: u64, method: &str, params: serde_json::Value| serde_json::json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+    let opened = control_call(
+        &socket_path,
+        call(
+            1,
+            "ptyctl_session",
+            serde_json::json!({"action": "open", "protocol": "telnet", "host": "127.0.0.1", "port": port, "task_id": "audit"}),
+        ),
+    )
+    .await;
+    let session_id = opened["result"]["session_id"].as_str().expect("session_id");
+    for (id, params) in [
+        (
+            2,
+            serde_json::json!({"action": "write", "session_id": session_id, "data": "password=letmein\n"}),
+        ),
+        (
+            3,
+            serde_json::json!({"action": "write", "session_id": session_id, "data": "username ops password=letmein\n", "sensitive": true}),
+        ),
+        (
+            6,
+            serde_json::json!({"action": "write", "session_id": session_id, "secret_ref": "secret://audit-test"}),
+        ),
+    ] {
+        let written = control_call(&socket_path, call(id, "ptyctl_session_io", params)).await;
+        assert!(written["result"]["bytes_written"].is_number());
+    }
+    control_call(
+        &socket_path,
+        call(4, "ptyctl_session", serde_json::json!({"action": "list"})),
+    )
+    .await;
+    control_call(
+        &socket_path,
+        call(
+            5,
+            "ptyctl_session",
+            serde_json::json!({"action": "close", "session_id": session_id}),
+        ),
+    )
+    .await;
+
+    let log = std::fs::read_to_string(&audit_path)?;
+    assert!(!log.contains("letmein") && !log.contains("hunter2"));
+    let mode =
+        std::os::unix::fs::PermissionsExt::mode(&std::fs::metadata(&audit_path)?.permissions());
+    assert_eq!(mode & 0o777, 0o600);
+    let events: Vec<serde_json::Value> = log
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    let names: Vec<&str> = events
+        .iter()
+        .map(|event| event["event"].as_str().unwrap_or_default())
+        .collect();
+    assert_eq!(names, ["open", "write", "write", "write", "close"]);
+    for event in &events {
+        assert_eq!(event["transport"], "control");
+        assert_eq!(event["outcome"], "ok");
+        assert_eq!(event["session_id"], session_id);
+        assert_eq!(event["host"], "127.0.0.1");
+        assert!(
+            event["client"]
+                .as_str()
+                .unwrap_or_default()
+                .starts_with("uid=")
+        );
+    }
+    assert_eq!(events[0]["task_id"], "audit");
+    assert_eq!(events[2]["command"], "username ops password=[REDACTED]\n");
+    assert_eq!(events[2]["sensitive"], true);
+    assert_eq!(events[3]["command"], "secret_ref=secret://audit-test");
+    assert!(events[3].get("sensitive").is_none());
+
+    child.kill().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn server_info_and_doctor_report_running_server() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let socket_path = dir.path().join("ptyctl.sock");
+    let config_path = dir.path().join("ptyctl.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "[ssh]\nopenssh_path = \"{}\"\n",
+            dir.path().join("missing-ssh").display()
+        ),
+    )?;
+    let mut child = spawn_server(&socket_path, &[]).await;
+
+    let info = control_call(
+        &socket_path,
+        serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "server_info"}),
+    )
+    .await;
+    assert!(
+        !info["result"]["version"]
+            .as_str()
+            .unwrap_or_default()
+            .is_empty()
+    );
+    assert_eq!(info["result"]["sessions"], 0);
+    assert_eq!(info["result"]["config"]["control_mode"], "readonly");
+
+    let config_arg = config_path.to_string_lossy().to_string();
+    let doctor = cli(&socket_path, &["doctor", "--json", "--config", &config_arg]).await;
+    assert!(!doctor.status.success());
+    let checks: Vec<serde_json::Value> = serde_json::from_slice(&doctor.stdout)?;
+    let check = |name: &str| {
+        checks
+            .iter()
+            .find(|check| check["name"] == name)
+            .cloned()
+            .unwrap_or_default()
+    };
+    assert_eq!(check("config")["status"], "ok");
+    assert_eq!(check("ssh")["status"], "fail");
+    assert!(check("ssh")["fix"].as_str().is_some());
+    assert_eq!(check("server")["status"], "ok");
+    assert!(
+        check("server")["detail"]
+            .as_str()
+            .unwrap_or_default()
+            .contains("running (pid")
+    );
+
+    child.kill().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn sigterm_logs_out_sessions_and_removes_socket() -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let (received_tx, received_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        if let Ok((mut socket, _)) = listener.accept().await {
+            let _ = socket.write_all(b"router> ").await;
+            let mut received = Vec::new();
+            let mut buf = [0u8; 256];
+            while let Ok(n) = tokio::io::AsyncReadExt::read(&mut socket, &mut buf).await {
+                if n == 0 {
+                    break;
+                }
+                received.extend_from_slice(&buf[..n]);
+                if received.windows(4).any(|window| window == b"exit") {
+                    break;
+                }
+            }
+            let _ = received_tx.send(received);
+        }
+    });
+
+    let dir = tempfile::tempdir()?;
+    let socket_path = dir.path().join("ptyctl.sock");
+    let audit_path = dir.path().join("audit.jsonl");
+    let config_path = dir.path().join("ptyctl.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "[server.shutdown]\nlogout_commands = [\"exit\"]\ngrace_period_ms = 3000\n\n[logging]\naudit_path = {:?}\n",
+            audit_path.to_string_lossy()
+        ),
+    )?;
+    let config_arg = config_path.to_string_lossy().to_string();
+    let mut child = spawn_server(
+        &socket_path,
+        &["--control-mode", "readwrite", "--config", &config_arg],
+    )
+    .await;
+
+    let opened = control_call(
+        &socket_path,
+        serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "ptyctl_session", "params": {"action": "open", "protocol": "telnet", "host": "127.0.0.1", "port": port}}),
+    )
+    .await;
+    let session_id = opened["result"]["session_id"]
+        .as_str()
+        .expect("session_id")
+        .to_string();
+
+    // `wait` would close stdin, ending the stdio transport before the signal lands.
+    let _stdin = child.stdin.take();
+    let pid = child.id().expect("pid") as libc::pid_t;
+    assert_eq!(unsafe { libc::kill(pid, libc::SIGTERM) }, 0);
+    let status = tokio::time::timeout(Duration::from_secs(10), child.wait()).await??;
+    assert!(status.success(), "exit status: {status:?}");
+    assert!(!socket_path.exists());
+
+    let received = tokio::time::timeout(Duration::from_secs(1), received_rx).await??;
+    assert!(String::from_utf8_lossy(&received).contains("exit"));
+    let log = std::fs::read_to_string(&audit_path)?;
+    let shutdown: serde_json::Value = serde_json::from_str(log.lines().last().expect("event"))?;
+    assert_eq!(shutdown["event"], "close");
+    assert_eq!(shutdown["session_id"], session_id.as_str());
+    assert_eq!(shutdown["command"], "shutdown=logged_out");
+    Ok(())
+}
+
+#[tokio::test]
+async fn reload_applies_live_fields_and_reports_restart_ones()
+-> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let socket_path = dir.path().join("ptyctl.sock");
+    let config_path = dir.path().join("ptyctl.toml");
+    std::fs::write(&config_path, "[session]\nmax_sessions = 5\n")?;
+    let config_arg = config_path.to_string_lossy().to_string();
+    let mut child = spawn_server(
+        &socket_path,
+        &["--control-mode", "readwrite", "--config", &config_arg],
+    )
+    .await;
+    let request = |method: &str| serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": method});
+    let max_sessions = |info: &serde_json::Value| info["result"]["config"]["max_sessions"].clone();
+    let info = control_call(&socket_path, request("server_info")).await;
+    assert_eq!(max_sessions(&info), 5);
+
+    std::fs::write(
+        &config_path,
+        "[server.http]\nlisten = \"127.0.0.1:9\"\n\n[session]\nmax_sessions = 7\n",
+    )?;
+    let reloaded = control_call(&socket_path, request("reload")).await;
+    assert_eq!(
+        reloaded["result"]["applied"],
+        serde_json::json!(["session.max_sessions"])
+    );
+    assert_eq!(
+        reloaded["result"]["restart_required"],
+        serde_json::json!(["server.http.listen"])
+    );
+    let info = control_call(&socket_path, request("server_info")).await;
+    assert_eq!(max_sessions(&info), 7);
+
+    std::fs::write(&config_path, "[session\n")?;
+    let rejected = control_call(&socket_path, request("reload")).await;
+    assert!(rejected["error"].is_object(), "reload: {rejected}");
+    let info = control_call(&socket_path, request("server_info")).await;
+    assert_eq!(max_sessions(&info), 7);
+
+    // The watcher picks up edits without an explicit reload.
+    std::fs::write(&config_path, "[session]\nmax_sessions = 9\n")?;
+    let mut watched = serde_json::Value::Null;
+    for _ in 0..50 {
+        watched = max_sessions(&control_call(&socket_path, request("server_info")).await);
+        if watched == 9 {
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(watched, 9);
+
+    child.kill().await?;
+    Ok(())
+}
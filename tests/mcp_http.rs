@@ -1,8 +1,11 @@
+use ptyctl::tokens::hash_token;
+use rcgen::{BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair};
 use rmcp::model::CallToolRequestParam;
-use rmcp::service::ServiceExt;
+use rmcp::service::{RoleClient, RunningService, ServiceExt};
 use rmcp::transport::StreamableHttpClientTransport;
 use rmcp::transport::streamable_http_client::StreamableHttpClientTransportConfig;
 use std::net::{SocketAddr, TcpListener};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::process::Command;
 use tokio::time::{Duration, Instant, sleep};
@@ -60,6 +63,15 @@ async fn http_supports_initialize_list_tools_and_call() -> Result<(), Box<dyn st
             .expect("session_list should include sessions array");
         assert!(sessions.is_empty(), "session_list should start empty");
 
+        let metrics = http_get(addr, "/metrics").await?;
+        assert!(metrics.starts_with("HTTP/1.1 200"), "metrics: {metrics}");
+        assert!(metrics.contains("ptyctl_bytes_in_total 0\n"));
+        assert!(metrics.contains("# TYPE ptyctl_exec_duration_seconds histogram\n"));
+        let health = http_get(addr, "/healthz").await?;
+        assert!(health.starts_with("HTTP/1.1 200"), "healthz: {health}");
+        let ready = http_get(addr, "/readyz").await?;
+        assert!(ready.contains("\"ready\":true"), "readyz: {ready}");
+
         service.cancel().await?;
         Ok(())
     }
@@ -97,6 +109,16 @@ async fn http_rejects_missing_auth_token() -> Result<(), Box<dyn std::error::Err
         let transport = StreamableHttpClientTransport::from_uri(url.as_str());
         let unauthorized = ().serve(transport).await;
         assert!(unauthorized.is_err(), "expected auth to be required");
+        let health = http_get(addr, "/healthz").await?;
+        assert!(
+            health.starts_with("HTTP/1.1 200"),
+            "probes skip auth: {health}"
+        );
+        let metrics = http_get(addr, "/metrics").await?;
+        assert!(
+            metrics.starts_with("HTTP/1.1 401"),
+            "metrics need auth: {metrics}"
+        );
 
         let transport = StreamableHttpClientTransport::from_config(
             StreamableHttpClientTransportConfig::with_uri(url.as_str()).auth_header(auth_token),
@@ -118,6 +140,381 @@ async fn http_rejects_missing_auth_token() -> Result<(), Box<dyn std::error::Err
     test_result
 }
 
+#[tokio::test]
+async fn http_enforces_scoped_token_permissions() -> Result<(), Box<dyn std::error::Error>> {
+    let port = pick_unused_port();
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let bin = env!("CARGO_BIN_EXE_ptyctl");
+    let dir = tempfile::tempdir()?;
+    let config_path = dir.path().join("ptyctl.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            r#"
+[[server.http.tokens]]
+name = "viewer"
+token_sha256 = "{}"
+
+[[server.http.tokens]]
+name = "lab"
+token_sha256 = "{}"
+mode = "readwrite"
+tools = ["ptyctl_session"]
+hosts = ["lab-*"]
+"#,
+            hash_token("viewer-secret"),
+            hash_token("lab-secret"),
+        ),
+    )?;
+
+    let mut child = Command::new(bin)
+        .arg("serve")
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--transport")
+        .arg("http")
+        .arg("--http-listen")
+        .arg(addr.to_string())
+        .arg("--control-mode")
+        .arg("disabled")
+        .spawn()?;
+
+    let test_result: Result<(), Box<dyn std::error::Error>> = async {
+        wait_for_port(addr).await?;
+        let url = format!("http://{}/mcp", addr);
+        let connect = |token: &str| {
+            StreamableHttpClientTransport::from_config(
+                StreamableHttpClientTransportConfig::with_uri(url.as_str()).auth_header(token),
+            )
+        };
+        let session_call = |arguments: serde_json::Value| CallToolRequestParam {
+            name: "ptyctl_session".into(),
+            arguments: arguments.as_object().cloned(),
+        };
+        let open = serde_json::json!({
+            "action": "open",
+            "protocol": "telnet",
+            "host": "127.0.0.1",
+            "port": 1
+        });
+
+        assert!(().serve(connect("wrong")).await.is_err(), "unknown token");
+
+        let viewer = ().serve(connect("viewer-secret")).await?;
+        let listed = viewer
+            .call_tool(session_call(serde_json::json!({"action": "list"})))
+            .await?;
+        assert!(listed.structured_content.is_some());
+        let denied = viewer.call_tool(session_call(open.clone())).await;
+        assert!(
+            format!("{denied:?}").contains("PERMISSION_DENIED"),
+            "read-only token: {denied:?}"
+        );
+        viewer.cancel().await?;
+
+        let lab = ().serve(connect("lab-secret")).await?;
+        let denied = lab.call_tool(session_call(open)).await;
+        assert!(
+            format!("{denied:?}").contains("may not access host"),
+            "host scope: {denied:?}"
+        );
+        // Every hop is checked before any secret reference is resolved.
+        let denied = lab
+            .call_tool(session_call(serde_json::json!({
+                "action": "open",
+                "protocol": "ssh",
+                "host": "lab-sw1",
+                "auth": {"password_ref": "secret://missing"},
+                "jump_hosts": [{"host": "10.9.9.9", "auth": {"password_ref": "secret://missing"}}]
+            })))
+            .await;
+        assert!(
+            format!("{denied:?}").contains("may not access host 10.9.9.9"),
+            "jump host scope: {denied:?}"
+        );
+        // ssh options that can redirect the connection are off limits too.
+        let denied = lab
+            .call_tool(session_call(serde_json::json!({
+                "action": "open",
+                "protocol": "ssh",
+                "host": "lab-sw1",
+                "ssh_options": {"extra_args": ["-oHostName=10.9.9.9"]}
+            })))
+            .await;
+        assert!(
+            format!("{denied:?}").contains("may not set ssh_options.extra_args"),
+            "ssh option scope: {denied:?}"
+        );
+        let denied = lab
+            .call_tool(CallToolRequestParam {
+                name: "ptyctl_session_io".into(),
+                arguments: serde_json::json!({"action": "read", "session_id": "x"})
+                    .as_object()
+                    .cloned(),
+            })
+            .await;
+        assert!(
+            format!("{denied:?}").contains("may not call"),
+            "tool scope: {denied:?}"
+        );
+        lab.cancel().await?;
+        Ok(())
+    }
+    .await;
+
+    let _ = child.kill().await;
+    let _ = child.wait().await;
+
+    test_result
+}
+
+#[tokio::test]
+async fn https_requires_client_certificate_and_maps_identity()
+-> Result<(), Box<dyn std::error::Error>> {
+    let echo = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let echo_port = echo.local_addr()?.port();
+    tokio::spawn(async move {
+        if let Ok((socket, _)) = echo.accept().await {
+            let (mut reader, mut writer) = socket.into_split();
+            let _ = tokio::io::copy(&mut reader, &mut writer).await;
+        }
+    });
+
+    let dir = tempfile::tempdir()?;
+    let ca_key = KeyPair::generate()?;
+    let mut ca_params = CertificateParams::new(Vec::<String>::new())?;
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    ca_params
+        .distinguished_name
+        .push(DnType::CommonName, "ptyctl test CA");
+    let ca = ca_params.self_signed(&ca_key)?;
+    let server_key = KeyPair::generate()?;
+    let server_cert = CertificateParams::new(vec!["localhost".to_string()])?.signed_by(
+        &server_key,
+        &ca,
+        &ca_key,
+    )?;
+    let client_key = KeyPair::generate()?;
+    let mut client_params = CertificateParams::new(Vec::<String>::new())?;
+    client_params
+        .distinguished_name
+        .push(DnType::CommonName, "alice");
+    client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+    let client_cert = client_params.signed_by(&client_key, &ca, &ca_key)?;
+
+    let write = |name: &str, pem: String| -> std::io::Result<String> {
+        let path = dir.path().join(name);
+        std::fs::write(&path, pem)?;
+        Ok(path.to_string_lossy().to_string())
+    };
+    let ca_path = write("ca.pem", ca.pem())?;
+    let cert_path = write("server.pem", server_cert.pem())?;
+    let key_path = write("server.key", server_key.serialize_pem())?;
+    let audit_path = dir.path().join("audit.jsonl");
+
+    let port = pick_unused_port();
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ptyctl"))
+        .arg("serve")
+        .arg("--transport")
+        .arg("http")
+        .arg("--http-listen")
+        .arg(addr.to_string())
+        .arg("--tls-cert")
+        .arg(&cert_path)
+        .arg("--tls-key")
+        .arg(&key_path)
+        .arg("--client-ca")
+        .arg(&ca_path)
+        .arg("--audit-log")
+        .arg(&audit_path)
+        .arg("--control-mode")
+        .arg("disabled")
+        .spawn()?;
+
+    let test_result: Result<(), Box<dyn std::error::Error>> = async {
+        wait_for_port(addr).await?;
+        let base = format!("https://localhost:{port}");
+        let root = reqwest::Certificate::from_pem(ca.pem().as_bytes())?;
+
+        let anonymous = reqwest::Client::builder()
+            .add_root_certificate(root.clone())
+            .build()?;
+        assert!(
+            anonymous
+                .get(format!("{base}/healthz"))
+                .send()
+                .await
+                .is_err(),
+            "handshake without a client certificate should fail"
+        );
+
+        let identity = reqwest::Identity::from_pem(
+            format!("{}{}", client_cert.pem(), client_key.serialize_pem()).as_bytes(),
+        )?;
+        let client = reqwest::Client::builder()
+            .add_root_certificate(root)
+            .identity(identity)
+            .build()?;
+        let health = client.get(format!("{base}/healthz")).send().await?;
+        assert_eq!(health.status(), 200);
+
+        let transport = StreamableHttpClientTransport::with_client(
+            client,
+            StreamableHttpClientTransportConfig::with_uri(format!("{base}/mcp")),
+        );
+        let service = ().serve(transport).await?;
+        let opened = service
+            .call_tool(CallToolRequestParam {
+                name: "ptyctl_session".into(),
+                arguments: serde_json::json!({
+                    "action": "open",
+                    "protocol": "telnet",
+                    "host": "127.0.0.1",
+                    "port": echo_port,
+                    "acquire_lock": true
+                })
+                .as_object()
+                .cloned(),
+            })
+            .await?
+            .structured_content
+            .expect("open should return structured content");
+        assert_eq!(opened["lock_acquired"], true);
+        let listed = service
+            .call_tool(CallToolRequestParam {
+                name: "ptyctl_session".into(),
+                arguments: serde_json::json!({"action": "list"}).as_object().cloned(),
+            })
+            .await?
+            .structured_content
+            .expect("list should return structured content");
+        let session = &listed["sessions"][0];
+        assert_eq!(session["opened_by"], "alice");
+        assert_eq!(session["lock_holder"], "alice");
+        service.cancel().await?;
+
+        let log = std::fs::read_to_string(&audit_path)?;
+        let open: serde_json::Value = serde_json::from_str(log.lines().next().unwrap_or("{}"))?;
+        assert_eq!(open["client"], "cert:alice");
+        assert_eq!(open["task_id"], "alice");
+        Ok(())
+    }
+    .await;
+
+    let _ = child.kill().await;
+    let _ = child.wait().await;
+
+    test_result
+}
+
+#[tokio::test]
+async fn http_clients_only_see_their_own_sessions() -> Result<(), Box<dyn std::error::Error>> {
+    let echo = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let echo_port = echo.local_addr()?.port();
+    tokio::spawn(async move {
+        if let Ok((socket, _)) = echo.accept().await {
+            let (mut reader, mut writer) = socket.into_split();
+            let _ = tokio::io::copy(&mut reader, &mut writer).await;
+        }
+    });
+
+    let port = pick_unused_port();
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ptyctl"))
+        .arg("serve")
+        .arg("--transport")
+        .arg("http")
+        .arg("--http-listen")
+        .arg(addr.to_string())
+        .arg("--control-mode")
+        .arg("disabled")
+        .spawn()?;
+
+    let test_result: Result<(), Box<dyn std::error::Error>> = async {
+        wait_for_port(addr).await?;
+        let url = format!("http://{}/mcp", addr);
+        let alice = ().serve(StreamableHttpClientTransport::from_uri(url.as_str())).await?;
+        let bob = ().serve(StreamableHttpClientTransport::from_uri(url.as_str())).await?;
+
+        let opened = session_call(
+            &alice,
+            serde_json::json!({"action": "open", "protocol": "telnet", "host": "127.0.0.1", "port": echo_port}),
+        )
+        .await?;
+        let session_id = opened["session_id"].as_str().unwrap_or_default().to_string();
+        let alice_list = session_call(&alice, serde_json::json!({"action": "list"})).await?;
+        assert_eq!(alice_list["sessions"].as_array().map(Vec::len), Some(1));
+        let bob_list = session_call(&bob, serde_json::json!({"action": "list"})).await?;
+        assert_eq!(bob_list["sessions"].as_array().map(Vec::len), Some(0));
+        let bob_id = bob_list["client_id"].as_str().unwrap_or_default().to_string();
+        assert!(bob_id.starts_with("mcp:"), "client_id: {bob_list}");
+
+        let denied = session_call(
+            &bob,
+            serde_json::json!({"action": "close", "session_id": session_id}),
+        )
+        .await;
+        assert!(
+            format!("{denied:?}").contains("belongs to another client"),
+            "{denied:?}"
+        );
+
+        session_call(
+            &alice,
+            serde_json::json!({"action": "share", "session_id": session_id, "client_id": bob_id}),
+        )
+        .await?;
+        let bob_list = session_call(&bob, serde_json::json!({"action": "list"})).await?;
+        assert_eq!(bob_list["sessions"][0]["shared_with"][0], bob_id.as_str());
+        let denied = session_call(
+            &bob,
+            serde_json::json!({"action": "close", "session_id": session_id}),
+        )
+        .await;
+        assert!(
+            format!("{denied:?}").contains("Only the owner"),
+            "{denied:?}"
+        );
+
+        session_call(
+            &alice,
+            serde_json::json!({"action": "transfer", "session_id": session_id, "client_id": bob_id}),
+        )
+        .await?;
+        let alice_list = session_call(&alice, serde_json::json!({"action": "list"})).await?;
+        assert_eq!(alice_list["sessions"].as_array().map(Vec::len), Some(0));
+        session_call(
+            &bob,
+            serde_json::json!({"action": "close", "session_id": session_id}),
+        )
+        .await?;
+
+        alice.cancel().await?;
+        bob.cancel().await?;
+        Ok(())
+    }
+    .await;
+
+    let _ = child.kill().await;
+    let _ = child.wait().await;
+
+    test_result
+}
+
+async fn session_call(
+    service: &RunningService<RoleClient, ()>,
+    arguments: serde_json::Value,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let result = service
+        .call_tool(CallToolRequestParam {
+            name: "ptyctl_session".into(),
+            arguments: arguments.as_object().cloned(),
+        })
+        .await?;
+    Ok(result.structured_content.unwrap_or_default())
+}
+
 fn pick_unused_port() -> u16 {
     let listener = TcpListener::bind("127.0.0.1:0").expect("bind to ephemeral port");
     listener.local_addr().expect("get local addr").port()
@@ -135,3 +532,12 @@ async fn wait_for_port(addr: SocketAddr) -> Result<(), Box<dyn std::error::Error
         sleep(Duration::from_millis(100)).await;
     }
 }
+
+async fn http_get(addr: SocketAddr, path: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let request = format!("GET {path} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
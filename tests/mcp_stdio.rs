@@ -103,3 +103,444 @@ async fn stdio_reports_not_found_for_unknown_session() -> Result<(), Box<dyn std
     let _ = service.cancel().await;
     outcome
 }
+
+struct ResourceUpdates(tokio::sync::mpsc::UnboundedSender<String>);
+
+impl rmcp::ClientHandler for ResourceUpdates {
+    async fn on_resource_updated(
+        &self,
+        params: rmcp::model::ResourceUpdatedNotificationParam,
+        _context: rmcp::service::NotificationContext<rmcp::RoleClient>,
+    ) {
+        let _ = self.0.send(params.uri);
+    }
+}
+
+#[tokio::test]
+async fn stdio_publishes_session_resources() -> Result<(), Box<dyn std::error::Error>> {
+    use rmcp::model::{ReadResourceRequestParam, ResourceContents, SubscribeRequestParam};
+    use tokio::io::AsyncWriteExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let (send_more, more) = tokio::sync::oneshot::channel::<()>();
+    tokio::spawn(async move {
+        if let Ok((mut socket, _)) = listener.accept().await {
+            let _ = socket.write_all(b"banner\r\nrouter> ").await;
+            let _ = more.await;
+            let _ = socket.write_all(b"more output\r\n").await;
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        }
+    });
+
+    let bin = env!("CARGO_BIN_EXE_ptyctl");
+    let transport = TokioChildProcess::new(Command::new(bin).configure(|cmd| {
+        cmd.arg("serve")
+            .arg("--transport")
+            .arg("stdio")
+            .arg("--control-mode")
+            .arg("disabled");
+    }))?;
+    let (updates_tx, mut updates) = tokio::sync::mpsc::unbounded_channel();
+    let service = ResourceUpdates(updates_tx).serve(transport).await?;
+    let info = service.peer_info().expect("server info");
+    let resources_capability = info.capabilities.resources.clone().expect("resources");
+    assert_eq!(resources_capability.subscribe, Some(true));
+
+    let opened = service
+        .call_tool(CallToolRequestParam {
+            name: "ptyctl_session".into(),
+            arguments: serde_json::json!({
+                "action": "open",
+                "protocol": "telnet",
+                "host": "127.0.0.1",
+                "port": port
+            })
+            .as_object()
+            .cloned(),
+        })
+        .await?;
+    let session_id = opened.structured_content.expect("open result")["session_id"]
+        .as_str()
+        .expect("session_id")
+        .to_string();
+    let transcript_uri = format!("ptyctl://sessions/{session_id}/transcript");
+
+    let resources = service.list_resources(None).await?;
+    let uris: Vec<&str> = resources.resources.iter().map(|r| r.uri.as_str()).collect();
+    assert!(uris.contains(&transcript_uri.as_str()), "uris: {uris:?}");
+    assert!(uris.contains(&format!("ptyctl://sessions/{session_id}/screen").as_str()));
+    let templates = service.list_resource_templates(None).await?;
+    assert_eq!(templates.resource_templates.len(), 3);
+
+    let mut transcript = String::new();
+    for _ in 0..20 {
+        let read = service
+            .read_resource(ReadResourceRequestParam {
+                uri: transcript_uri.clone(),
+            })
+            .await?;
+        if let Some(ResourceContents::TextResourceContents { text, .. }) = read.contents.first() {
+            transcript = text.clone();
+        }
+        if transcript.contains("router> ") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(transcript.contains("banner"), "transcript: {transcript:?}");
+
+    service
+        .subscribe(SubscribeRequestParam {
+            uri: transcript_uri.clone(),
+        })
+        .await?;
+    let _ = send_more.send(());
+    let updated = tokio::time::timeout(std::time::Duration::from_secs(5), updates.recv()).await?;
+    assert_eq!(updated.as_deref(), Some(transcript_uri.as_str()));
+
+    let _ = service.cancel().await;
+    Ok(())
+}
+
+struct SecretProvider;
+
+impl rmcp::ClientHandler for SecretProvider {
+    async fn create_elicitation(
+        &self,
+        request: rmcp::model::CreateElicitationRequestParam,
+        _context: rmcp::service::RequestContext<rmcp::RoleClient>,
+    ) -> Result<rmcp::model::CreateElicitationResult, rmcp::ErrorData> {
+        assert!(request.message.contains("Password:"), "{}", request.message);
+        Ok(rmcp::model::CreateElicitationResult {
+            action: rmcp::model::ElicitationAction::Accept,
+            content: Some(serde_json::json!({ "value": "hunter2" })),
+        })
+    }
+
+    fn get_info(&self) -> rmcp::model::ClientInfo {
+        let mut info = rmcp::model::ClientInfo::default();
+        info.capabilities.elicitation = Some(rmcp::model::ElicitationCapability::default());
+        info
+    }
+}
+
+#[tokio::test]
+async fn stdio_elicits_secret_without_exposing_it() -> Result<(), Box<dyn std::error::Error>> {
+    let bin = env!("CARGO_BIN_EXE_ptyctl");
+    let transport = TokioChildProcess::new(Command::new(bin).configure(|cmd| {
+        cmd.arg("serve")
+            .arg("--transport")
+            .arg("stdio")
+            .arg("--control-mode")
+            .arg("disabled");
+    }))?;
+    elicits_secret_without_exposing_it(transport).await
+}
+
+#[tokio::test]
+async fn mcp_proxy_relays_elicitation() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let socket_path = dir.path().join("ptyctl.sock");
+    let bin = env!("CARGO_BIN_EXE_ptyctl");
+    let transport = TokioChildProcess::new(Command::new(bin).configure(|cmd| {
+        cmd.arg("mcp").arg("--control-socket").arg(&socket_path);
+    }))?;
+    let result = elicits_secret_without_exposing_it(transport).await;
+    stop_daemon(&socket_path).await?;
+    result
+}
+
+async fn elicits_secret_without_exposing_it(
+    transport: TokioChildProcess,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let (received_tx, received_rx) = tokio::sync::oneshot::channel::<String>();
+    tokio::spawn(async move {
+        if let Ok((mut socket, _)) = listener.accept().await {
+            let _ = socket.write_all(b"Password: ").await;
+            let mut received = Vec::new();
+            let mut buf = [0u8; 256];
+            while !received.iter().any(|byte| matches!(byte, b'\r' | b'\n')) {
+                match socket.read(&mut buf).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => received.extend_from_slice(&buf[..n]),
+                }
+            }
+            let _ = received_tx.send(String::from_utf8_lossy(&received).into_owned());
+        }
+    });
+
+    let service = SecretProvider.serve(transport).await?;
+    let opened = service
+        .call_tool(CallToolRequestParam {
+            name: "ptyctl_session".into(),
+            arguments: serde_json::json!({
+                "action": "open",
+                "protocol": "telnet",
+                "host": "127.0.0.1",
+                "port": port
+            })
+            .as_object()
+            .cloned(),
+        })
+        .await?;
+    let session_id = opened.structured_content.expect("open result")["session_id"]
+        .as_str()
+        .expect("session_id")
+        .to_string();
+    service
+        .call_tool(CallToolRequestParam {
+            name: "ptyctl_session_io".into(),
+            arguments: serde_json::json!({
+                "action": "read",
+                "session_id": session_id,
+                "cursor": "0",
+                "until_regex": "Password:",
+                "timeout_ms": 3000
+            })
+            .as_object()
+            .cloned(),
+        })
+        .await?;
+
+    let written = service
+        .call_tool(CallToolRequestParam {
+            name: "ptyctl_session_io".into(),
+            arguments: serde_json::json!({
+                "action": "write",
+                "session_id": session_id,
+                "elicit": {"kind": "secret"}
+            })
+            .as_object()
+            .cloned(),
+        })
+        .await?;
+    let result = serde_json::to_string(&written)?;
+    assert!(!result.contains("hunter2"), "secret leaked: {result}");
+    assert_eq!(
+        written.structured_content.expect("write result")["elicitation"],
+        "accepted"
+    );
+    let received = tokio::time::timeout(std::time::Duration::from_secs(5), received_rx).await??;
+    assert!(received.starts_with("hunter2"), "received: {received:?}");
+
+    let _ = service.cancel().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn stdio_refuses_forced_lock_takeover() -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::AsyncWriteExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(async move {
+        if let Ok((mut socket, _)) = listener.accept().await {
+            let _ = socket.write_all(b"router> ").await;
+            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        }
+    });
+
+    let bin = env!("CARGO_BIN_EXE_ptyctl");
+    let transport = TokioChildProcess::new(Command::new(bin).configure(|cmd| {
+        cmd.arg("serve")
+            .arg("--transport")
+            .arg("stdio")
+            .arg("--control-mode")
+            .arg("disabled");
+    }))?;
+    let service = ().serve(transport).await?;
+    let call = |arguments: serde_json::Value| CallToolRequestParam {
+        name: "ptyctl_session".into(),
+        arguments: arguments.as_object().cloned(),
+    };
+
+    let opened = service
+        .call_tool(call(
+            serde_json::json!({"action": "open", "protocol": "telnet", "host": "127.0.0.1", "port": port}),
+        ))
+        .await?;
+    let session_id = opened.structured_content.expect("open result")["session_id"]
+        .as_str()
+        .expect("session_id")
+        .to_string();
+    service
+        .call_tool(call(
+            serde_json::json!({"action": "lock", "session_id": session_id, "task_id": "human:ops"}),
+        ))
+        .await?;
+    let forced = service
+        .call_tool(call(
+            serde_json::json!({"action": "lock", "session_id": session_id, "task_id": "agent", "force": true}),
+        ))
+        .await;
+    match forced {
+        Err(ServiceError::McpError(err)) => {
+            assert!(err.message.contains("control socket"), "{err:?}")
+        }
+        other => panic!("forced lock takeover should be refused: {other:?}"),
+    }
+
+    let _ = service.cancel().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn mcp_proxy_sessions_survive_client_restart() -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(async move {
+        if let Ok((mut socket, _)) = listener.accept().await {
+            let _ = socket.write_all(b"router> ").await;
+            let mut buf = [0u8; 256];
+            while let Ok(n) = socket.read(&mut buf).await {
+                if n == 0 || socket.write_all(&buf[..n]).await.is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let dir = tempfile::tempdir()?;
+    let socket_path = dir.path().join("ptyctl.sock");
+    let bin = env!("CARGO_BIN_EXE_ptyctl");
+    let proxy = |client_id: &str| {
+        TokioChildProcess::new(Command::new(bin).configure(|cmd| {
+            cmd.arg("mcp")
+                .arg("--control-socket")
+                .arg(&socket_path)
+                .env("PTYCTL_CLIENT_ID", client_id);
+        }))
+    };
+    let call = |name: &'static str, arguments: serde_json::Value| CallToolRequestParam {
+        name: name.into(),
+        arguments: arguments.as_object().cloned(),
+    };
+
+    let first = ().serve(proxy("agent-a")?).await?;
+    let opened = first
+        .call_tool(call(
+            "ptyctl_session",
+            serde_json::json!({"action": "open", "protocol": "telnet", "host": "127.0.0.1", "port": port}),
+        ))
+        .await?;
+    let session_id = opened.structured_content.expect("open result")["session_id"]
+        .as_str()
+        .expect("session_id")
+        .to_string();
+    first
+        .call_tool(call(
+            "ptyctl_session_io",
+            serde_json::json!({"action": "write", "session_id": session_id, "data": "hello\n"}),
+        ))
+        .await?;
+    let elicit = first
+        .call_tool(call(
+            "ptyctl_session_io",
+            serde_json::json!({"action": "write", "session_id": session_id, "elicit": {"kind": "secret"}}),
+        ))
+        .await;
+    assert!(
+        matches!(elicit, Err(ServiceError::McpError(_))),
+        "elicit: {elicit:?}"
+    );
+    first.cancel().await?;
+
+    let other = ().serve(proxy("agent-b")?).await?;
+    let listed = other
+        .call_tool(call(
+            "ptyctl_session",
+            serde_json::json!({"action": "list"}),
+        ))
+        .await?;
+    let listed = listed.structured_content.expect("list result");
+    assert_eq!(listed["sessions"], serde_json::json!([]));
+    let denied = other
+        .call_tool(call(
+            "ptyctl_session_io",
+            serde_json::json!({"action": "read", "session_id": session_id, "cursor": "0"}),
+        ))
+        .await;
+    assert!(
+        matches!(denied, Err(ServiceError::McpError(_))),
+        "read: {denied:?}"
+    );
+    other.cancel().await?;
+
+    let second = ().serve(proxy("agent-a")?).await?;
+    let listed = second
+        .call_tool(call(
+            "ptyctl_session",
+            serde_json::json!({"action": "list"}),
+        ))
+        .await?;
+    let listed = listed.structured_content.expect("list result");
+    assert_eq!(listed["sessions"][0]["session_id"], session_id.as_str());
+    let read = second
+        .call_tool(call(
+            "ptyctl_session_io",
+            serde_json::json!({"action": "read", "session_id": session_id, "cursor": "0", "until_regex": "hello", "timeout_ms": 3000}),
+        ))
+        .await?;
+    assert_eq!(
+        read.structured_content.expect("read result")["matched"],
+        true
+    );
+    second.cancel().await?;
+    stop_daemon(&socket_path).await?;
+
+    let log = std::fs::read_to_string(dir.path().join("ptyctl.log"))?;
+    assert!(log.contains("Shutdown complete"), "daemon log: {log:?}");
+    Ok(())
+}
+
+#[tokio::test]
+async fn daemon_requires_readwrite_control_mode() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let output = Command::new(env!("CARGO_BIN_EXE_ptyctl"))
+        .arg("daemon")
+        .arg("--control-socket")
+        .arg(dir.path().join("ptyctl.sock"))
+        .arg("--control-mode")
+        .arg("readonly")
+        .output()
+        .await?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("readwrite mode"), "stderr: {stderr}");
+    assert!(!dir.path().join("ptyctl.sock").exists());
+    Ok(())
+}
+
+/// Stops the daemon a `ptyctl mcp` test started and waits for its socket to go.
+async fn stop_daemon(socket_path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let stream = tokio::net::UnixStream::connect(socket_path).await?;
+    let (reader, mut writer) = stream.into_split();
+    writer
+        .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"server_info\"}\n")
+        .await?;
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .expect("reply");
+    let info: serde_json::Value = serde_json::from_str(&line)?;
+    let pid = info["result"]["pid"].as_i64().expect("pid") as libc::pid_t;
+    assert_eq!(unsafe { libc::kill(pid, libc::SIGTERM) }, 0);
+    for _ in 0..100 {
+        if !socket_path.exists() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(!socket_path.exists(), "daemon left its socket behind");
+    Ok(())
+}
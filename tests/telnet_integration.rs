@@ -1,7 +1,7 @@
-use ptyctl::config::{SessionConfig, SshConfig, TelnetLineEnding};
+use ptyctl::config::{SessionConfig, SshConfig, TelnetConfig};
 use ptyctl::session::{
-    ExpectConfig, Protocol, PtyOptions, ReadParams, SessionManager, SessionOpenRequest, Timeouts,
-    read_from_session,
+    ExpectConfig, LoginStep, Protocol, PtyOptions, ReadParams, ReconnectPolicy, SessionManager,
+    SessionOpenRequest, Timeouts, read_from_session,
 };
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpListener;
@@ -49,7 +49,7 @@ async fn telnet_negotiation_and_read() {
     let manager = SessionManager::new(
         SessionConfig::default(),
         SshConfig::default(),
-        TelnetLineEnding::Cr,
+        TelnetConfig::default(),
     );
     let open = manager
         .open_session(SessionOpenRequest {
@@ -67,14 +67,21 @@ async fn telnet_negotiation_and_read() {
             timeouts: Some(Timeouts {
                 connect_timeout_ms: Some(5_000),
                 idle_timeout_ms: None,
+                queue_timeout_ms: None,
             }),
             ssh_options: None,
+            jump_hosts: None,
+            reconnect: None,
             expect: Some(ExpectConfig::default()),
             session_type: None,
             device_id: None,
             acquire_lock: None,
             lock_ttl_ms: None,
             task_id: None,
+            profile: None,
+            opened_by: None,
+            owner: None,
+            token_limit: None,
         })
         .await
         .unwrap();
@@ -101,3 +108,112 @@ async fn telnet_negotiation_and_read() {
     assert!(received.windows(3).any(|w| w == [IAC, WILL, OPT_TTYPE]));
     assert!(received.windows(3).any(|w| w == [IAC, WILL, OPT_NAWS]));
 }
+
+#[tokio::test]
+async fn telnet_reconnects_under_same_session_id() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server_task = tokio::spawn(async move {
+        let (mut first, _) = listener.accept().await.unwrap();
+        first.write_all(b"first\n").await.unwrap();
+        drop(first);
+
+        let (mut second, _) = listener.accept().await.unwrap();
+        second.write_all(b"Username: ").await.unwrap();
+        let mut received = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while Instant::now() < deadline && !received.windows(5).any(|w| w == b"admin") {
+            let mut buf = [0u8; 128];
+            match timeout(Duration::from_millis(100), second.read(&mut buf)).await {
+                Ok(Ok(0)) | Ok(Err(_)) => break,
+                Ok(Ok(n)) => received.extend_from_slice(&buf[..n]),
+                Err(_) => {}
+            }
+        }
+        second.write_all(b"welcome\n").await.unwrap();
+        let mut buf = [0u8; 128];
+        let _ = timeout(Duration::from_secs(2), second.read(&mut buf)).await;
+        received
+    });
+
+    let manager = SessionManager::new(
+        SessionConfig::default(),
+        SshConfig::default(),
+        TelnetConfig::default(),
+    );
+    let open = manager
+        .open_session(SessionOpenRequest {
+            protocol: Protocol::Telnet,
+            host: "127.0.0.1".to_string(),
+            port: Some(addr.port()),
+            username: None,
+            auth: None,
+            pty: None,
+            timeouts: None,
+            ssh_options: None,
+            jump_hosts: None,
+            reconnect: Some(ReconnectPolicy {
+                max_attempts: Some(2),
+                backoff_ms: Some(50),
+                max_backoff_ms: None,
+                login_script: Some(vec![LoginStep {
+                    expect_regex: Some("Username:".to_string()),
+                    send: "admin\n".to_string(),
+                    sensitive: None,
+                    timeout_ms: Some(2_000),
+                }]),
+            }),
+            expect: None,
+            session_type: None,
+            device_id: None,
+            acquire_lock: None,
+            lock_ttl_ms: None,
+            task_id: None,
+            profile: None,
+            opened_by: None,
+            owner: None,
+            token_limit: None,
+        })
+        .await
+        .unwrap();
+
+    let session = manager.get_session(&open.session_id).await.unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut output = Vec::new();
+    let mut cursor = 0;
+    while Instant::now() < deadline && !output.windows(7).any(|w| w == b"welcome") {
+        let read = read_from_session(
+            &session,
+            ReadParams {
+                cursor: Some(cursor),
+                timeout_ms: 500,
+                max_bytes: 1024,
+                until_regex: None,
+                include_match: true,
+                until_idle_ms: None,
+                input_hints: None,
+            },
+        )
+        .await
+        .unwrap();
+        output.extend_from_slice(&read.slice.bytes);
+        cursor = read.next_cursor;
+    }
+
+    let text = String::from_utf8_lossy(&output);
+    assert!(text.starts_with("first\n"), "unexpected output: {text}");
+    assert!(
+        text.contains("[ptyctl] reconnected"),
+        "missing marker: {text}"
+    );
+    assert!(text.contains("welcome"), "missing replayed output: {text}");
+
+    let received = server_task.await.unwrap();
+    assert!(received.windows(5).any(|w| w == b"admin"));
+
+    let list = manager.list_sessions().await;
+    assert_eq!(list.sessions.len(), 1);
+    assert_eq!(list.sessions[0].session_id, open.session_id);
+    assert_eq!(list.sessions[0].reconnects, 1);
+}